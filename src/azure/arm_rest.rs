@@ -0,0 +1,781 @@
+use super::{
+    az_cli_path, get_az_cli_error, AccessToken, AzCliError, AzErrorDetail, AzMonitorLog,
+    AzureBackend, TokenCredential, WhatIfResult,
+};
+use crate::containerapps::ApiVersion;
+use anyhow::{anyhow, Error, Result};
+use log::{debug, trace};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::sync::{Mutex, RwLock};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const RESOURCE_MANAGER_ENDPOINT: &str = "https://management.azure.com";
+const RESOURCE_MANAGER_RESOURCE: &str = "https://management.azure.com/";
+const AUTHORITY: &str = "https://login.microsoftonline.com";
+const LOG_ANALYTICS_ENDPOINT: &str = "https://api.loganalytics.io";
+const LOG_ANALYTICS_RESOURCE: &str = "https://api.loganalytics.io";
+const DEPLOYMENTS_API_VERSION: &str = "2021-04-01";
+const SUBSCRIPTIONS_API_VERSION: &str = "2020-01-01";
+
+/// Talks to Azure Resource Manager over REST.  Long running deployments
+/// and what-if operations are polled until they finish.
+pub struct ArmRestBackend {
+    credential: TokenCredential,
+    agent: ureq::Agent,
+    endpoint: String,
+    authority: String,
+    log_analytics_endpoint: String,
+    poll_interval: Duration,
+    poll_timeout: Duration,
+    subscription: RwLock<Option<String>>,
+    tokens: Mutex<HashMap<String, AccessToken>>,
+}
+
+struct ArmResponse {
+    status: u16,
+    location: Option<String>,
+    body: Value,
+}
+
+impl ArmRestBackend {
+    pub fn new(credential: TokenCredential) -> Self {
+        ArmRestBackend {
+            credential,
+            agent: ureq::AgentBuilder::new().build(),
+            endpoint: RESOURCE_MANAGER_ENDPOINT.to_owned(),
+            authority: AUTHORITY.to_owned(),
+            log_analytics_endpoint: LOG_ANALYTICS_ENDPOINT.to_owned(),
+            poll_interval: Duration::from_secs(5),
+            poll_timeout: Duration::from_secs(60 * 60),
+            subscription: RwLock::new(env::var("AZURE_SUBSCRIPTION_ID").ok()),
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim_end_matches('/').to_owned();
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_authority(mut self, authority: &str) -> Self {
+        self.authority = authority.trim_end_matches('/').to_owned();
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_log_analytics_endpoint(mut self, log_analytics_endpoint: &str) -> Self {
+        self.log_analytics_endpoint = log_analytics_endpoint.trim_end_matches('/').to_owned();
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// How long to wait for a deployment or what-if to finish.
+    #[allow(dead_code)]
+    pub fn with_poll_timeout(mut self, poll_timeout: Duration) -> Self {
+        self.poll_timeout = poll_timeout;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_subscription(self, subscription_id: &str) -> Self {
+        *self.subscription.write().unwrap() = Some(subscription_id.to_owned());
+        self
+    }
+
+    fn token(&self, resource: &str) -> Result<String> {
+        let mut tokens = self.tokens.lock().unwrap();
+        if let Some(token) = tokens.get(resource).filter(|t| t.is_valid()) {
+            return Ok(token.token.to_owned());
+        }
+        let token = self
+            .credential
+            .get_token(&self.agent, &self.authority, resource)?;
+        if let Some(subscription) = token.subscription.as_ref() {
+            let mut current = self.subscription.write().unwrap();
+            if current.is_none() {
+                debug!("Using the az CLI default subscription {}", subscription);
+                *current = Some(subscription.to_owned());
+            }
+        }
+        let value = token.token.to_owned();
+        tokens.insert(resource.to_owned(), token);
+        Ok(value)
+    }
+
+    /// The target subscription.  Falls back to the only subscription the
+    /// credential can see.
+    fn subscription_id(&self) -> Result<String> {
+        if let Some(subscription) = self.subscription.read().unwrap().as_ref() {
+            return Ok(subscription.to_owned());
+        }
+        // The az CLI token carries the default subscription.
+        self.token(RESOURCE_MANAGER_RESOURCE)?;
+        if let Some(subscription) = self.subscription.read().unwrap().as_ref() {
+            return Ok(subscription.to_owned());
+        }
+        let subscriptions = self.list_subscriptions()?;
+        match subscriptions.as_slice() {
+            [(id, _)] => {
+                *self.subscription.write().unwrap() = Some(id.to_owned());
+                Ok(id.to_owned())
+            }
+            _ => Err(anyhow!(
+                "Unable to pick a subscription, set AZURE_SUBSCRIPTION_ID or pass --subscription-name"
+            )),
+        }
+    }
+
+    /// Subscription ids and names.
+    fn list_subscriptions(&self) -> Result<Vec<(String, String)>> {
+        let url = format!(
+            "{}/subscriptions?api-version={}",
+            self.endpoint, SUBSCRIPTIONS_API_VERSION
+        );
+        let response = self.send_checked("GET", &url, None)?;
+        Ok(response.body["value"]
+            .as_array()
+            .map(|subscriptions| {
+                subscriptions
+                    .iter()
+                    .filter_map(|s| {
+                        Some((
+                            s["subscriptionId"].as_str()?.to_owned(),
+                            s["displayName"].as_str().unwrap_or_default().to_owned(),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn send(&self, method: &str, url: &str, body: Option<&Value>) -> Result<ArmResponse> {
+        let resource = if url.starts_with(&self.log_analytics_endpoint) {
+            LOG_ANALYTICS_RESOURCE
+        } else {
+            RESOURCE_MANAGER_RESOURCE
+        };
+        let token = self.token(resource)?;
+        debug!("\t`{} {}`", method, url);
+        let request = self
+            .agent
+            .request(method, url)
+            .set("Authorization", &format!("Bearer {}", token));
+        let result = match body {
+            Some(body) => request.send_json(body.clone()),
+            None => request.call(),
+        };
+        let response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(Error::new(e)),
+        };
+        let status = response.status();
+        let location = response.header("Location").map(|l| l.to_owned());
+        let text = response.into_string()?;
+        trace!("{} {} returned {}: {}", method, url, status, &text);
+        let body = if text.trim().is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text)?
+        };
+        Ok(ArmResponse {
+            status,
+            location,
+            body,
+        })
+    }
+
+    /// Like `send`, but turns error responses into errors.
+    fn send_checked(&self, method: &str, url: &str, body: Option<&Value>) -> Result<ArmResponse> {
+        check_response(method, url, self.send(method, url, body)?)
+    }
+
+    fn deployment_url(&self, resource_group: Option<&str>, template_path: &Path) -> Result<String> {
+        let scope = match resource_group {
+            Some(resource_group) => format!(
+                "/subscriptions/{}/resourcegroups/{}",
+                self.subscription_id()?,
+                resource_group
+            ),
+            None => format!("/subscriptions/{}", self.subscription_id()?),
+        };
+        Ok(format!(
+            "{}{}/providers/Microsoft.Resources/deployments/{}",
+            self.endpoint,
+            scope,
+            get_deployment_name(template_path)
+        ))
+    }
+
+    fn deploy(
+        &self,
+        deployment_url: &str,
+        location: Option<&str>,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<Value> {
+        let url = format!("{}?api-version={}", deployment_url, DEPLOYMENTS_API_VERSION);
+        let body = get_deployment_body(location, template_path, parameters)?;
+        let mut deployment = self.send_checked("PUT", &url, Some(&body))?.body;
+        let started = Instant::now();
+        loop {
+            match deployment["properties"]["provisioningState"].as_str() {
+                Some("Succeeded") | Some("Failed") | Some("Canceled") => return Ok(deployment),
+                state => trace!("Deployment is {:?}", state),
+            }
+            self.wait_to_poll(started, "deployment", deployment_url)?;
+            deployment = self.send_checked("GET", &url, None)?.body;
+        }
+    }
+
+    /// Sleeps before polling again, or fails once the operation has run
+    /// longer than the poll timeout.
+    fn wait_to_poll(&self, started: Instant, operation: &str, url: &str) -> Result<()> {
+        if started.elapsed() + self.poll_interval > self.poll_timeout {
+            return Err(anyhow!(
+                "The {} {} did not finish within {} seconds",
+                operation,
+                url,
+                self.poll_timeout.as_secs()
+            ));
+        }
+        sleep(self.poll_interval);
+        Ok(())
+    }
+
+    fn what_if(
+        &self,
+        deployment_url: &str,
+        location: Option<&str>,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<WhatIfResult> {
+        let url = format!(
+            "{}/whatIf?api-version={}",
+            deployment_url, DEPLOYMENTS_API_VERSION
+        );
+        let body = get_deployment_body(location, template_path, parameters)?;
+        let mut response = self.send_checked("POST", &url, Some(&body))?;
+        let started = Instant::now();
+        while response.status == 202 {
+            let location = response
+                .location
+                .ok_or_else(|| anyhow!("The what-if operation did not return a location"))?;
+            self.wait_to_poll(started, "what-if", deployment_url)?;
+            response = self.send_checked("GET", &location, None)?;
+        }
+        // The REST API nests the changes under properties, az flattens them.
+        let result = json!({
+            "status": response.body["status"],
+            "error": response.body["error"],
+            "changes": response.body["properties"]["changes"],
+        });
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+impl AzureBackend for ArmRestBackend {
+    fn ensure_available(&self) -> Result<()> {
+        if matches!(self.credential, TokenCredential::AzureCli) {
+            az_cli_path()?;
+        }
+        Ok(())
+    }
+
+    fn set_subscription(&self, subscription: &str) -> Result<()> {
+        let subscriptions = self.list_subscriptions()?;
+        let (id, name) = subscriptions
+            .iter()
+            .find(|(id, name)| id == subscription || name.eq_ignore_ascii_case(subscription))
+            .ok_or_else(|| anyhow!("Unable to find the subscription {}", subscription))?;
+        eprintln!("Setting the target subscription to {}\n", name);
+        *self.subscription.write().unwrap() = Some(id.to_owned());
+        Ok(())
+    }
+
+    fn register_providers(&self) -> Result<()> {
+        trace!("Registering the Microsoft.App provider.");
+        let url = format!(
+            "{}/subscriptions/{}/providers/Microsoft.App/register?api-version={}",
+            self.endpoint,
+            self.subscription_id()?,
+            DEPLOYMENTS_API_VERSION
+        );
+        self.send_checked("POST", &url, None)?;
+        Ok(())
+    }
+
+    fn resource_group_exists(&self, resource_group: &str) -> Result<bool> {
+        let url = format!(
+            "{}/subscriptions/{}/resourcegroups/{}?api-version={}",
+            self.endpoint,
+            self.subscription_id()?,
+            resource_group,
+            DEPLOYMENTS_API_VERSION
+        );
+        let response = self.send("GET", &url, None)?;
+        if response.status == 404 {
+            return Ok(false);
+        }
+        check_response("GET", &url, response)?;
+        Ok(true)
+    }
+
+    fn get_containerapp(&self, resource_group: &str, name: &str) -> Result<Value> {
+        let url = format!(
+            "{}/subscriptions/{}/resourceGroups/{}/providers/Microsoft.App/containerApps/{}?api-version={}",
+            self.endpoint,
+            self.subscription_id()?,
+            resource_group,
+            name,
+            ApiVersion::default()
+        );
+        let response = self.send("GET", &url, None)?;
+        if response.status == 404 {
+            return Err(Error::new(AzCliError::ContainerAppNotFound {
+                name: name.to_owned(),
+                resource_group: resource_group.to_owned(),
+            }));
+        }
+        let mut app = check_response("GET", &url, response)?.body;
+        // az adds the resource group to what it shows.
+        if let Some(app) = app.as_object_mut() {
+            app.insert("resourceGroup".to_owned(), Value::from(resource_group));
+        }
+        Ok(app)
+    }
+
+    fn get_containerapp_environment(
+        &self,
+        resource_group: Option<&str>,
+        environment_name: Option<&str>,
+        environment_id: Option<&str>,
+    ) -> Result<Value> {
+        let id = match (resource_group, environment_name, environment_id) {
+            (Some(resource_group), Some(environment_name), _) => format!(
+                "/subscriptions/{}/resourceGroups/{}/providers/Microsoft.App/managedEnvironments/{}",
+                self.subscription_id()?,
+                resource_group,
+                environment_name
+            ),
+            (_, _, Some(environment_id)) => environment_id.to_owned(),
+            _ => return Err(Error::new(AzCliError::NoParameters)),
+        };
+        let url = format!(
+            "{}{}?api-version={}",
+            self.endpoint,
+            id,
+            ApiVersion::default()
+        );
+        let response = self.send("GET", &url, None)?;
+        if response.status == 404 {
+            return Ok(Value::Null);
+        }
+        Ok(check_response("GET", &url, response)?.body)
+    }
+
+    fn query_logs(&self, workspace_id: &str, query: &str) -> Result<Vec<AzMonitorLog>> {
+        let url = format!(
+            "{}/v1/workspaces/{}/query",
+            self.log_analytics_endpoint, workspace_id
+        );
+        let response = self.send_checked("POST", &url, Some(&json!({ "query": query })))?;
+        let mut logs = Vec::new();
+        for table in response.body["tables"].as_array().into_iter().flatten() {
+            let columns: Vec<&str> = table["columns"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|c| c["name"].as_str())
+                .collect();
+            for row in table["rows"].as_array().into_iter().flatten() {
+                let mut log = Map::new();
+                log.insert("TableName".to_owned(), table["name"].clone());
+                for (column, value) in columns.iter().zip(row.as_array().into_iter().flatten()) {
+                    log.insert((*column).to_owned(), value.clone());
+                }
+                logs.push(serde_json::from_value(Value::Object(log))?);
+            }
+        }
+        Ok(logs)
+    }
+
+    fn deploy_subscription_template(
+        &self,
+        name: &str,
+        location: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<Value> {
+        trace!("Deploying {} to {}", name, location);
+        let url = self.deployment_url(None, template_path)?;
+        self.deploy(&url, Some(location), template_path, parameters)
+    }
+
+    fn deploy_group_template(
+        &self,
+        name: &str,
+        resource_group: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<Value> {
+        trace!("Deploying {} to {}", name, resource_group);
+        let url = self.deployment_url(Some(resource_group), template_path)?;
+        self.deploy(&url, None, template_path, parameters)
+    }
+
+    fn what_if_subscription_template(
+        &self,
+        name: &str,
+        location: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<WhatIfResult> {
+        trace!("Previewing {} in {}", name, location);
+        let url = self.deployment_url(None, template_path)?;
+        self.what_if(&url, Some(location), template_path, parameters)
+    }
+
+    fn what_if_group_template(
+        &self,
+        name: &str,
+        resource_group: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<WhatIfResult> {
+        trace!("Previewing {} in {}", name, resource_group);
+        let url = self.deployment_url(Some(resource_group), template_path)?;
+        self.what_if(&url, None, template_path, parameters)
+    }
+}
+
+/// Deployments are named after the template, as the az CLI does.
+fn get_deployment_name(template_path: &Path) -> String {
+    let name: String = template_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.()".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(64)
+        .collect();
+    if name.is_empty() {
+        "compose2containerapp".to_owned()
+    } else {
+        name
+    }
+}
+
+/// Parameters come in the az CLI's `name=value` form, or as `@file` for a
+/// parameters file.  The az CLI reads values as the type the template
+/// declares, so the same is done here.
+fn get_deployment_body(
+    location: Option<&str>,
+    template_path: &Path,
+    parameters: &[String],
+) -> Result<Value> {
+    let template: Value = serde_json::from_str(&read_to_string(template_path)?)?;
+    let mut parameter_values = Map::new();
+    for parameter in parameters.iter() {
+        if let Some(parameters_path) = parameter.strip_prefix('@') {
+            let parameters_file: Value = serde_json::from_str(&read_to_string(parameters_path)?)?;
+            if let Some(values) = parameters_file["parameters"].as_object() {
+                parameter_values.extend(values.clone());
+            }
+            continue;
+        }
+        let (name, value) = parameter
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Parameters are set as <name>=<value>, found {}", parameter))?;
+        let parameter_type = template["parameters"][name]["type"]
+            .as_str()
+            .unwrap_or("string");
+        parameter_values.insert(
+            name.to_owned(),
+            json!({ "value": get_parameter_value(name, parameter_type, value)? }),
+        );
+    }
+    let mut body = json!({
+        "properties": {
+            "mode": "Incremental",
+            "template": template,
+            "parameters": parameter_values,
+        }
+    });
+    if let Some(location) = location {
+        body["location"] = Value::from(location);
+    }
+    Ok(body)
+}
+
+fn get_parameter_value(name: &str, parameter_type: &str, value: &str) -> Result<Value> {
+    let typed = match parameter_type.to_lowercase().as_str() {
+        "int" => value.parse::<i64>().map(Value::from).ok(),
+        "bool" => value.parse::<bool>().map(Value::from).ok(),
+        "object" | "secureobject" | "array" => serde_json::from_str(value).ok(),
+        _ => Some(Value::from(value)),
+    };
+    typed.ok_or_else(|| {
+        anyhow!(
+            "The parameter {} is a {}, which {} is not",
+            name,
+            parameter_type,
+            value
+        )
+    })
+}
+
+fn check_response(method: &str, url: &str, response: ArmResponse) -> Result<ArmResponse> {
+    if response.status >= 400 {
+        let error = AzErrorDetail::from_json(&response.body).unwrap_or_else(|| AzErrorDetail {
+            code: Some(response.status.to_string()),
+            message: response.body.to_string(),
+            ..AzErrorDetail::default()
+        });
+        let name = format!("{} {}", method, url.split('?').next().unwrap_or(url));
+        return Err(Error::new(get_az_cli_error(&name, error)));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{spawn, JoinHandle};
+
+    /// Serves the canned `(status, location, body)` responses in order and
+    /// returns each request line with its Authorization header.
+    fn serve(
+        responses: Vec<(u16, Option<&'static str>, &'static str)>,
+    ) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server_url = url.clone();
+        let handle = spawn(move || {
+            let mut requests = Vec::new();
+            for (status, location, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut authorization = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        match name.to_lowercase().as_str() {
+                            "authorization" => authorization = value.trim().to_owned(),
+                            "content-length" => content_length = value.trim().parse().unwrap(),
+                            _ => {}
+                        }
+                    }
+                }
+                let mut request_body = vec![0; content_length];
+                reader.read_exact(&mut request_body).unwrap();
+                requests.push(format!("{} {}", request_line.trim(), authorization));
+
+                let location = location
+                    .map(|l| format!("Location: {}\r\n", l.replace("{url}", &server_url)))
+                    .unwrap_or_default();
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    location,
+                    body.len(),
+                    body
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn backend(url: &str) -> ArmRestBackend {
+        ArmRestBackend::new(TokenCredential::AccessToken("token".to_owned()))
+            .with_endpoint(url)
+            .with_subscription("sub")
+            .with_poll_interval(Duration::ZERO)
+    }
+
+    #[test]
+    fn get_containerapp_reports_missing_apps() {
+        let (url, server) = serve(vec![
+            (200, None, r#"{"name": "web", "properties": {}}"#),
+            (404, None, r#"{"error": {"code": "ResourceNotFound"}}"#),
+        ]);
+        let backend = backend(&url);
+
+        let app = backend.get_containerapp("rg", "web").unwrap();
+        assert_eq!(app["name"], "web");
+        assert_eq!(app["resourceGroup"], "rg");
+        let error = backend.get_containerapp("rg", "db").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<AzCliError>(),
+            Some(AzCliError::ContainerAppNotFound { .. })
+        ));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with(
+            "GET /subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/containerApps/web?"
+        ));
+        assert!(requests[0].ends_with("Bearer token"));
+    }
+
+    #[test]
+    fn deployments_are_polled_until_they_finish() {
+        let (url, server) = serve(vec![
+            (
+                201,
+                None,
+                r#"{"properties": {"provisioningState": "Accepted"}}"#,
+            ),
+            (
+                200,
+                None,
+                r#"{"properties": {"provisioningState": "Running"}}"#,
+            ),
+            (
+                200,
+                None,
+                r#"{"properties": {"provisioningState": "Succeeded", "outputs": {"fqdn": {"value": "web.azurecontainerapps.io"}}}}"#,
+            ),
+        ]);
+        let path = env::temp_dir().join(format!(
+            "arm-rest-deploy-test-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::write(&path, r#"{"resources": []}"#).unwrap();
+
+        let deployment = backend(&url)
+            .deploy_group_template("web", "rg", &path, &["name=web".to_owned()])
+            .unwrap();
+        assert_eq!(
+            deployment["properties"]["outputs"]["fqdn"]["value"],
+            "web.azurecontainerapps.io"
+        );
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].starts_with(&format!(
+            "PUT /subscriptions/sub/resourcegroups/rg/providers/Microsoft.Resources/deployments/{}?",
+            path.file_stem().unwrap().to_string_lossy()
+        )));
+        assert!(requests[2].starts_with("GET "));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn what_if_follows_the_location_header() {
+        let (url, server) = serve(vec![
+            (202, Some("{url}/operations/1"), ""),
+            (
+                200,
+                None,
+                r#"{"status": "Succeeded", "properties": {"changes": [{"resourceId": "/subscriptions/sub/providers/Microsoft.App/managedEnvironments/env", "changeType": "Create"}]}}"#,
+            ),
+        ]);
+        let path = env::temp_dir().join(format!(
+            "arm-rest-what-if-test-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::write(&path, r#"{"resources": []}"#).unwrap();
+
+        let result = backend(&url)
+            .what_if_subscription_template("env", "eastus", &path, &[])
+            .unwrap();
+        let changes = result.changes.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].resource_name(),
+            "Microsoft.App/managedEnvironments/env"
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[1].starts_with("GET /operations/1 "));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn client_secrets_are_exchanged_for_tokens() {
+        let (url, server) = serve(vec![
+            (
+                200,
+                None,
+                r#"{"access_token": "secret-token", "expires_in": 3600}"#,
+            ),
+            (200, None, r#"{"name": "web"}"#),
+        ]);
+        let backend = ArmRestBackend::new(TokenCredential::ClientSecret {
+            tenant_id: "tenant".to_owned(),
+            client_id: "client".to_owned(),
+            client_secret: "secret".to_owned(),
+        })
+        .with_endpoint(&url)
+        .with_authority(&url)
+        .with_subscription("sub");
+
+        backend.get_containerapp("rg", "web").unwrap();
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("POST /tenant/oauth2/v2.0/token "));
+        assert!(requests[1].ends_with("Bearer secret-token"));
+    }
+
+    #[test]
+    fn parameters_take_the_type_the_template_declares() {
+        let path = env::temp_dir().join(format!(
+            "arm-rest-parameters-test-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::write(
+            &path,
+            r#"{"parameters": {"replicas": {"type": "int"}, "external": {"type": "bool"}, "tags": {"type": "object"}, "name": {"type": "string"}}}"#,
+        )
+        .unwrap();
+        let parameters: Vec<String> = [
+            "replicas=3",
+            "external=true",
+            r#"tags={"env": "dev"}"#,
+            "name=42",
+        ]
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+
+        let body = get_deployment_body(None, &path, &parameters).unwrap();
+        let invalid = get_deployment_body(None, &path, &["replicas=three".to_owned()]);
+        std::fs::remove_file(path).unwrap();
+
+        let values = &body["properties"]["parameters"];
+        assert_eq!(values["replicas"]["value"], json!(3));
+        assert_eq!(values["external"]["value"], json!(true));
+        assert_eq!(values["tags"]["value"]["env"], "dev");
+        assert_eq!(values["name"]["value"], "42");
+        assert!(invalid.is_err());
+    }
+}
@@ -1,20 +1,24 @@
 #![allow(dead_code)]
-use super::AzCliCommand;
-use super::ARM;
+use super::{
+    get_az_cli_error, AzCliCommand, AzErrorDetail, AzureBackend, CommandRunner, ProcessRunner,
+    WhatIfChange, WhatIfResult, ARM,
+};
 use anyhow::{Error, Result};
 use custom_error::custom_error;
 use log::{debug, trace};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fs::{remove_file, File};
 use std::io::{BufRead, BufReader, Write};
 use std::path::Path;
+use std::sync::Arc;
 
 custom_error! {
     pub AzCliError
     Unknown = "unknown error",
-    CliMissing = "Unable to find the Azure CLI.",
+    CliMissing = "Unable to find the Azure CLI.  Please install the Az CLI to continue (https://aka.ms/containerapps/install-az-cli) or use --skip-azure to only process the Compose files.",
     NoParameters = "Command called without required parameters.",
     InvalidJsonError{source: std::string::FromUtf8Error} = "Failed to convert the output.",
     RegexError{source: regex::Error} = "Regex problem.",
@@ -23,6 +27,12 @@ custom_error! {
     NotLoggedIn = "Az CLI is not authenticated.",
     MissingTemplate = "No template available to deploy",
     TemplateFailed = "Deployment did not achieve the desired result.",
+    WhatIfFailed{message: String} = "What-if failed: {message}",
+    ContainerAppNotFound{name: String, resource_group: String} = "Unable to find the ContainerApp {name} in {resource_group}.",
+    CommandFailed{name: String, error: AzErrorDetail} = "{name} failed: {error}",
+    DeploymentFailed{name: String, error: AzErrorDetail} = "The deployment of {name} failed: {error}",
+    ResourceGroupNotFound{error: AzErrorDetail} = "{error}",
+    AuthorizationFailed{error: AzErrorDetail} = "Not authorized: {error}",
 }
 
 #[derive(Default, Clone, Debug)]
@@ -32,64 +42,22 @@ pub struct AzAccountInfo {
     tenant_id: Option<String>,
 }
 
-pub fn set_azure_environment(subscription: &str) -> Result<()> {
+pub fn set_azure_environment(backend: &dyn AzureBackend, subscription: &str) -> Result<()> {
     trace!("Entering set azure environment.");
-    println!(
-        "Checking to see if the Azure CLI is authenticated and which subscription is default."
-    );
-    let account = match get_account_info() {
-        Ok(a) => a,
-        Err(_) => {
-            trace!("Failed to get existing login information.  Prompting for new login.");
-            login()?;
-            println!("Checking for the default subscription.");
-            get_account_info()?
-        }
-    };
-
-    if let Some(account_subscription) = account.subscription_name {
-        println!("The default subscription is {}", &account_subscription);
-
-        if !subscription.is_empty() {
-            if account_subscription.trim_matches('"') == subscription {
-                println!("Subscription already configured correctly.\n");
-            } else {
-                println!("Setting the target subscription to {}\n", &subscription);
-                set_target_subscription(subscription)?;
-            }
-        }
-    }
-
-    Ok(())
+    backend.set_subscription(subscription)
 }
 
-pub fn setup_extensions_and_preview_commands() -> Result<()> {
-    trace!("Enabling the preview extension for az containerapps.");
-    let extension_url = "https://workerappscliextension.blob.core.windows.net/azure-cli-extension/containerapp-0.2.0-py2.py3-none-any.whl";
-    let _ = AzCliCommand::default()
-        .with_name("Enable Preview Extension.")
-        .with_args(vec!["extension", "add", "--source", extension_url, "--yes"])
-        .run()?;
-
-    trace!("Enabling the extension for az log-analytics.");
-    let _ = AzCliCommand::default()
-        .with_name("Enable Preview Extension.")
-        .with_args(vec!["extension", "add", "--name", "log-anaytics", "--yes"])
-        .run()?;
-
-    trace!("Registering the Microsoft.Web provider.");
-    let _ = AzCliCommand::default()
-        .with_name("Register Microsoft.Web provider.")
-        .with_args(vec!["provider", "register", "--namespace", "Microsoft.Web"])
-        .run()?;
-    Ok(())
+pub fn setup_extensions_and_preview_commands(backend: &dyn AzureBackend) -> Result<()> {
+    backend.register_providers()
 }
 
 pub fn get_az_containerapp_environment_resource_id(
+    backend: &dyn AzureBackend,
     resource_group: &str,
     environment_name: &str,
 ) -> Result<Option<String>> {
-    let json = get_az_containerapp_environment(Some(resource_group), Some(environment_name), None)?;
+    let json =
+        backend.get_containerapp_environment(Some(resource_group), Some(environment_name), None)?;
 
     if let Some(resource_id) = json["id"].as_str() {
         debug!("az containerapp show output: {:?}", resource_id);
@@ -100,14 +68,21 @@ pub fn get_az_containerapp_environment_resource_id(
 }
 
 pub fn get_az_containerapp_environment_log_workspace_id(
+    backend: &dyn AzureBackend,
     resource_group: Option<&str>,
     environment_name: Option<&str>,
     environment_id: Option<&str>,
 ) -> Result<Option<String>> {
-    let json = get_az_containerapp_environment(resource_group, environment_name, environment_id)?;
+    let json =
+        backend.get_containerapp_environment(resource_group, environment_name, environment_id)?;
 
+    // ARM nests the configuration under properties, older az versions don't.
+    let app_logs_configuration = match json["properties"].get("appLogsConfiguration") {
+        Some(configuration) => configuration,
+        None => &json["appLogsConfiguration"],
+    };
     if let Some(log_workspace_id) =
-        json["appLogsConfiguration"]["logAnalyticsConfiguration"]["customerId"].as_str()
+        app_logs_configuration["logAnalyticsConfiguration"]["customerId"].as_str()
     {
         debug!("az containerapp show output: {:?}", log_workspace_id);
         Ok(Some(log_workspace_id.to_owned()))
@@ -116,45 +91,12 @@ pub fn get_az_containerapp_environment_log_workspace_id(
     }
 }
 
-fn get_az_containerapp_environment(
-    resource_group: Option<&str>,
-    environment_name: Option<&str>,
-    environment_id: Option<&str>,
+pub fn get_az_containerapp(
+    backend: &dyn AzureBackend,
+    resource_group: &str,
+    name: &str,
 ) -> Result<Value> {
-    let args = if resource_group.is_some() && environment_name.is_some() {
-        vec![
-            "containerapp",
-            "env",
-            "show",
-            "--resource-group",
-            resource_group.unwrap(),
-            "--name",
-            environment_name.unwrap(),
-        ]
-    } else if environment_id.is_some() {
-        vec![
-            "containerapp",
-            "env",
-            "show",
-            "--ids",
-            environment_id.unwrap(),
-        ]
-    } else {
-        return Err(Error::new(AzCliError::NoParameters));
-    };
-
-    let command = AzCliCommand::default()
-        .with_name("Retrieve existing Az ContainerApps Environment")
-        .with_args(args)
-        .run()?;
-
-    let stdout = command.get_stdout().unwrap();
-
-    let json: Value = match serde_json::from_str(&stdout) {
-        Ok(json_value) => json_value,
-        Err(_) => Value::default(),
-    };
-    Ok(json)
+    backend.get_containerapp(resource_group, name)
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, PartialOrd, Ord)]
@@ -170,6 +112,7 @@ pub struct AzMonitorLog {
 }
 
 pub fn get_az_monitor_logs(
+    backend: &dyn AzureBackend,
     client_id: &str,
     service_name: &Option<String>,
     max_results: &Option<u32>,
@@ -186,64 +129,33 @@ pub fn get_az_monitor_logs(
         let max_filter = format!(" | take {}", u);
         query.push_str(&max_filter);
     };
-    let args = vec![
-        "monitor",
-        "log-analytics",
-        "query",
-        "--workspace",
-        client_id,
-        "--analytics-query",
-        &query,
-    ];
-
-    let command = AzCliCommand::default()
-        .with_name("Retrieve existing Az ContainerApps Environment")
-        .with_args(args)
-        .run()?;
-
-    let stdout = command.get_stdout().unwrap();
-
-    let logs: Vec<AzMonitorLog> = match serde_json::from_str(&stdout) {
-        Ok(json_value) => json_value,
-        Err(_) => Vec::new(),
-    };
-
-    Ok(logs)
+    backend.query_logs(client_id, &query)
 }
 
 pub fn deploy_containerapps_env<'a>(
+    backend: &'a dyn AzureBackend,
     resource_group: &'a str,
     environment_name: &'a str,
     location: &'a str,
 ) -> Result<String> {
     create_arm_template()?;
 
-    let resource_group_parameter = format!("rgName={}", resource_group);
-    let name_parameter = format!("name={}", environment_name);
-    let location_parameter = format!("location={}", location);
-
-    let args = vec![
-        "deployment",
-        "sub",
-        "create",
-        "--location",
-        location,
-        "--template-file",
-        "azuredeploy.json",
-        "--parameters",
-        &resource_group_parameter,
-        &name_parameter,
-        &location_parameter,
+    let parameters = vec![
+        format!("rgName={}", resource_group),
+        format!("name={}", environment_name),
+        format!("location={}", location),
     ];
-    let command = AzCliCommand::default()
-        .with_name(format!("Deploy ContainerApps Environment {}", environment_name).as_str())
-        .with_args(args)
-        .run()?;
-    let stdout = command.get_stdout().unwrap();
+    let deployment = backend.deploy_subscription_template(
+        format!("ContainerApps Environment {}", environment_name).as_str(),
+        location,
+        Path::new("azuredeploy.json"),
+        &parameters,
+    );
 
     delete_arm_template()?;
 
-    let v: Value = serde_json::from_str(&stdout)?;
+    let v = deployment?;
+    check_deployment(environment_name, &v)?;
     if let Some(resource_id) = v["properties"]["outputs"]["containerappEnvId"]["value"].as_str() {
         debug!("New environment resource id: {}", resource_id);
         Ok(resource_id.to_owned())
@@ -253,28 +165,22 @@ pub fn deploy_containerapps_env<'a>(
 }
 
 pub fn deploy_containerapps<'a>(
+    backend: &'a dyn AzureBackend,
     name: &'a str,
     resource_group: &'a str,
     json_path: &'a Path,
+    parameters: &'a [String],
 ) -> Result<String> {
     trace!("Deploying {} to {}", name, resource_group);
-    let args = vec![
-        "deployment",
-        "group",
-        "create",
-        "--resource-group",
+    let v = backend.deploy_group_template(
+        format!("containerapps app {}", &name).as_str(),
         resource_group,
-        "--template-file",
-        json_path.to_str().unwrap(),
-    ];
-    let command = AzCliCommand::default()
-        .with_name(format!("Deploy containerapps app {} to {}", &name, &resource_group).as_str())
-        .with_args(args)
-        .run()?;
-    let stdout = command.get_stdout().unwrap();
+        json_path,
+        parameters,
+    )?;
     remove_file(json_path)?;
+    check_deployment(name, &v)?;
 
-    let v: Value = serde_json::from_str(&stdout)?;
     if let Some(fqdn) = v["properties"]["outputs"]["containerappFqdn"]["value"].as_str() {
         debug!("New environment resource id: {}", fqdn);
         Ok(fqdn.to_owned())
@@ -283,80 +189,573 @@ pub fn deploy_containerapps<'a>(
     }
 }
 
-fn create_arm_template() -> Result<()> {
-    trace!("Creating ARM template.");
-    let mut output = File::create("azuredeploy.json")?;
-    write!(output, "{}", ARM)?;
-    Ok(())
+pub fn deploy_combined_containerapps<'a>(
+    backend: &'a dyn AzureBackend,
+    resource_group: &'a str,
+    json_path: &'a Path,
+    parameters: &'a [String],
+) -> Result<BTreeMap<String, String>> {
+    trace!("Deploying the combined template to {}", resource_group);
+    let v =
+        backend.deploy_group_template("containerapps", resource_group, json_path, parameters)?;
+    remove_file(json_path)?;
+    check_deployment("containerapps", &v)?;
+
+    match v["properties"]["outputs"].as_object() {
+        Some(outputs) => Ok(outputs
+            .iter()
+            .filter_map(|(name, output)| {
+                output["value"]
+                    .as_str()
+                    .map(|value| (name.to_owned(), value.to_owned()))
+            })
+            .collect()),
+        None => Err(Error::new(AzCliError::TemplateFailed)),
+    }
 }
-fn delete_arm_template() -> Result<()> {
-    trace!("Removing ARM template.");
-    remove_file("azuredeploy.json")?;
+
+pub fn deploy_arm_resources<'a>(
+    backend: &'a dyn AzureBackend,
+    name: &'a str,
+    resource_group: &'a str,
+    json_path: &'a Path,
+    parameters: &'a [String],
+) -> Result<()> {
+    trace!("Deploying {} to {}", name, resource_group);
+    let v = backend.deploy_group_template(name, resource_group, json_path, parameters)?;
+    remove_file(json_path)?;
+    check_deployment(name, &v)?;
+
+    if let Some(state) = v["properties"]["provisioningState"].as_str() {
+        debug!("{} deployment finished as {}", name, state);
+        if state == "Succeeded" {
+            return Ok(());
+        }
+    }
+    Err(Error::new(AzCliError::TemplateFailed))
+}
+
+/// Previews `deploy_containerapps_env` without creating anything.
+pub fn what_if_containerapps_env<'a>(
+    backend: &'a dyn AzureBackend,
+    resource_group: &'a str,
+    environment_name: &'a str,
+    location: &'a str,
+) -> Result<Vec<WhatIfChange>> {
+    create_arm_template()?;
+
+    let parameters = vec![
+        format!("rgName={}", resource_group),
+        format!("name={}", environment_name),
+        format!("location={}", location),
+    ];
+    let result = backend.what_if_subscription_template(
+        format!("ContainerApps Environment {}", environment_name).as_str(),
+        location,
+        Path::new("azuredeploy.json"),
+        &parameters,
+    );
+
+    delete_arm_template()?;
+    get_what_if_changes(result?)
+}
+
+/// Previews deploying an ARM template to a resource group without changing
+/// anything.  A resource group that doesn't exist yet can't be previewed, so
+/// that returns `None`.
+pub fn what_if_arm_resources<'a>(
+    backend: &'a dyn AzureBackend,
+    name: &'a str,
+    resource_group: &'a str,
+    json_path: &'a Path,
+    parameters: &'a [String],
+) -> Result<Option<Vec<WhatIfChange>>> {
+    if !backend.resource_group_exists(resource_group)? {
+        debug!(
+            "{} doesn't exist, skipping the preview of {}",
+            resource_group, name
+        );
+        remove_file(json_path)?;
+        return Ok(None);
+    }
+    trace!("Previewing {} in {}", name, resource_group);
+    let result = backend.what_if_group_template(name, resource_group, json_path, parameters);
+    remove_file(json_path)?;
+    get_what_if_changes(result?).map(Some)
+}
+
+/// Turns a deployment that finished as Failed into the error ARM recorded.
+fn check_deployment(name: &str, deployment: &Value) -> Result<()> {
+    if deployment["properties"]["provisioningState"].as_str() == Some("Failed") {
+        let error =
+            AzErrorDetail::from_json(&deployment["properties"]["error"]).unwrap_or_default();
+        return Err(Error::new(AzCliError::DeploymentFailed {
+            name: name.to_owned(),
+            error,
+        }));
+    }
     Ok(())
 }
 
-fn get_account_info() -> Result<AzAccountInfo> {
-    let command = AzCliCommand::default()
-        .with_name("Show logged in account.")
-        .with_args(vec!["account", "show", "--output", "json"])
-        .run()?;
+fn get_what_if_changes(result: WhatIfResult) -> Result<Vec<WhatIfChange>> {
+    if let Some(error) = result.error {
+        return Err(Error::new(AzCliError::WhatIfFailed {
+            message: error.message.or(error.code).unwrap_or_default(),
+        }));
+    }
+    debug!("What-if finished as {:?}", result.status);
+    Ok(result.changes.unwrap_or_default())
+}
+
+/// Runs everything through the `az` CLI and reads its JSON output.
+pub struct AzCliBackend {
+    runner: Arc<dyn CommandRunner>,
+}
 
-    let regex_string = "Please run 'az login' to setup account.";
-    let re = Regex::new(regex_string)?;
+impl Default for AzCliBackend {
+    fn default() -> Self {
+        AzCliBackend {
+            runner: Arc::new(ProcessRunner),
+        }
+    }
+}
 
-    let account = AzAccountInfo::default();
+impl AzCliBackend {
+    #[cfg(test)]
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
 
-    let mut return_value = Ok(account);
-    let stdout = &command.get_stdout().unwrap();
-    if let Some(_captures) = re.captures(stdout) {
-        return_value = Err(Error::new(AzCliError::NotLoggedIn));
-    } else {
-        let v: Value = serde_json::from_str(stdout)?;
+    fn command(&self) -> AzCliCommand<'_> {
+        AzCliCommand::default().with_runner(self.runner.clone())
+    }
 
-        let current_account = AzAccountInfo {
+    fn run_what_if(&self, name: &str, args: Vec<&str>) -> Result<WhatIfResult> {
+        let command = self.command().with_name(name).with_args(args).run()?;
+        if !command.success() {
+            let error = AzErrorDetail::from_stderr(&command.get_stderr().unwrap_or_default());
+            return Err(Error::new(AzCliError::WhatIfFailed {
+                message: error.to_string(),
+            }));
+        }
+        let stdout = command.get_stdout().unwrap();
+        Ok(serde_json::from_str(&stdout)?)
+    }
+
+    fn get_account_info(&self) -> Result<AzAccountInfo> {
+        let command = self
+            .command()
+            .with_name("Show logged in account.")
+            .with_args(vec!["account", "show", "--output", "json"])
+            .run()?
+            .check()?;
+
+        // Not being logged in fails the command, which `check` reports.
+        let v: Value = serde_json::from_str(&command.get_stdout().unwrap_or_default())?;
+        Ok(AzAccountInfo {
             subscription_id: Some(v["id"].to_string()),
             subscription_name: Some(v["name"].to_string()),
             tenant_id: Some(v["tenantId"].to_string()),
-        };
+        })
+    }
 
-        return_value = Ok(current_account);
+    fn login(&self) -> Result<()> {
+        let error_pipe_reader = self
+            .command()
+            .with_name("Login")
+            .with_args(vec!["login"])
+            .stderr_reader()?;
+
+        for line in BufReader::new(error_pipe_reader).lines().flatten() {
+            let logged_in_regex = r"^WARNING: (You have logged in\.)";
+            let warning_regex = r"^WARNING: (.*)$";
+            let warn = Regex::new(warning_regex).expect("Boom");
+            let logged_in = Regex::new(logged_in_regex).expect("Boom");
+
+            if let Some(m) = warn.captures(&line) {
+                if let Some(m2) = logged_in.captures(&line) {
+                    eprintln!("{}", &m2[1]);
+                } else {
+                    eprintln!("{}", &m[1]);
+                }
+            }
+        }
+        Ok(())
     }
 
-    return_value
+    fn set_target_subscription(&self, subscription_name: &str) -> Result<()> {
+        self.command()
+            .with_name("Login")
+            .with_args(vec!["account", "set", "--subscription", subscription_name])
+            .run()?
+            .check()?;
+        Ok(())
+    }
 }
 
-fn login() -> Result<()> {
-    let error_pipe_reader = AzCliCommand::default()
-        .with_name("Login")
-        .with_args(vec!["login"])
-        .stderr_reader()?;
-
-    for line in BufReader::new(error_pipe_reader).lines().flatten() {
-        let logged_in_regex = r"^WARNING: (You have logged in\.)";
-        let warning_regex = r"^WARNING: (.*)$";
-        let warn = Regex::new(warning_regex).expect("Boom");
-        let logged_in = Regex::new(logged_in_regex).expect("Boom");
-
-        if let Some(m) = warn.captures(&line) {
-            if let Some(m2) = logged_in.captures(&line) {
-                println!("{}", &m2[1]);
-            } else {
-                println!("{}", &m[1]);
+impl AzureBackend for AzCliBackend {
+    fn ensure_available(&self) -> Result<()> {
+        self.runner.ensure_available()
+    }
+
+    fn set_subscription(&self, subscription: &str) -> Result<()> {
+        eprintln!(
+            "Checking to see if the Azure CLI is authenticated and which subscription is default."
+        );
+        let account = match self.get_account_info() {
+            Ok(a) => a,
+            Err(_) => {
+                trace!("Failed to get existing login information.  Prompting for new login.");
+                self.login()?;
+                eprintln!("Checking for the default subscription.");
+                self.get_account_info()?
+            }
+        };
+
+        if let Some(account_subscription) = account.subscription_name {
+            eprintln!("The default subscription is {}", &account_subscription);
+
+            if !subscription.is_empty() {
+                if account_subscription.trim_matches('"') == subscription {
+                    eprintln!("Subscription already configured correctly.\n");
+                } else {
+                    eprintln!("Setting the target subscription to {}\n", &subscription);
+                    self.set_target_subscription(subscription)?;
+                }
             }
         }
+
+        Ok(())
+    }
+
+    fn register_providers(&self) -> Result<()> {
+        trace!("Enabling the extension for az containerapp.");
+        let _ = self
+            .command()
+            .with_name("Enable ContainerApp Extension.")
+            .with_args(vec![
+                "extension",
+                "add",
+                "--name",
+                "containerapp",
+                "--upgrade",
+                "--yes",
+            ])
+            .run()?;
+
+        trace!("Enabling the extension for az log-analytics.");
+        let _ = self
+            .command()
+            .with_name("Enable Preview Extension.")
+            .with_args(vec!["extension", "add", "--name", "log-anaytics", "--yes"])
+            .run()?;
+
+        trace!("Registering the Microsoft.App provider.");
+        let _ = self
+            .command()
+            .with_name("Register Microsoft.App provider.")
+            .with_args(vec!["provider", "register", "--namespace", "Microsoft.App"])
+            .run()?;
+        Ok(())
+    }
+
+    fn resource_group_exists(&self, resource_group: &str) -> Result<bool> {
+        let command = self
+            .command()
+            .with_name(format!("Check resource group {}", resource_group).as_str())
+            .with_args(vec!["group", "exists", "--name", resource_group])
+            .run()?
+            .check()?;
+        Ok(command.get_stdout().unwrap_or_default().trim() == "true")
+    }
+
+    fn get_containerapp(&self, resource_group: &str, name: &str) -> Result<Value> {
+        let command = self
+            .command()
+            .with_name(format!("Retrieve ContainerApp {}", name).as_str())
+            .with_args(vec![
+                "containerapp",
+                "show",
+                "--resource-group",
+                resource_group,
+                "--name",
+                name,
+                "--output",
+                "json",
+            ])
+            .run()?;
+
+        if !command.success() {
+            let error = AzErrorDetail::from_stderr(&command.get_stderr().unwrap_or_default());
+            // A missing resource group is reported as is.
+            if error.is_not_found() && error.code.as_deref() != Some("ResourceGroupNotFound") {
+                return Err(Error::new(AzCliError::ContainerAppNotFound {
+                    name: name.to_owned(),
+                    resource_group: resource_group.to_owned(),
+                }));
+            }
+            return Err(Error::new(get_az_cli_error(
+                &format!("Retrieve ContainerApp {}", name),
+                error,
+            )));
+        }
+        let stdout = command.get_stdout().unwrap();
+        let json: Value = serde_json::from_str(&stdout)?;
+        Ok(json)
+    }
+
+    fn get_containerapp_environment(
+        &self,
+        resource_group: Option<&str>,
+        environment_name: Option<&str>,
+        environment_id: Option<&str>,
+    ) -> Result<Value> {
+        let args = if resource_group.is_some() && environment_name.is_some() {
+            vec![
+                "containerapp",
+                "env",
+                "show",
+                "--resource-group",
+                resource_group.unwrap(),
+                "--name",
+                environment_name.unwrap(),
+            ]
+        } else if environment_id.is_some() {
+            vec![
+                "containerapp",
+                "env",
+                "show",
+                "--ids",
+                environment_id.unwrap(),
+            ]
+        } else {
+            return Err(Error::new(AzCliError::NoParameters));
+        };
+
+        let command = self
+            .command()
+            .with_name("Retrieve existing Az ContainerApps Environment")
+            .with_args(args)
+            .run()?;
+
+        if !command.success() {
+            let error = AzErrorDetail::from_stderr(&command.get_stderr().unwrap_or_default());
+            // A missing environment, or resource group, gets created.
+            if !error.is_not_found() {
+                return Err(Error::new(get_az_cli_error(
+                    "Retrieve existing Az ContainerApps Environment",
+                    error,
+                )));
+            }
+        }
+        let stdout = command.get_stdout().unwrap();
+
+        let json: Value = match serde_json::from_str(&stdout) {
+            Ok(json_value) => json_value,
+            Err(_) => Value::default(),
+        };
+        Ok(json)
+    }
+
+    fn query_logs(&self, workspace_id: &str, query: &str) -> Result<Vec<AzMonitorLog>> {
+        let args = vec![
+            "monitor",
+            "log-analytics",
+            "query",
+            "--workspace",
+            workspace_id,
+            "--analytics-query",
+            query,
+        ];
+
+        let command = self
+            .command()
+            .with_name("Query the Log Analytics workspace")
+            .with_args(args)
+            .run()?
+            .check()?;
+
+        let stdout = command.get_stdout().unwrap();
+
+        let logs: Vec<AzMonitorLog> = match serde_json::from_str(&stdout) {
+            Ok(json_value) => json_value,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(logs)
+    }
+
+    fn deploy_subscription_template(
+        &self,
+        name: &str,
+        location: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<Value> {
+        let mut args = vec![
+            "deployment",
+            "sub",
+            "create",
+            "--location",
+            location,
+            "--template-file",
+            template_path.to_str().unwrap(),
+        ];
+        add_parameters(&mut args, parameters);
+        let command = self
+            .command()
+            .with_name(format!("Deploy {}", name).as_str())
+            .with_args(args)
+            .run()?;
+        get_deployment(name, command)
+    }
+
+    fn deploy_group_template(
+        &self,
+        name: &str,
+        resource_group: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<Value> {
+        let mut args = vec![
+            "deployment",
+            "group",
+            "create",
+            "--resource-group",
+            resource_group,
+            "--template-file",
+            template_path.to_str().unwrap(),
+        ];
+        add_parameters(&mut args, parameters);
+        let command = self
+            .command()
+            .with_name(format!("Deploy {} to {}", &name, &resource_group).as_str())
+            .with_args(args)
+            .run()?;
+        get_deployment(name, command)
+    }
+
+    fn what_if_subscription_template(
+        &self,
+        name: &str,
+        location: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<WhatIfResult> {
+        let mut args = vec![
+            "deployment",
+            "sub",
+            "what-if",
+            "--location",
+            location,
+            "--template-file",
+            template_path.to_str().unwrap(),
+            "--no-pretty-print",
+            "--output",
+            "json",
+        ];
+        add_parameters(&mut args, parameters);
+        self.run_what_if(format!("Preview {}", name).as_str(), args)
+    }
+
+    fn what_if_group_template(
+        &self,
+        name: &str,
+        resource_group: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<WhatIfResult> {
+        let mut args = vec![
+            "deployment",
+            "group",
+            "what-if",
+            "--resource-group",
+            resource_group,
+            "--template-file",
+            template_path.to_str().unwrap(),
+            "--no-pretty-print",
+            "--output",
+            "json",
+        ];
+        add_parameters(&mut args, parameters);
+        self.run_what_if(
+            format!("Preview {} in {}", &name, &resource_group).as_str(),
+            args,
+        )
     }
-    Ok(())
 }
 
-fn set_target_subscription(subscription_name: &str) -> Result<()> {
-    let command = AzCliCommand::default()
-        .with_name("Login")
-        .with_args(vec!["account", "set", "--subscription", subscription_name])
-        .run()?;
+/// The finished deployment az printed, or the error it failed with.
+fn get_deployment(name: &str, command: AzCliCommand) -> Result<Value> {
+    if !command.success() {
+        let error = AzErrorDetail::from_stderr(&command.get_stderr().unwrap_or_default());
+        return Err(Error::new(get_az_cli_error(name, error)));
+    }
+    let stdout = command.get_stdout().unwrap();
+    Ok(serde_json::from_str(&stdout)?)
+}
 
-    if command.success() {
-        Ok(())
-    } else {
-        Err(Error::new(AzCliError::Unknown))
+fn add_parameters<'a>(args: &mut Vec<&'a str>, parameters: &'a [String]) {
+    if !parameters.is_empty() {
+        args.push("--parameters");
+        args.extend(parameters.iter().map(|p| p.as_str()));
+    }
+}
+
+fn create_arm_template() -> Result<()> {
+    trace!("Creating ARM template.");
+    let mut output = File::create("azuredeploy.json")?;
+    write!(output, "{}", ARM)?;
+    Ok(())
+}
+fn delete_arm_template() -> Result<()> {
+    trace!("Removing ARM template.");
+    remove_file("azuredeploy.json")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::azure::ReplayRunner;
+
+    #[test]
+    fn failed_deployments_report_the_azure_error() {
+        let path = std::env::temp_dir().join(format!(
+            "az-cli-failed-deploy-test-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::write(&path, "{}").unwrap();
+        let runner = ReplayRunner::default().with_failure(
+            &[
+                "deployment",
+                "group",
+                "create",
+                "--resource-group",
+                "rg",
+                "--template-file",
+                path.to_str().unwrap(),
+            ],
+            r#"ERROR: {"status":"Failed","error":{"code":"DeploymentFailed","message":"At least one resource deployment operation failed.","details":[{"code":"ContainerAppInvalidImage","message":"Image 'web:missing' was not found.","target":"web"}]}}"#,
+        );
+        let backend = AzCliBackend::default().with_runner(Arc::new(runner));
+
+        let error = deploy_arm_resources(&backend, "web", "rg", &path, &[]).unwrap_err();
+        match error.downcast_ref::<AzCliError>() {
+            Some(AzCliError::DeploymentFailed { name, error }) => {
+                assert_eq!(name, "web");
+                assert_eq!(
+                    error.details[0].code.as_deref(),
+                    Some("ContainerAppInvalidImage")
+                );
+            }
+            other => panic!("Unexpected error {:?}", other),
+        }
+        assert_eq!(
+            error.to_string(),
+            "The deployment of web failed: (DeploymentFailed) At least one resource deployment operation failed.\n  - (ContainerAppInvalidImage) Image 'web:missing' was not found. [target: web]"
+        );
+        std::fs::remove_file(path).unwrap();
     }
 }
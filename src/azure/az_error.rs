@@ -0,0 +1,223 @@
+use super::AzCliError;
+use serde_json::Value;
+use std::fmt;
+
+/// An Azure error as the az CLI prints it, or as ARM returns it.  ARM
+/// deployment failures nest the errors of each failed resource as details.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AzErrorDetail {
+    pub code: Option<String>,
+    pub message: String,
+    pub target: Option<String>,
+    pub details: Vec<AzErrorDetail>,
+}
+
+impl AzErrorDetail {
+    /// Reads what az printed to stderr.  Newer versions print `Code:`,
+    /// `Message:` and `Target:` lines, older ones and ARM deployments print
+    /// the error as JSON after `ERROR:`.
+    pub fn from_stderr(stderr: &str) -> AzErrorDetail {
+        let text: String = stderr
+            .lines()
+            .filter(|line| !line.starts_with("WARNING:"))
+            .collect::<Vec<&str>>()
+            .join("\n");
+        let text = text.trim();
+        let text = text.strip_prefix("ERROR:").unwrap_or(text).trim();
+
+        if let Some(error) = text
+            .find('{')
+            .and_then(|start| serde_json::from_str::<Value>(&text[start..]).ok())
+            .and_then(|json| AzErrorDetail::from_json(&json))
+        {
+            return error;
+        }
+        from_text(text)
+    }
+
+    /// Reads an ARM error, either bare or wrapped in `error`.
+    pub fn from_json(json: &Value) -> Option<AzErrorDetail> {
+        let json = match json.get("error") {
+            Some(error) if error.is_object() => error,
+            _ => json,
+        };
+        let code = json["code"].as_str().map(|c| c.to_owned());
+        let message = json["message"].as_str().map(|m| m.to_owned());
+        if code.is_none() && message.is_none() {
+            return None;
+        }
+        let mut details: Vec<AzErrorDetail> = json["details"]
+            .as_array()
+            .map(|details| {
+                details
+                    .iter()
+                    .filter_map(AzErrorDetail::from_json)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let message = message.unwrap_or_default();
+        // Resource providers often return their own error as a JSON string.
+        if details.is_empty() && message.trim_start().starts_with('{') {
+            if let Some(inner) = serde_json::from_str::<Value>(&message)
+                .ok()
+                .and_then(|inner| AzErrorDetail::from_json(&inner))
+            {
+                details.push(inner);
+            }
+        }
+        Some(AzErrorDetail {
+            code,
+            message,
+            target: json["target"].as_str().map(|t| t.to_owned()),
+            details,
+        })
+    }
+
+    /// Whether Azure reported that the resource doesn't exist.
+    pub fn is_not_found(&self) -> bool {
+        match self.code.as_deref() {
+            Some(code) => code.ends_with("NotFound"),
+            None => {
+                let message = self.message.to_lowercase();
+                message.contains("not found") || message.contains("does not exist")
+            }
+        }
+    }
+
+    fn fmt_with_indent(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        // Nested messages that are JSON are shown through their details.
+        let message = if !self.details.is_empty() && self.message.trim_start().starts_with('{') {
+            ""
+        } else {
+            self.message.trim()
+        };
+        match self.code.as_ref() {
+            Some(code) if message.is_empty() => write!(f, "({})", code)?,
+            Some(code) => write!(f, "({}) {}", code, message)?,
+            None => write!(f, "{}", message)?,
+        }
+        if let Some(target) = self.target.as_ref() {
+            write!(f, " [target: {}]", target)?;
+        }
+        for detail in self.details.iter() {
+            write!(f, "\n{}- ", "  ".repeat(indent + 1))?;
+            detail.fmt_with_indent(f, indent + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AzErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with_indent(f, 0)
+    }
+}
+
+/// Picks the `AzCliError` for an error Azure returned to `name`.
+pub fn get_az_cli_error(name: &str, error: AzErrorDetail) -> AzCliError {
+    match error.code.as_deref() {
+        Some("AuthorizationFailed") | Some("LinkedAuthorizationFailed") => {
+            AzCliError::AuthorizationFailed { error }
+        }
+        Some("ResourceGroupNotFound") => AzCliError::ResourceGroupNotFound { error },
+        Some("DeploymentFailed")
+        | Some("InvalidTemplate")
+        | Some("InvalidTemplateDeployment")
+        | Some("DeploymentActive") => AzCliError::DeploymentFailed {
+            name: name.to_owned(),
+            error,
+        },
+        _ if error.message.contains("az login") => AzCliError::NotLoggedIn,
+        _ => AzCliError::CommandFailed {
+            name: name.to_owned(),
+            error,
+        },
+    }
+}
+
+/// `Code:`, `Message:` and `Target:` lines.  Indented ones, listed under
+/// `Exception Details:`, are the inner errors.
+fn from_text(text: &str) -> AzErrorDetail {
+    let mut error = AzErrorDetail::default();
+    let mut first_line = true;
+    for line in text.lines() {
+        let nested = line.starts_with(char::is_whitespace);
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(code) = line.strip_prefix("Code:") {
+            let code = Some(code.trim().to_owned());
+            if nested {
+                error.details.push(AzErrorDetail {
+                    code,
+                    ..AzErrorDetail::default()
+                });
+            } else {
+                error.code = code;
+            }
+        } else if let Some(message) = line.strip_prefix("Message:") {
+            match error.details.last_mut() {
+                Some(detail) if nested => detail.message = message.trim().to_owned(),
+                _ => error.message = message.trim().to_owned(),
+            }
+        } else if let Some(target) = line.strip_prefix("Target:") {
+            match error.details.last_mut() {
+                Some(detail) if nested => detail.target = Some(target.trim().to_owned()),
+                _ => error.target = Some(target.trim().to_owned()),
+            }
+        } else if first_line {
+            // `(Code) Message` on the first line.
+            match line
+                .strip_prefix('(')
+                .and_then(|rest| rest.split_once(") "))
+            {
+                Some((code, message)) => {
+                    error.code = Some(code.to_owned());
+                    error.message = message.to_owned();
+                }
+                None => error.message = line.to_owned(),
+            }
+        }
+        first_line = false;
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structured_and_json_errors_are_parsed() {
+        let stderr = "WARNING: Command group 'containerapp' is in preview.
+ERROR: (ResourceGroupNotFound) Resource group 'missing' could not be found.
+Code: ResourceGroupNotFound
+Message: Resource group 'missing' could not be found.
+Target: resourceGroup
+";
+        let error = AzErrorDetail::from_stderr(stderr);
+        assert_eq!(error.code.as_deref(), Some("ResourceGroupNotFound"));
+        assert_eq!(
+            error.message,
+            "Resource group 'missing' could not be found."
+        );
+        assert_eq!(error.target.as_deref(), Some("resourceGroup"));
+        assert!(matches!(
+            get_az_cli_error("deploy", error),
+            AzCliError::ResourceGroupNotFound { .. }
+        ));
+
+        let stderr = r#"ERROR: {"status":"Failed","error":{"code":"DeploymentFailed","message":"At least one resource deployment operation failed.","details":[{"code":"BadRequest","message":"{\r\n  \"error\": {\r\n    \"code\": \"InvalidParameterValue\",\r\n    \"message\": \"Invalid ingress target port.\"\r\n  }\r\n}"}]}}"#;
+        let error = AzErrorDetail::from_stderr(stderr);
+        assert_eq!(error.code.as_deref(), Some("DeploymentFailed"));
+        assert_eq!(
+            error.details[0].details[0].code.as_deref(),
+            Some("InvalidParameterValue")
+        );
+        assert_eq!(
+            error.to_string(),
+            "(DeploymentFailed) At least one resource deployment operation failed.\n  - (BadRequest)\n    - (InvalidParameterValue) Invalid ingress target port."
+        );
+    }
+}
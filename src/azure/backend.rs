@@ -0,0 +1,116 @@
+use super::{ArmRestBackend, AzCliBackend, AzMonitorLog, TokenCredential, WhatIfResult};
+use anyhow::{anyhow, Result};
+use log::{debug, info};
+use serde_json::Value;
+use std::env;
+use std::path::Path;
+
+lazy_static! {
+    static ref AZURE_BACKEND: Result<Box<dyn AzureBackend>> = get_azure_backend();
+}
+
+/// How the tool talks to Azure.  The az CLI backend shells out to `az` and
+/// reads its output, the REST backend calls Azure Resource Manager directly.
+pub trait AzureBackend: Send + Sync {
+    /// Fails early when the backend has no way to reach Azure, like the az
+    /// CLI not being installed.
+    fn ensure_available(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Makes `subscription`, a name or id, the target of later calls.
+    fn set_subscription(&self, subscription: &str) -> Result<()>;
+
+    /// Registers the resource providers ContainerApps needs.
+    fn register_providers(&self) -> Result<()>;
+
+    /// Whether the resource group exists yet.  Previews of resource group
+    /// deployments need it to.
+    fn resource_group_exists(&self, resource_group: &str) -> Result<bool>;
+
+    fn get_containerapp(&self, resource_group: &str, name: &str) -> Result<Value>;
+
+    /// Looks up an environment by resource group and name, or by id.
+    fn get_containerapp_environment(
+        &self,
+        resource_group: Option<&str>,
+        environment_name: Option<&str>,
+        environment_id: Option<&str>,
+    ) -> Result<Value>;
+
+    fn query_logs(&self, workspace_id: &str, query: &str) -> Result<Vec<AzMonitorLog>>;
+
+    /// Deploys an ARM template at subscription scope and returns the finished
+    /// deployment.  Parameters are `name=value` pairs or `@file`
+    /// parameters files.
+    fn deploy_subscription_template(
+        &self,
+        name: &str,
+        location: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<Value>;
+
+    /// Deploys an ARM template to a resource group and returns the finished
+    /// deployment.  Parameters are `name=value` pairs or `@file`
+    /// parameters files.
+    fn deploy_group_template(
+        &self,
+        name: &str,
+        resource_group: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<Value>;
+
+    fn what_if_subscription_template(
+        &self,
+        name: &str,
+        location: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<WhatIfResult>;
+
+    fn what_if_group_template(
+        &self,
+        name: &str,
+        resource_group: &str,
+        template_path: &Path,
+        parameters: &[String],
+    ) -> Result<WhatIfResult>;
+}
+
+/// The backend picked by `AZURE_BACKEND`, or the reason it couldn't be.
+pub fn azure_backend() -> Result<&'static dyn AzureBackend> {
+    match AZURE_BACKEND.as_ref() {
+        Ok(backend) => Ok(backend.as_ref()),
+        Err(e) => Err(anyhow!("{}", e)),
+    }
+}
+
+/// `AZURE_BACKEND` picks the backend: `az`, `rest`, or `auto` by default.
+/// Auto uses the REST backend when credentials are set in the environment
+/// and falls back to the az CLI otherwise.
+fn get_azure_backend() -> Result<Box<dyn AzureBackend>> {
+    let kind = env::var("AZURE_BACKEND").unwrap_or_else(|_| "auto".to_owned());
+    let backend: Box<dyn AzureBackend> = match kind.to_lowercase().as_str() {
+        "az" | "cli" => Box::new(AzCliBackend::default()),
+        "rest" => Box::new(ArmRestBackend::new(
+            TokenCredential::from_environment().unwrap_or(TokenCredential::AzureCli),
+        )),
+        "auto" => match TokenCredential::from_environment() {
+            Some(credential) => {
+                info!("Using the REST Azure backend with the credentials set in the environment, set AZURE_BACKEND=az to use the az CLI instead");
+                Box::new(ArmRestBackend::new(credential))
+            }
+            None => Box::new(AzCliBackend::default()),
+        },
+        other => {
+            return Err(anyhow!(
+                "AZURE_BACKEND must be az, rest or auto, found {}",
+                other
+            ))
+        }
+    };
+    debug!("Using the {} Azure backend", kind);
+    Ok(backend)
+}
@@ -0,0 +1,167 @@
+use super::AzCliCommand;
+use anyhow::{anyhow, Result};
+use log::{debug, trace};
+use serde::Deserialize;
+use std::env;
+use std::time::{Duration, Instant};
+
+/// Where the REST backend gets its bearer tokens.
+#[derive(Clone, Debug)]
+pub enum TokenCredential {
+    /// A token for Azure Resource Manager from `AZURE_ACCESS_TOKEN`, for
+    /// pipelines that already hold one.
+    AccessToken(String),
+    /// A service principal from `AZURE_TENANT_ID`, `AZURE_CLIENT_ID` and
+    /// `AZURE_CLIENT_SECRET`.
+    ClientSecret {
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    },
+    /// Whoever is logged into the az CLI, through its token cache.
+    AzureCli,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Option<Instant>,
+    /// The default subscription, when the credential knows it.
+    pub subscription: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ClientSecretTokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct AzCliTokenResponse {
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    expires_on: Option<u64>,
+    subscription: Option<String>,
+}
+
+impl TokenCredential {
+    /// The credential set in the environment, if any.
+    pub fn from_environment() -> Option<TokenCredential> {
+        if let Ok(token) = env::var("AZURE_ACCESS_TOKEN") {
+            return Some(TokenCredential::AccessToken(token));
+        }
+        match (
+            env::var("AZURE_TENANT_ID"),
+            env::var("AZURE_CLIENT_ID"),
+            env::var("AZURE_CLIENT_SECRET"),
+        ) {
+            (Ok(tenant_id), Ok(client_id), Ok(client_secret)) => {
+                Some(TokenCredential::ClientSecret {
+                    tenant_id,
+                    client_id,
+                    client_secret,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets a token for `resource`, like `https://management.azure.com/`.
+    /// Service principals sign in through `authority`.
+    pub fn get_token(
+        &self,
+        agent: &ureq::Agent,
+        authority: &str,
+        resource: &str,
+    ) -> Result<AccessToken> {
+        match self {
+            TokenCredential::AccessToken(token) => {
+                if !resource.starts_with("https://management.") {
+                    return Err(anyhow!(
+                        "AZURE_ACCESS_TOKEN only holds a Resource Manager token, {} needs a service principal or the az CLI",
+                        resource
+                    ));
+                }
+                Ok(AccessToken {
+                    token: token.to_owned(),
+                    expires_at: None,
+                    subscription: None,
+                })
+            }
+            TokenCredential::ClientSecret {
+                tenant_id,
+                client_id,
+                client_secret,
+            } => {
+                trace!("Requesting a token for {} as {}", resource, client_id);
+                let url = format!(
+                    "{}/{}/oauth2/v2.0/token",
+                    authority.trim_end_matches('/'),
+                    tenant_id
+                );
+                let scope = format!("{}/.default", resource.trim_end_matches('/'));
+                let response: ClientSecretTokenResponse = agent
+                    .post(&url)
+                    .send_form(&[
+                        ("grant_type", "client_credentials"),
+                        ("client_id", client_id),
+                        ("client_secret", client_secret),
+                        ("scope", &scope),
+                    ])
+                    .map_err(|e| anyhow!("Unable to sign in as {} - {}", client_id, e))?
+                    .into_json()?;
+                Ok(AccessToken {
+                    token: response.access_token,
+                    expires_at: response
+                        .expires_in
+                        .map(|s| Instant::now() + Duration::from_secs(s)),
+                    subscription: None,
+                })
+            }
+            TokenCredential::AzureCli => {
+                trace!("Requesting a token for {} from the az CLI", resource);
+                let command = AzCliCommand::default()
+                    .with_name("Get an access token.")
+                    .with_args(vec![
+                        "account",
+                        "get-access-token",
+                        "--resource",
+                        resource,
+                        "--output",
+                        "json",
+                    ])
+                    .run()?;
+                if !command.success() {
+                    return Err(anyhow!(
+                        "Unable to get a token from the az CLI, run az login first"
+                    ));
+                }
+                let response: AzCliTokenResponse =
+                    serde_json::from_str(&command.get_stdout().unwrap_or_default())?;
+                let expires_at = response.expires_on.and_then(|expires_on| {
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .ok()?
+                        .as_secs();
+                    Some(Instant::now() + Duration::from_secs(expires_on.saturating_sub(now)))
+                });
+                debug!("Token for {} expires at {:?}", resource, expires_at);
+                Ok(AccessToken {
+                    token: response.access_token,
+                    expires_at,
+                    subscription: response.subscription,
+                })
+            }
+        }
+    }
+}
+
+impl AccessToken {
+    /// Whether the token is still good for another minute.
+    // `Option::is_none_or` needs Rust 1.82.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn is_valid(&self) -> bool {
+        self.expires_at
+            .map_or(true, |e| e > Instant::now() + Duration::from_secs(60))
+    }
+}
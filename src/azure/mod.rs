@@ -1,31 +1,43 @@
 #![allow(unused_assignments)]
 
+mod arm_rest;
 mod az_cli;
+mod az_error;
+mod backend;
+mod credential;
 mod find_command;
+mod runner;
+mod what_if;
 
-use anyhow::Result;
-use duct::{cmd, ReaderHandle};
+use anyhow::{Error, Result};
 use find_command::find_command;
 use log::{debug, trace};
-use std::path::PathBuf;
-use std::process::ExitStatus;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+pub use arm_rest::*;
 pub use az_cli::*;
+pub use az_error::*;
+pub use backend::*;
+pub use credential::*;
+pub use runner::*;
+pub use what_if::*;
 
 lazy_static! {
-    pub static ref AZ_CLI_PATH: PathBuf = get_az_cli_path().unwrap();
+    static ref AZ_CLI_PATH: Option<PathBuf> = get_az_cli_path();
 }
 
 const ARM: &str = include_str!("../support/main.json");
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct AzCliCommand<'a> {
     name: String,
-    path: PathBuf,
+    runner: Arc<dyn CommandRunner>,
     args: Vec<&'a str>,
     stdout: Option<String>,
     stderr: Option<String>,
-    exit_status: Option<ExitStatus>,
+    exit_status: Option<bool>,
     verbose: bool,
     show_progress: bool,
 }
@@ -34,7 +46,7 @@ impl<'a> Default for AzCliCommand<'a> {
     fn default() -> AzCliCommand<'a> {
         AzCliCommand {
             name: "login".to_owned(),
-            path: AZ_CLI_PATH.clone(),
+            runner: Arc::new(ProcessRunner),
             args: Vec::new(),
             stdout: None,
             stderr: None,
@@ -54,6 +66,10 @@ impl<'a> AzCliCommand<'a> {
         self.args = args;
         self
     }
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
     #[allow(dead_code)]
     pub fn with_verbose(mut self, verbose: bool) -> Self {
         self.verbose = verbose;
@@ -67,57 +83,58 @@ impl<'a> AzCliCommand<'a> {
     pub fn get_stdout(&self) -> Option<String> {
         self.stdout.clone()
     }
-    #[allow(dead_code)]
     pub fn get_stderr(&self) -> Option<String> {
         self.stderr.clone()
     }
     pub fn success(&self) -> bool {
-        if let Some(s) = &self.exit_status {
-            s.success()
-        } else {
-            false
+        self.exit_status.unwrap_or(false)
+    }
+    /// Fails with the error az printed when the command didn't succeed.
+    pub fn check(self) -> Result<Self> {
+        if self.success() {
+            return Ok(self);
         }
+        let error = AzErrorDetail::from_stderr(self.stderr.as_deref().unwrap_or_default());
+        debug!("{} failed with {:?}", &self.name, &error);
+        Err(Error::new(get_az_cli_error(&self.name, error)))
     }
     pub fn run(mut self) -> Result<Self> {
         trace!("Command: {} running", &self.name);
         debug!("\t`az {}`", &self.args.join(" "));
-        let output = cmd(&self.path, &self.args)
-            .stderr_capture()
-            .stdout_capture()
-            .unchecked()
-            .run()?;
-        self.stdout = Some(String::from_utf8(output.stdout)?);
-        self.stderr = Some(String::from_utf8(output.stderr)?);
-        self.exit_status = Some(output.status);
+        let output = self.runner.run(&self.args)?;
+        self.stdout = Some(output.stdout);
+        self.stderr = Some(output.stderr);
+        self.exit_status = Some(output.success);
         debug!("Az CLI command stdout: {:?}", &self.stdout);
         debug!("Az CLI command stderr: {:?}", &self.stderr);
         trace!("Finished with command {}", &self.name);
 
         Ok(self)
     }
-    pub fn stderr_reader(&self) -> Result<ReaderHandle> {
+    pub fn stderr_reader(&self) -> Result<Box<dyn Read + Send>> {
         trace!("Command {} running", &self.name);
         debug!("\t`az {}`", &self.args.join(" "));
-        let reader = cmd(&self.path, &self.args).stderr_capture().reader()?;
+        let reader = self.runner.stderr_reader(&self.args)?;
         trace!("Returning reader handle.");
         Ok(reader)
     }
-    #[allow(dead_code)]
-    pub fn stdout_reader(&self) -> Result<ReaderHandle> {
-        trace!("Command {} running", &self.name);
-        debug!("\t`az {}`", &self.args.join(" "));
-        let reader = cmd(&self.path, &self.args).stdout_capture().reader()?;
-        trace!("Returning reader handle.");
-        Ok(reader)
+}
+
+/// The `az` binary, looked up the first time a command needs it.
+pub fn az_cli_path() -> Result<&'static Path> {
+    match AZ_CLI_PATH.as_ref() {
+        Some(path) => Ok(path.as_path()),
+        None => Err(Error::new(AzCliError::CliMissing)),
     }
 }
 
-fn get_az_cli_path() -> Result<PathBuf> {
+fn get_az_cli_path() -> Option<PathBuf> {
     let cmd_name = if cfg!(target_os = "windows") {
         "az.cmd"
     } else {
         "az"
     };
-    let cli_path = find_command(cmd_name).expect("Failed to find the Az CLI.  Please install the Az CLI to continue (https://aka.ms/containerapps/install-az-cli) or use --skip-azure to only process the Compose files.");
-    Ok(cli_path)
+    let cli_path = find_command(cmd_name);
+    debug!("Az CLI path: {:?}", &cli_path);
+    cli_path
 }
@@ -0,0 +1,150 @@
+use super::az_cli_path;
+use anyhow::Result;
+use duct::cmd;
+use std::io::Read;
+
+/// What an az CLI invocation printed and whether it succeeded.
+#[derive(Clone, Debug, Default)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub success: bool,
+}
+
+/// Runs az CLI commands.  `AzCliCommand` goes through a runner so tests can
+/// swap the `az` binary for canned output.
+pub trait CommandRunner: Send + Sync {
+    fn run(&self, args: &[&str]) -> Result<CommandOutput>;
+
+    /// Streams stderr while the command runs, for interactive commands like
+    /// `az login`.
+    fn stderr_reader(&self, args: &[&str]) -> Result<Box<dyn Read + Send>>;
+
+    /// Fails when the runner can't run az commands at all.
+    fn ensure_available(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the installed `az` binary.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcessRunner;
+
+impl CommandRunner for ProcessRunner {
+    fn run(&self, args: &[&str]) -> Result<CommandOutput> {
+        let output = cmd(az_cli_path()?, args)
+            .stderr_capture()
+            .stdout_capture()
+            .unchecked()
+            .run()?;
+        Ok(CommandOutput {
+            stdout: String::from_utf8(output.stdout)?,
+            stderr: String::from_utf8(output.stderr)?,
+            success: output.status.success(),
+        })
+    }
+
+    fn stderr_reader(&self, args: &[&str]) -> Result<Box<dyn Read + Send>> {
+        let reader = cmd(az_cli_path()?, args).stderr_to_stdout().reader()?;
+        Ok(Box::new(reader))
+    }
+
+    fn ensure_available(&self) -> Result<()> {
+        az_cli_path()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub use replay::ReplayRunner;
+
+#[cfg(test)]
+mod replay {
+    use super::{CommandOutput, CommandRunner};
+    use anyhow::{anyhow, Result};
+    use log::debug;
+    use std::collections::{HashMap, VecDeque};
+    use std::io::{Cursor, Read};
+    use std::sync::Mutex;
+
+    /// Serves recorded output for each argument vector instead of running `az`,
+    /// and records the commands it was asked to run.  Responses for the same
+    /// arguments are replayed in order, and the last one repeats.
+    #[derive(Default)]
+    pub struct ReplayRunner {
+        responses: Mutex<HashMap<Vec<String>, VecDeque<CommandOutput>>>,
+        calls: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl ReplayRunner {
+        /// Replays `stdout` from a successful run of `az <args>`.
+        pub fn with_response(self, args: &[&str], stdout: &str) -> Self {
+            self.with_output(
+                args,
+                CommandOutput {
+                    stdout: stdout.to_owned(),
+                    stderr: String::new(),
+                    success: true,
+                },
+            )
+        }
+
+        /// Replays `stderr` from a failed run of `az <args>`.
+        pub fn with_failure(self, args: &[&str], stderr: &str) -> Self {
+            self.with_output(
+                args,
+                CommandOutput {
+                    stdout: String::new(),
+                    stderr: stderr.to_owned(),
+                    success: false,
+                },
+            )
+        }
+
+        pub fn with_output(self, args: &[&str], output: CommandOutput) -> Self {
+            self.responses
+                .lock()
+                .unwrap()
+                .entry(to_key(args))
+                .or_default()
+                .push_back(output);
+            self
+        }
+
+        /// The argument vectors run so far, in order.
+        pub fn calls(&self) -> Vec<Vec<String>> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn next_output(&self, args: &[&str]) -> Result<CommandOutput> {
+            let key = to_key(args);
+            self.calls.lock().unwrap().push(key.clone());
+            let mut responses = self.responses.lock().unwrap();
+            let outputs = responses
+                .get_mut(&key)
+                .ok_or_else(|| anyhow!("No recorded output for `az {}`", args.join(" ")))?;
+            let output = if outputs.len() > 1 {
+                outputs.pop_front().unwrap()
+            } else {
+                outputs.front().cloned().unwrap_or_default()
+            };
+            debug!("Replaying `az {}`", args.join(" "));
+            Ok(output)
+        }
+    }
+
+    impl CommandRunner for ReplayRunner {
+        fn run(&self, args: &[&str]) -> Result<CommandOutput> {
+            self.next_output(args)
+        }
+
+        fn stderr_reader(&self, args: &[&str]) -> Result<Box<dyn Read + Send>> {
+            let output = self.next_output(args)?;
+            Ok(Box::new(Cursor::new(output.stderr.into_bytes())))
+        }
+    }
+
+    fn to_key(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+}
@@ -0,0 +1,231 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+
+/// The result of `az deployment ... what-if --no-pretty-print`.
+#[derive(Debug, Deserialize)]
+pub struct WhatIfResult {
+    pub status: Option<String>,
+    pub error: Option<WhatIfError>,
+    pub changes: Option<Vec<WhatIfChange>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WhatIfError {
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub enum WhatIfChangeType {
+    Create,
+    Delete,
+    Deploy,
+    Ignore,
+    Modify,
+    NoChange,
+    Unsupported,
+}
+
+/// A resource the deployment would change.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WhatIfChange {
+    #[serde(rename = "resourceId")]
+    pub resource_id: String,
+    #[serde(rename = "changeType")]
+    pub change_type: WhatIfChangeType,
+    pub delta: Option<Vec<WhatIfPropertyChange>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub enum PropertyChangeType {
+    Array,
+    Create,
+    Delete,
+    Modify,
+    NoEffect,
+}
+
+/// A change to one property of a resource.  Arrays and objects list the
+/// changes to their items as children.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WhatIfPropertyChange {
+    pub path: String,
+    #[serde(rename = "propertyChangeType")]
+    pub property_change_type: PropertyChangeType,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub children: Option<Vec<WhatIfPropertyChange>>,
+}
+
+impl WhatIfChange {
+    /// The resource type and name, without the subscription and resource group.
+    pub fn resource_name(&self) -> &str {
+        match self.resource_id.rsplit_once("/providers/") {
+            Some((_, name)) => name,
+            None => &self.resource_id,
+        }
+    }
+
+    /// Every changed property, with paths from the resource root.
+    pub fn property_changes(&self) -> Vec<(String, &WhatIfPropertyChange)> {
+        let mut changes = Vec::new();
+        if let Some(delta) = self.delta.as_ref() {
+            for change in delta.iter() {
+                flatten_property_change(change.path.to_owned(), change, &mut changes);
+            }
+        }
+        changes
+    }
+}
+
+impl fmt::Display for WhatIfChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (symbol, description) = match self.change_type {
+            WhatIfChangeType::Create => ("+", "create"),
+            WhatIfChangeType::Delete => ("-", "delete"),
+            WhatIfChangeType::Modify => ("~", "modify"),
+            WhatIfChangeType::Deploy => ("!", "deploy"),
+            WhatIfChangeType::NoChange => ("=", "no change"),
+            WhatIfChangeType::Ignore => ("*", "ignore"),
+            WhatIfChangeType::Unsupported => ("x", "unsupported"),
+        };
+        write!(f, "{} {} ({})", symbol, self.resource_name(), description)
+    }
+}
+
+fn flatten_property_change<'a>(
+    path: String,
+    change: &'a WhatIfPropertyChange,
+    changes: &mut Vec<(String, &'a WhatIfPropertyChange)>,
+) {
+    match change.children.as_ref() {
+        Some(children) if !children.is_empty() => {
+            for child in children.iter() {
+                let child_path = if change.property_change_type == PropertyChangeType::Array {
+                    format!("{}[{}]", path, child.path)
+                } else {
+                    format!("{}.{}", path, child.path)
+                };
+                flatten_property_change(child_path, child, changes);
+            }
+        }
+        _ if change.property_change_type == PropertyChangeType::NoEffect => {}
+        _ => changes.push((path, change)),
+    }
+}
+
+/// Prints the changes a deployment would make, leaving out unchanged
+/// resources.
+pub fn print_what_if_changes(name: &str, changes: &[WhatIfChange]) {
+    let count = |change_type: WhatIfChangeType| {
+        changes
+            .iter()
+            .filter(|c| c.change_type == change_type)
+            .count()
+    };
+    println!(
+        "{}: {} to create, {} to modify, {} to delete.",
+        name,
+        count(WhatIfChangeType::Create),
+        count(WhatIfChangeType::Modify),
+        count(WhatIfChangeType::Delete)
+    );
+    for change in changes
+        .iter()
+        .filter(|c| c.change_type != WhatIfChangeType::NoChange)
+    {
+        println!("  {}", change);
+        for (path, property) in change.property_changes() {
+            let before = property.before.as_ref().map(|v| v.to_string());
+            let after = property.after.as_ref().map(|v| v.to_string());
+            match property.property_change_type {
+                PropertyChangeType::Create => {
+                    println!("      + {}: {}", path, after.unwrap_or_default())
+                }
+                PropertyChangeType::Delete => {
+                    println!("      - {}: {}", path, before.unwrap_or_default())
+                }
+                _ => println!(
+                    "      ~ {}: {} -> {}",
+                    path,
+                    before.unwrap_or_default(),
+                    after.unwrap_or_default()
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHAT_IF_OUTPUT: &str = r#"{
+  "status": "Succeeded",
+  "error": null,
+  "changes": [
+    {
+      "resourceId": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/containerApps/web",
+      "changeType": "Modify",
+      "delta": [
+        {
+          "path": "properties.template.containers",
+          "propertyChangeType": "Array",
+          "before": null,
+          "after": null,
+          "children": [
+            {
+              "path": "0",
+              "propertyChangeType": "Modify",
+              "before": null,
+              "after": null,
+              "children": [
+                {
+                  "path": "image",
+                  "propertyChangeType": "Modify",
+                  "before": "contoso/web:1.0",
+                  "after": "contoso/web:1.1",
+                  "children": null
+                }
+              ]
+            }
+          ]
+        },
+        {
+          "path": "properties.latestRevisionName",
+          "propertyChangeType": "NoEffect",
+          "before": "web--abc123",
+          "after": null,
+          "children": null
+        }
+      ]
+    },
+    {
+      "resourceId": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/containerApps/db",
+      "changeType": "Create",
+      "delta": null
+    }
+  ]
+}"#;
+
+    #[test]
+    fn what_if_changes_are_flattened_per_property() {
+        let result: WhatIfResult = serde_json::from_str(WHAT_IF_OUTPUT).unwrap();
+        let changes = result.changes.unwrap();
+
+        assert_eq!(
+            changes[0].to_string(),
+            "~ Microsoft.App/containerApps/web (modify)"
+        );
+        let properties = changes[0].property_changes();
+        assert_eq!(properties.len(), 1);
+        assert_eq!(properties[0].0, "properties.template.containers[0].image");
+        assert_eq!(properties[0].1.after.as_ref().unwrap(), "contoso/web:1.1");
+        assert_eq!(
+            changes[1].to_string(),
+            "+ Microsoft.App/containerApps/db (create)"
+        );
+        assert!(changes[1].property_changes().is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::commands::WorkflowPipeline;
+use crate::containerapps::ApiVersion;
 use clap::{arg_enum, App, Arg, SubCommand};
 
 arg_enum! {
@@ -10,6 +12,36 @@ arg_enum! {
     }
 }
 
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    pub enum RevisionMode {
+        single,
+        multiple,
+    }
+}
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    pub enum OutputFormat {
+        yaml,
+        arm,
+        bicep,
+        terraform,
+        azd,
+    }
+}
+
+arg_enum! {
+    #[allow(non_camel_case_types)]
+    pub enum TemplateParameter {
+        environment,
+        location,
+        secrets,
+        env,
+        images,
+    }
+}
+
 arg_enum! {
     pub enum Transport {
         Auto,
@@ -26,6 +58,11 @@ pub fn get_app_cli<'a, 'b>(version: &'b str) -> App<'a, 'b> {
         .subcommand(convert_subcommand())
         .subcommand(deploy_subcommand())
         .subcommand(logs_subcommand())
+        .subcommand(migrate_subcommand())
+        .subcommand(to_compose_subcommand())
+        .subcommand(export_subcommand())
+        .subcommand(diff_subcommand())
+        .subcommand(generate_workflow_subcommand())
 }
 
 fn convert_subcommand<'a, 'b>() -> App<'a, 'b> {
@@ -34,9 +71,28 @@ fn convert_subcommand<'a, 'b>() -> App<'a, 'b> {
         .about("Converts a Docker Compose file into Azure ContainerApps configurations.")
         .args(&standard_args)
         .arg(containerapps_environment_id_arg())
+        .arg(containerapps_environment_name_arg())
+        .arg(skip_azure_arg())
         .arg(resource_group_name_arg())
         .arg(location_arg())
         .arg(transport_arg())
+        .arg(revision_mode_arg())
+        .arg(revision_suffix_arg())
+        .arg(traffic_arg())
+        .arg(identity_arg())
+        .arg(registry_server_arg())
+        .arg(registry_identity_arg())
+        .arg(key_vault_arg())
+        .arg(domain_arg())
+        .arg(certificate_arg())
+        .arg(certificate_password_arg())
+        .arg(out_dir_arg())
+        .arg(name_template_arg())
+        .arg(format_arg())
+        .arg(single_file_arg())
+        .arg(combined_arg())
+        .arg(template_parameters_arg())
+        .arg(api_version_arg())
 }
 
 fn deploy_subcommand<'a, 'b>() -> App<'a, 'b> {
@@ -50,6 +106,22 @@ fn deploy_subcommand<'a, 'b>() -> App<'a, 'b> {
         .arg(resource_group_name_arg())
         .arg(location_arg())
         .arg(transport_arg())
+        .arg(revision_mode_arg())
+        .arg(revision_suffix_arg())
+        .arg(traffic_arg())
+        .arg(identity_arg())
+        .arg(registry_server_arg())
+        .arg(registry_identity_arg())
+        .arg(key_vault_arg())
+        .arg(domain_arg())
+        .arg(certificate_arg())
+        .arg(certificate_password_arg())
+        .arg(out_dir_arg())
+        .arg(name_template_arg())
+        .arg(combined_arg())
+        .arg(template_parameters_arg())
+        .arg(api_version_arg())
+        .arg(dry_run_arg())
 }
 
 fn logs_subcommand<'a, 'b>() -> App<'a, 'b> {
@@ -63,18 +135,149 @@ fn logs_subcommand<'a, 'b>() -> App<'a, 'b> {
         .arg(containerapps_name_arg())
 }
 
+fn migrate_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("migrate")
+        .about("Upgrades an Azure ContainerApps yaml configuration file to a newer API version.")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Path to the Azure ContainerApps yaml configuration file to migrate.")
+                .index(1)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the migrated configuration.  Defaults to updating INPUT in place.")
+                .index(2),
+        )
+        .arg(api_version_arg())
+}
+
+fn diff_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("diff")
+        .about("Compares the ContainerApps converted from a Docker Compose file with the deployed apps.  Exits with 2 when they differ.")
+        .arg(input_arg())
+        .arg(project_directory_arg())
+        .arg(containerapps_environment_id_arg())
+        .arg(resource_group_name_arg())
+        .arg(location_arg())
+        .arg(transport_arg())
+        .arg(revision_mode_arg())
+        .arg(identity_arg())
+        .arg(registry_server_arg())
+        .arg(registry_identity_arg())
+        .arg(key_vault_arg())
+        .arg(api_version_arg())
+}
+
+fn to_compose_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("to-compose")
+        .about("Converts Azure ContainerApps yaml configurations back into a Docker Compose file.")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Paths to the Azure ContainerApps yaml configuration files to convert.")
+                .index(1)
+                .multiple(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the Docker Compose file, or - to write it to stdout.")
+                .short("o")
+                .long("output")
+                .takes_value(true)
+                .default_value("./docker-compose.yml"),
+        )
+}
+
+fn export_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("export")
+        .about("Exports a deployed Azure ContainerApp to a yaml configuration file.")
+        .arg(
+            Arg::with_name("ContainerAppName")
+                .long("name")
+                .help("Name of the ContainerApp to export.")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(resource_group_name_arg())
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the configuration, or - to write it to stdout.  Defaults to {name}-containerapps.yml.")
+                .index(1),
+        )
+        .arg(api_version_arg())
+}
+
+fn generate_workflow_subcommand<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("generate-workflow")
+        .about("Generates a CI workflow that builds the images and deploys the ContainerApps from a Docker Compose file.")
+        .arg(
+            Arg::with_name("INPUT")
+                .help("Path to read the Docker Compose yaml configuration file.")
+                .index(1)
+                .default_value("./docker-compose.yml"),
+        )
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Path to write the workflow.  Defaults to .github/workflows/containerapps.yml for GitHub and azure-pipelines.yml for Azure Pipelines.")
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("Pipeline")
+                .long("pipeline")
+                .help("CI system to generate the workflow for.")
+                .takes_value(true)
+                .default_value("github")
+                .possible_values(&WorkflowPipeline::variants()),
+        )
+        .arg(
+            Arg::with_name("Branch")
+                .long("branch")
+                .help("Branch that triggers the workflow.")
+                .takes_value(true)
+                .default_value("main"),
+        )
+        .arg(containerapps_environment_id_arg())
+        .arg(resource_group_name_arg())
+        .arg(location_arg())
+        .arg(transport_arg())
+        .arg(revision_mode_arg())
+        .arg(revision_suffix_arg())
+        .arg(traffic_arg())
+        .arg(identity_arg())
+        .arg(registry_server_arg())
+        .arg(registry_identity_arg())
+        .arg(key_vault_arg())
+        .arg(domain_arg())
+        .arg(certificate_arg())
+        .arg(combined_arg())
+        .arg(template_parameters_arg())
+        .arg(api_version_arg())
+}
+
 fn standard_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
-    vec!(
-        Arg::with_name("INPUT")
-            .help("Path to read the Docker Compose yaml configuration file.")
-            .index(1)
-            .default_value("./docker-compose.yml"),
-
-        Arg::with_name("OUTPUT")
-                .help("Base file name to write the Azure ContainerApps yaml configuration files.  Output file name will be prefixed with the service name.")
-                .index(2)
-                .default_value("containerapps.yml"),
-    )
+    vec![input_arg(), project_directory_arg(), output_arg()]
+}
+
+fn input_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("INPUT")
+        .help("Path to read the Docker Compose yaml configuration file.  Use - to read it from stdin.")
+        .index(1)
+        .default_value("./docker-compose.yml")
+}
+
+fn project_directory_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("ProjectDirectory")
+        .long("project-directory")
+        .help("Directory that relative paths in the Docker Compose file resolve against.  Defaults to the directory of INPUT, or the current directory when reading from stdin.")
+        .takes_value(true)
+}
+
+fn output_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("OUTPUT")
+        .help("Base file name to write the Azure ContainerApps yaml configuration files.  Output file name will be prefixed with the service name.  Use - to write the yaml to stdout.")
+        .index(2)
+        .default_value("containerapps.yml")
 }
 
 fn containerapps_environment_name_arg<'a, 'b>() -> Arg<'a, 'b> {
@@ -96,6 +299,8 @@ fn containerapps_environment_id_arg<'a, 'b>() -> Arg<'a, 'b> {
             "resourceid",
             "kubeEnvironmentId",
             "kube-environment-id",
+            "environmentId",
+            "environment-id",
         ])
         .env("CONTAINERAPPS_ENVIRONMENT_ID")
         .takes_value(true)
@@ -147,6 +352,157 @@ fn transport_arg<'a, 'b>() -> Arg<'a, 'b> {
         .possible_values(&Transport::variants())
 }
 
+fn combined_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("Combined").long("combined").help(
+        "Puts every service in one ARM template so the project deploys as a single deployment.",
+    )
+}
+
+fn dry_run_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("DryRun").long("dry-run").help(
+        "Previews the deployment with az deployment what-if and lists the resources it would create, modify or delete without applying anything.",
+    )
+}
+
+fn skip_azure_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("SkipAzure")
+        .long("skip-azure")
+        .aliases(&["offline"])
+        .help("Only processes the Compose file and never calls Azure, so the Az CLI isn't needed.  The ContainerApps environment id isn't looked up from --containerapps-environment-name.")
+}
+
+fn template_parameters_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("TemplateParameters")
+        .long("template-parameters")
+        .help("Values to lift into ARM template parameters, written to a matching .parameters.json file.  Defaults to environment,location,secrets.  Secrets are always lifted.")
+        .takes_value(true)
+        .multiple(true)
+        .use_delimiter(true)
+        .possible_values(&TemplateParameter::variants())
+}
+
+fn api_version_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("ApiVersion")
+        .long("api-version")
+        .help("ContainerApps API version to write.  2021-03-01 targets the Microsoft.Web preview, later versions target Microsoft.App.")
+        .takes_value(true)
+        .default_value("2023-05-01")
+        .possible_values(&ApiVersion::variants())
+}
+
+fn out_dir_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("OutDir")
+        .long("out-dir")
+        .help("Directory to write the generated files to.  Defaults to the current directory.")
+        .takes_value(true)
+}
+
+fn name_template_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("NameTemplate")
+        .long("name-template")
+        .help("File name for each service's configuration, relative to --out-dir.  {service}, {env} and {format} are replaced with the service name, the ContainerApps environment name and the output format.  Defaults to {service}-OUTPUT.")
+        .takes_value(true)
+}
+
+fn single_file_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("SingleFile")
+        .long("single-file")
+        .help("Writes every service to OUTPUT as one multi-document yaml file.")
+}
+
+fn format_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("Format")
+        .long("format")
+        .help("Output format for the ContainerApps configurations.")
+        .takes_value(true)
+        .default_value("yaml")
+        .possible_values(&OutputFormat::variants())
+}
+
+fn revision_mode_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("RevisionMode")
+        .long("revision-mode")
+        .help("ContainerApps revision mode.  Multiple keeps previous revisions active for traffic splitting.")
+        .takes_value(true)
+        .possible_values(&RevisionMode::variants())
+}
+
+fn revision_suffix_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("RevisionSuffix")
+        .long("revision-suffix")
+        .help("Suffix for the revision created by this configuration.  {service}, {hash} (of the rendered template) and {git} (the current commit) are replaced.")
+        .takes_value(true)
+}
+
+fn traffic_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("Traffic")
+        .long("traffic")
+        .help("Traffic weights by revision, like latest=20,myapp--blue=80.  Weights must add up to 100.")
+        .takes_value(true)
+}
+
+fn identity_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("Identity")
+        .long("identity")
+        .help("Managed identity for a service, like web=system or web=<user assigned identity resource id>.  Can be repeated.")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+}
+
+fn registry_server_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("RegistryServer")
+        .long("registry-server")
+        .help("Container registry (like myregistry.azurecr.io) to pull images from with a managed identity.")
+        .takes_value(true)
+        .requires("RegistryIdentity")
+        .env("REGISTRY_SERVER")
+}
+
+fn registry_identity_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("RegistryIdentity")
+        .long("registry-identity")
+        .help("Managed identity used to pull from the registry server, either system or a user assigned identity resource id.")
+        .takes_value(true)
+        .env("REGISTRY_IDENTITY")
+}
+
+fn key_vault_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("KeyVault")
+        .long("keyvault")
+        .help("Reads an environment variable from Key Vault, like DB_PASSWORD=https://myvault.vault.azure.net/secrets/db-password.  Can be repeated.")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .aliases(&["key-vault"])
+}
+
+fn domain_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("Domain")
+        .long("domain")
+        .help("Custom domain for a service, like web=www.contoso.com.  Can be repeated.")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+}
+
+fn certificate_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("Certificate")
+        .long("certificate")
+        .help("Path to the PFX certificate for the custom domains.")
+        .takes_value(true)
+        .env("CERTIFICATE_PATH")
+}
+
+fn certificate_password_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("CertificatePassword")
+        .long("certificate-password")
+        .help("Password for the PFX certificate.")
+        .takes_value(true)
+        .hide_env_values(true)
+        .env("CERTIFICATE_PASSWORD")
+}
+
 fn max_records_arg<'a, 'b>() -> Arg<'a, 'b> {
     Arg::with_name("NumberOfResults")
         .long("number-of-results")
@@ -1,15 +1,33 @@
+use super::OutputManifest;
 use crate::azure::*;
-use crate::compose::{read_compose_file, Compose};
+use crate::compose::{
+    get_project_directory, read_compose_document, read_compose_document_from_reader, Compose,
+    ComposeDocument, ServiceBuild,
+};
 use crate::containerapps::{
-    write_containerapps_arm_template, write_to_containerapps_file, ContainerAppConfig, Transport,
+    get_certificates_arm_template, get_combined_secret_parameters, get_containerapps_yaml,
+    get_dapr_components_arm_template, get_parameter_name, get_secret_parameters,
+    write_arm_parameters_file, write_azd_project, write_certificates_arm_template,
+    write_combined_containerapps_arm_template, write_containerapps_arm_template,
+    write_containerapps_bicep_main, write_containerapps_bicep_module,
+    write_containerapps_terraform, write_dapr_components_arm_template, write_to_containerapps_file,
+    ApiVersion, AzdService, Certificate, ContainerAppConfig, DaprComponent, OutputFormat,
+    RevisionMode, SecureParametersFile, TemplateParameter, TrafficConfiguration, Transport,
+};
+use crate::convert::{
+    convert_to_containerapps, get_certificates_from_service, get_dapr_components_from_compose,
+    get_extension_from_service, is_daprd_service, ContainerAppsExtension,
 };
-use crate::convert::convert_to_containerapps;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use dialoguer::Input;
-use log::{debug, trace};
+use log::{debug, trace, warn};
+use std::collections::BTreeMap;
 use std::env;
-use std::path::PathBuf;
+use std::fs::{create_dir_all, File};
+use std::io::{prelude::*, stdin};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct ConvertedComposeConfiguration {
@@ -17,25 +35,68 @@ pub struct ConvertedComposeConfiguration {
     pub resource_group: String,
     pub path: PathBuf,
     pub url: Option<String>,
+    pub certificates: Vec<Certificate>,
+    pub depends_on: Vec<String>,
+    pub build: Option<ServiceBuild>,
 }
 
+#[derive(Default)]
 pub struct ContainerAppsConfigurationData<'a> {
     pub resource_group: &'a str,
     pub location: &'a str,
     pub containerapps_environment_id: &'a str,
     pub transport: Transport,
+    pub revision_mode: RevisionMode,
+    pub revision_suffix: Option<&'a str>,
+    pub traffic: Vec<TrafficConfiguration>,
+    pub identities: Vec<String>,
+    pub registry_server: Option<&'a str>,
+    pub registry_identity: Option<&'a str>,
+    pub key_vault_secrets: Vec<(String, String)>,
+    pub domains: Vec<String>,
+    pub certificate: Option<&'a str>,
+    pub certificate_password: Option<&'a str>,
+    /// Certificates are named after their path in the Compose project.
+    pub project_directory: PathBuf,
+    pub extension: ContainerAppsExtension,
+    /// Set when stdin holds the Compose file, so nothing can be prompted for.
+    pub no_prompts: bool,
 }
 
 #[derive(Default)]
 pub struct ConvertComposeCommand {
     compose_path: PathBuf,
+    project_directory: Option<PathBuf>,
     containerapps_path: PathBuf,
     containerapps_configs: Vec<ConvertedComposeConfiguration>,
+    dapr_components: Vec<DaprComponent>,
     resource_group: Option<String>,
     location: Option<String>,
     containerapps_environment_id: Option<String>,
+    containerapps_environment_name: Option<String>,
     transport: Transport,
+    revision_mode: RevisionMode,
+    revision_suffix: Option<String>,
+    traffic: Option<String>,
+    identities: Vec<String>,
+    registry_server: Option<String>,
+    registry_identity: Option<String>,
+    key_vault_secrets: Vec<String>,
+    domains: Vec<String>,
+    certificate: Option<String>,
+    certificate_password: Option<String>,
+    format: OutputFormat,
+    out_dir: Option<PathBuf>,
+    name_template: Option<String>,
+    single_file: bool,
+    stdout: bool,
+    combined: bool,
+    template_parameters: Option<Vec<String>>,
+    api_version: ApiVersion,
     deploy_azure: bool,
+    dry_run: bool,
+    skip_azure: bool,
+    backend: Option<Arc<dyn AzureBackend>>,
 }
 
 impl ConvertComposeCommand {
@@ -46,9 +107,17 @@ impl ConvertComposeCommand {
         self
     }
 
+    pub fn with_project_directory(mut self, project_directory: Option<&str>) -> Self {
+        self.project_directory = project_directory.map(PathBuf::from);
+        self
+    }
+
     pub fn with_containerapps_path(mut self, containerapps_file_path: &str) -> Self {
-        if let Ok(p) = PathBuf::from_str(containerapps_file_path) {
-            self.containerapps_path = p;
+        if containerapps_file_path == "-" {
+            self.stdout = true;
+            self.containerapps_path = PathBuf::from("containerapps.yml");
+        } else {
+            self.containerapps_path = PathBuf::from(containerapps_file_path);
         }
         self
     }
@@ -71,29 +140,339 @@ impl ConvertComposeCommand {
         self
     }
 
-    pub fn with_transport(mut self, transport: Option<&str>) -> Self {
+    /// Looks up the environment id from the resource group and this name
+    /// when no id is given.
+    pub fn with_containerapps_environment_name(
+        mut self,
+        containerapps_environment_name: Option<&str>,
+    ) -> Self {
+        self.containerapps_environment_name = containerapps_environment_name.map(|v| v.to_string());
+        self
+    }
+
+    pub fn with_transport(mut self, transport: Option<&str>) -> Result<Self> {
         if let Some(t) = transport {
-            self.transport = Transport::from_str(t).unwrap();
+            self.transport = Transport::from_str(t)?;
         } else {
             self.transport = Transport::default();
         };
+        Ok(self)
+    }
+
+    pub fn with_revision_mode(mut self, revision_mode: Option<&str>) -> Result<Self> {
+        if let Some(r) = revision_mode {
+            self.revision_mode = RevisionMode::from_str(r)?;
+        } else {
+            self.revision_mode = RevisionMode::default();
+        };
+        Ok(self)
+    }
+
+    pub fn with_revision_suffix(mut self, revision_suffix: Option<&str>) -> Self {
+        self.revision_suffix = revision_suffix.map(|v| v.to_string());
+        self
+    }
+
+    pub fn with_traffic(mut self, traffic: Option<&str>) -> Self {
+        self.traffic = traffic.map(|v| v.to_string());
+        self
+    }
+
+    pub fn with_identities(mut self, identities: Option<Vec<&str>>) -> Self {
+        self.identities = identities
+            .unwrap_or_default()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        self
+    }
+
+    pub fn with_registry_server(mut self, registry_server: Option<&str>) -> Self {
+        self.registry_server = registry_server.map(|v| v.to_string());
+        self
+    }
+
+    pub fn with_registry_identity(mut self, registry_identity: Option<&str>) -> Self {
+        self.registry_identity = registry_identity.map(|v| v.to_string());
+        self
+    }
+
+    pub fn with_key_vault_secrets(mut self, key_vault_secrets: Option<Vec<&str>>) -> Self {
+        self.key_vault_secrets = key_vault_secrets
+            .unwrap_or_default()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        self
+    }
+
+    pub fn with_domains(mut self, domains: Option<Vec<&str>>) -> Self {
+        self.domains = domains
+            .unwrap_or_default()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        self
+    }
+
+    pub fn with_certificate(mut self, certificate: Option<&str>) -> Self {
+        self.certificate = certificate.map(|v| v.to_string());
+        self
+    }
+
+    pub fn with_certificate_password(mut self, certificate_password: Option<&str>) -> Self {
+        self.certificate_password = certificate_password.map(|v| v.to_string());
+        self
+    }
+
+    pub fn with_format(mut self, format: Option<&str>) -> Result<Self> {
+        if let Some(f) = format {
+            self.format = OutputFormat::from_str(f)?;
+        } else {
+            self.format = OutputFormat::default();
+        };
+        Ok(self)
+    }
+
+    pub fn with_out_dir(mut self, out_dir: Option<&str>) -> Self {
+        self.out_dir = out_dir.map(PathBuf::from);
         self
     }
 
+    pub fn with_name_template(mut self, name_template: Option<&str>) -> Self {
+        self.name_template = name_template.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_single_file(mut self, single_file: bool) -> Self {
+        self.single_file = single_file;
+        self
+    }
+
+    pub fn with_combined(mut self, combined: bool) -> Self {
+        self.combined = combined;
+        self
+    }
+
+    pub fn with_template_parameters(mut self, template_parameters: Option<Vec<&str>>) -> Self {
+        self.template_parameters =
+            template_parameters.map(|v| v.iter().map(|p| p.to_string()).collect());
+        self
+    }
+
+    pub fn with_api_version(mut self, api_version: Option<&str>) -> Result<Self> {
+        if let Some(v) = api_version {
+            self.api_version = ApiVersion::from_str(v)?;
+        } else {
+            self.api_version = ApiVersion::default();
+        };
+        Ok(self)
+    }
+
     pub fn with_deploy_azure(mut self, deploy_azure: bool) -> Self {
         self.deploy_azure = deploy_azure;
         self
     }
 
+    /// Previews the deployment with what-if instead of applying it.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Never calls Azure, so converting works without the az CLI.
+    pub fn with_skip_azure(mut self, skip_azure: bool) -> Self {
+        self.skip_azure = skip_azure;
+        self
+    }
+
+    /// Runs az CLI commands through `runner` rather than the `az` binary.
+    #[cfg(test)]
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.backend = Some(Arc::new(AzCliBackend::default().with_runner(runner)));
+        self
+    }
+
     pub fn convert(mut self) -> Result<Self> {
-        let compose_file = self.get_docker_compose_file()?;
-        self.containerapps_configs = self.convert_services_to_containerapps(compose_file)?;
+        if self.stdout && self.format != OutputFormat::Yaml {
+            return Err(anyhow!(
+                "Only the yaml format can be written to stdout, not {}",
+                self.format
+            ));
+        }
+        if self.single_file && self.format != OutputFormat::Yaml {
+            return Err(anyhow!(
+                "--single-file only applies to the yaml format, use --combined for a single ARM template"
+            ));
+        }
+        if self.combined && !self.deploy_azure && self.format != OutputFormat::Arm {
+            return Err(anyhow!(
+                "--combined only applies to the arm format and to deploy, not {}",
+                self.format
+            ));
+        }
+        if let Some(template) = self.name_template.as_ref() {
+            if !template.contains("{service}") {
+                return Err(anyhow!(
+                    "--name-template needs {{service}}, otherwise every service is written to {}",
+                    template
+                ));
+            }
+        }
+        if let Some(out_dir) = self.out_dir.as_ref() {
+            create_dir_all(out_dir)?;
+        }
+        self.lookup_containerapps_environment_id()?;
+        let compose_document = self.get_docker_compose_document()?;
+        let compose_file = compose_document.compose()?;
+        self.dapr_components = self.convert_dapr_components(&compose_document, &compose_file)?;
+        self.containerapps_configs =
+            self.convert_services_to_containerapps(&compose_document, compose_file)?;
+        if self.deploy_azure && self.combined {
+            self.deploy_combined_containerapps()?;
+        }
         Ok(self)
     }
 
     pub fn write(self) -> Result<Self> {
+        let mut manifest = OutputManifest::new(&self.format, self.api_version);
+        // With stdout, the templates are YAML documents of their own.
+        if !self.dapr_components.is_empty() && self.stdout {
+            let (template, _) =
+                get_dapr_components_arm_template(&self.dapr_components, self.api_version)?;
+            println!("---\n{}", template);
+        } else if !self.dapr_components.is_empty() {
+            let dapr_components_path = self.dapr_components_path();
+            write_dapr_components_arm_template(
+                &dapr_components_path,
+                &self.dapr_components,
+                self.api_version,
+            )?;
+            manifest.add(&dapr_components_path, None);
+        }
+        let mut certificates: Vec<Certificate> = Vec::new();
         for config in self.containerapps_configs.iter() {
-            write_to_containerapps_file(&config.path, &config.configuration)?
+            for certificate in config.certificates.iter() {
+                if !certificates.iter().any(|c| c.name == certificate.name) {
+                    certificates.push(certificate.clone());
+                }
+            }
+        }
+        if !certificates.is_empty() && self.stdout {
+            println!(
+                "---\n{}",
+                get_certificates_arm_template(&certificates, self.api_version)?
+            );
+        } else if !certificates.is_empty() {
+            let certificates_path = self.certificates_path();
+            write_certificates_arm_template(&certificates_path, &certificates, self.api_version)?;
+            manifest.add(&certificates_path, None);
+        }
+        match self.format {
+            OutputFormat::Yaml if self.stdout || self.single_file => {
+                let mut output_content = String::new();
+                for config in self.containerapps_configs.iter() {
+                    output_content.push_str(&get_containerapps_yaml(
+                        &config.configuration,
+                        self.api_version,
+                    )?);
+                }
+                if self.stdout {
+                    print!("{}", output_content);
+                } else {
+                    let output_path = self.output_path(&self.containerapps_path);
+                    debug!(
+                        "Writing the ContainerApps configurations to {}.",
+                        output_path.display()
+                    );
+                    let mut file = File::create(&output_path)?;
+                    file.write_all(output_content.into_bytes().as_ref())?;
+                    manifest.add(&output_path, None);
+                }
+            }
+            OutputFormat::Yaml => {
+                for config in self.containerapps_configs.iter() {
+                    write_to_containerapps_file(
+                        &config.path,
+                        &config.configuration,
+                        self.api_version,
+                    )?;
+                    manifest.add(&config.path, Some(&config.configuration.name));
+                }
+            }
+            OutputFormat::Arm if self.combined => {
+                let template_path = self.combined_path();
+                let parameter_values = write_combined_containerapps_arm_template(
+                    &template_path,
+                    &self.combined_apps(),
+                    &self.template_parameters()?,
+                    self.api_version,
+                )?;
+                let parameters_path = get_parameters_path(&template_path);
+                write_arm_parameters_file(&parameters_path, &parameter_values)?;
+                manifest.add(&template_path, None);
+                manifest.add(&parameters_path, None);
+            }
+            OutputFormat::Arm => {
+                for config in self.containerapps_configs.iter() {
+                    let template_path = config.path.with_extension("json");
+                    let parameter_values = write_containerapps_arm_template(
+                        &template_path,
+                        &config.configuration,
+                        &self.template_parameters()?,
+                        self.api_version,
+                    )?;
+                    let parameters_path = get_parameters_path(&template_path);
+                    write_arm_parameters_file(&parameters_path, &parameter_values)?;
+                    manifest.add(&template_path, Some(&config.configuration.name));
+                    manifest.add(&parameters_path, Some(&config.configuration.name));
+                }
+            }
+            OutputFormat::Bicep => {
+                let main_path = self.output_path(&self.containerapps_path.with_extension("bicep"));
+                let main_directory = main_path.parent().unwrap_or_else(|| Path::new(""));
+                let module_paths: Vec<PathBuf> = self
+                    .containerapps_configs
+                    .iter()
+                    .map(|c| c.path.with_extension("bicep"))
+                    .collect();
+                let mut modules: Vec<(&Path, &ContainerAppConfig)> = Vec::new();
+                for (config, module_path) in self.containerapps_configs.iter().zip(&module_paths) {
+                    debug!("Writing a Bicep module to {}.", module_path.display());
+                    write_containerapps_bicep_module(
+                        module_path,
+                        &config.configuration,
+                        self.api_version,
+                    )?;
+                    manifest.add(module_path, Some(&config.configuration.name));
+                    modules.push((
+                        module_path
+                            .strip_prefix(main_directory)
+                            .unwrap_or(module_path),
+                        &config.configuration,
+                    ));
+                }
+                write_containerapps_bicep_main(&main_path, &modules)?;
+                manifest.add(&main_path, None);
+            }
+            OutputFormat::Terraform => {
+                let configs: Vec<&ContainerAppConfig> = self
+                    .containerapps_configs
+                    .iter()
+                    .map(|c| &c.configuration)
+                    .collect();
+                let terraform_path = self.output_path(&self.containerapps_path.with_extension(""));
+                write_containerapps_terraform(&terraform_path, &configs)?;
+                manifest.add(&terraform_path, None);
+            }
+            OutputFormat::Azd => {
+                let project_directory = self.write_azd_project()?;
+                manifest.add(&project_directory.join("azure.yaml"), None);
+                manifest.add(&project_directory.join("infra"), None);
+            }
+        }
+        if !self.stdout {
+            manifest.write(&self.manifest_path())?;
         }
         Ok(self)
     }
@@ -102,36 +481,264 @@ impl ConvertComposeCommand {
         self.containerapps_configs.to_vec()
     }
 
-    fn get_docker_compose_file(&self) -> Result<Compose> {
+    fn get_docker_compose_document(&self) -> Result<ComposeDocument> {
         trace!("Starting the conversion from Docker Compose to ContainerApps configuration.");
-        debug!(
-            "Reading the Docker Compose file from {}",
-            &self.compose_path.display()
-        );
-        read_compose_file(&self.compose_path)
+        if self.compose_path == Path::new("-") {
+            debug!("Reading the Docker Compose file from stdin");
+            read_compose_document_from_reader(stdin().lock(), &self.project_directory())
+        } else {
+            debug!(
+                "Reading the Docker Compose file from {}",
+                &self.compose_path.display()
+            );
+            match self.project_directory.as_ref() {
+                Some(p) => read_compose_document_from_reader(File::open(&self.compose_path)?, p),
+                None => read_compose_document(&self.compose_path),
+            }
+        }
+    }
+
+    /// Relative paths in the Compose file resolve against `--project-directory`,
+    /// or the directory the Compose file is in.  Stdin has no directory, so the
+    /// current one is used.
+    fn project_directory(&self) -> PathBuf {
+        match self.project_directory.as_ref() {
+            Some(p) => p.to_path_buf(),
+            None if self.compose_path == Path::new("-") => PathBuf::from("."),
+            None => get_project_directory(&self.compose_path),
+        }
+    }
+
+    /// Places a generated file in `--out-dir`, or the current directory.
+    fn output_path(&self, path: &Path) -> PathBuf {
+        match self.out_dir.as_ref() {
+            Some(out_dir) => out_dir.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Path of a service's configuration, from `--name-template` when given.
+    /// Other formats swap the extension.
+    fn service_path(&self, service_name: &str, environment_name: &str) -> PathBuf {
+        let file_name = match self.name_template.as_ref() {
+            Some(template) => template
+                .replace("{service}", service_name)
+                .replace("{env}", environment_name)
+                .replace("{format}", &self.format.to_string().to_lowercase()),
+            None => format!("{}-{}", service_name, &self.containerapps_path.display()),
+        };
+        self.output_path(Path::new(&file_name))
+    }
+
+    fn dapr_components_path(&self) -> PathBuf {
+        self.output_path(&PathBuf::from(format!(
+            "dapr-components-{}",
+            &self.containerapps_path.display()
+        )))
+        .with_extension("json")
+    }
+
+    fn certificates_path(&self) -> PathBuf {
+        self.output_path(&PathBuf::from(format!(
+            "certificates-{}",
+            &self.containerapps_path.display()
+        )))
+        .with_extension("json")
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.output_path(&self.containerapps_path.with_extension("manifest.json"))
+    }
+
+    /// azd expects `azure.yaml` at the root of the project, so it goes next to
+    /// the Compose file where the build contexts are relative to.
+    fn write_azd_project(&self) -> Result<PathBuf> {
+        let project_directory = self.project_directory();
+        let project_name = project_directory
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.file_name().map(|f| f.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "containerapps".to_string());
+        let environment_name =
+            get_azd_environment_name(self.containerapps_environment_id.as_deref(), &project_name);
+        let location = self
+            .containerapps_configs
+            .first()
+            .map(|c| c.configuration.location.as_str())
+            .unwrap_or_default();
+        let services: Vec<AzdService> = self
+            .containerapps_configs
+            .iter()
+            .map(|c| AzdService {
+                config: &c.configuration,
+                project: c
+                    .build
+                    .as_ref()
+                    .map(|b| get_azd_project_path(&b.context, &project_directory)),
+                dockerfile: c.build.as_ref().and_then(|b| b.dockerfile.to_owned()),
+            })
+            .collect();
+
+        debug!("Writing an azd project to {}.", project_directory.display());
+        let secret_variables = write_azd_project(
+            &project_directory,
+            &project_name,
+            &environment_name,
+            location,
+            &services,
+            self.api_version,
+        )?;
+        if !self.dapr_components.is_empty() {
+            warn!("Dapr components are not part of the azd infrastructure, deploy them to the environment separately.");
+        }
+        for variable in secret_variables {
+            eprintln!(
+                "Set the secret before running azd up: azd env set {} <value>",
+                variable
+            );
+        }
+        Ok(project_directory)
+    }
+
+    fn combined_path(&self) -> PathBuf {
+        self.output_path(&self.containerapps_path.with_extension("json"))
+    }
+
+    fn combined_apps(&self) -> Vec<(&ContainerAppConfig, Vec<String>)> {
+        self.containerapps_configs
+            .iter()
+            .map(|c| (&c.configuration, c.depends_on.clone()))
+            .collect()
+    }
+
+    fn deploy_combined_containerapps(&mut self) -> Result<()> {
+        let json_file_path = self.combined_path();
+        write_combined_containerapps_arm_template(
+            &json_file_path,
+            &self.combined_apps(),
+            &self.template_parameters()?,
+            self.api_version,
+        )?;
+        let configs: Vec<&ContainerAppConfig> = self
+            .containerapps_configs
+            .iter()
+            .map(|c| &c.configuration)
+            .collect();
+        let secrets = get_combined_secret_parameters(&configs);
+        let parameters_file = if secrets.is_empty() {
+            None
+        } else {
+            Some(SecureParametersFile::write(&secrets)?)
+        };
+        let parameters: Vec<String> = parameters_file.iter().map(|f| f.parameter()).collect();
+        if self.dry_run {
+            return self.preview("ContainerApps", &json_file_path, &parameters);
+        }
+        let outputs = deploy_combined_containerapps(
+            self.backend()?,
+            &self.resource_group()?,
+            &json_file_path,
+            &parameters,
+        )?;
+        for config in self.containerapps_configs.iter_mut() {
+            let output_name = format!("{}Fqdn", get_parameter_name(&config.configuration.name));
+            config.url = outputs.get(&output_name).cloned();
+        }
+        Ok(())
+    }
+
+    fn convert_dapr_components(
+        &self,
+        compose_document: &ComposeDocument,
+        compose_file: &Compose,
+    ) -> Result<Vec<DaprComponent>> {
+        let service_names: Vec<String> = compose_file.services.keys().cloned().collect();
+        let dapr_components = get_dapr_components_from_compose(
+            &self.containerapps_environment_id()?,
+            compose_document,
+            &service_names,
+        )?;
+        if self.deploy_azure && !dapr_components.is_empty() {
+            let json_file_path = self.dapr_components_path();
+            let secrets = write_dapr_components_arm_template(
+                &json_file_path,
+                &dapr_components,
+                self.api_version,
+            )?;
+            let parameters_file = if secrets.is_empty() {
+                None
+            } else {
+                Some(SecureParametersFile::write(&secrets)?)
+            };
+            let parameters: Vec<String> = parameters_file.iter().map(|f| f.parameter()).collect();
+            if self.dry_run {
+                self.preview("Dapr components", &json_file_path, &parameters)?;
+            } else {
+                deploy_arm_resources(
+                    self.backend()?,
+                    "Dapr components",
+                    &self.resource_group()?,
+                    &json_file_path,
+                    &parameters,
+                )?;
+                for component in dapr_components.iter() {
+                    eprintln!("Deployed Dapr component: {}", &component.name);
+                }
+            }
+        }
+        Ok(dapr_components)
     }
 
     fn convert_services_to_containerapps(
         &self,
+        compose_document: &ComposeDocument,
         compose_file: Compose,
     ) -> Result<Vec<ConvertedComposeConfiguration>> {
         let mut containerapps = Vec::new();
+        // Services sharing a certificate deploy it once.
+        let mut deployed_certificates: Vec<String> = Vec::new();
+        // Looked up once, for the FQDNs of a dry run.
+        let mut default_domain: Option<String> = None;
         for (service_name, service) in compose_file.services {
+            if is_daprd_service(compose_document, &service_name) {
+                debug!(
+                    "Skipping the {} service, ContainerApps runs the Dapr sidecar itself.",
+                    service_name
+                );
+                continue;
+            }
             debug!(
                 "Creating a ContainerApps configuration for the {} service.",
                 service_name
             );
-            let new_path = PathBuf::from(format!(
-                "{}-{}",
-                &service_name,
-                &self.containerapps_path.display()
-            ));
+            let environment_id = self.containerapps_environment_id()?;
+            let environment_name = environment_id.rsplit('/').next().unwrap_or_default();
+            let new_path = self.service_path(&service_name, environment_name);
+            if !self.stdout {
+                if let Some(parent) = new_path.parent() {
+                    create_dir_all(parent)?;
+                }
+            }
             let containerapps_configuration_data = ContainerAppsConfigurationData {
                 resource_group: &self.resource_group()?,
                 location: &self.location()?,
-                containerapps_environment_id: &self.containerapps_environment_id()?,
+                containerapps_environment_id: &environment_id,
                 transport: self.transport()?,
+                revision_mode: self.revision_mode.clone(),
+                revision_suffix: self.revision_suffix.as_deref(),
+                traffic: self.traffic(&service_name)?,
+                identities: self.identities(&service_name)?,
+                registry_server: self.registry_server.as_deref(),
+                registry_identity: self.registry_identity.as_deref(),
+                key_vault_secrets: self.key_vault_secrets()?,
+                domains: self.domains(&service_name)?,
+                certificate: self.certificate.as_deref(),
+                certificate_password: self.certificate_password.as_deref(),
+                project_directory: compose_document.project_directory().to_path_buf(),
+                extension: get_extension_from_service(compose_document, &service_name)?,
+                no_prompts: self.compose_path == Path::new("-"),
             };
+            let certificates = get_certificates_from_service(&containerapps_configuration_data)?;
             let container_file =
                 convert_to_containerapps(&service_name, service, containerapps_configuration_data)?;
 
@@ -140,44 +747,315 @@ impl ConvertComposeCommand {
                 &new_path.display()
             );
             let mut fqdn = None;
-            if self.deploy_azure {
+            let new_certificates: Vec<Certificate> = certificates
+                .iter()
+                .filter(|c| !deployed_certificates.contains(&c.name))
+                .cloned()
+                .collect();
+            if self.deploy_azure && !new_certificates.is_empty() {
+                let certificates_path = self.certificates_path();
+                let certificates_path = certificates_path.with_file_name(format!(
+                    "{}-{}",
+                    &service_name,
+                    certificates_path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                ));
+                write_certificates_arm_template(
+                    &certificates_path,
+                    &new_certificates,
+                    self.api_version,
+                )?;
+                let passwords: BTreeMap<String, String> = new_certificates
+                    .iter()
+                    .map(|c| (c.password_parameter_name(), c.properties.password.clone()))
+                    .collect();
+                let parameters_file = SecureParametersFile::write(&passwords)?;
+                let parameters = vec![parameters_file.parameter()];
+                if self.dry_run {
+                    self.preview(
+                        &format!("{} certificates", &service_name),
+                        &certificates_path,
+                        &parameters,
+                    )?;
+                } else {
+                    deploy_arm_resources(
+                        self.backend()?,
+                        "Certificates",
+                        &self.resource_group()?,
+                        &certificates_path,
+                        &parameters,
+                    )?;
+                }
+                deployed_certificates.extend(new_certificates.into_iter().map(|c| c.name));
+            }
+            if self.deploy_azure && !self.combined {
                 let json_file_path = new_path.to_path_buf().with_extension("json");
-                write_containerapps_arm_template(&json_file_path, &container_file)?;
-                let service_fqdn =
-                    deploy_containerapps(&service_name, &self.resource_group()?, &json_file_path)?;
-                let env_var_name = format!("{}_FQDN", &service_name.to_uppercase());
-                debug!(
-                    "Setting enviroment variable {} to {}",
-                    &env_var_name, &service_fqdn
-                );
-                env::set_var(&env_var_name, &service_fqdn);
-                fqdn = Some(service_fqdn);
+                write_containerapps_arm_template(
+                    &json_file_path,
+                    &container_file,
+                    &self.template_parameters()?,
+                    self.api_version,
+                )?;
+                let secrets = get_secret_parameters(&container_file);
+                let parameters_file = if secrets.is_empty() {
+                    None
+                } else {
+                    Some(SecureParametersFile::write(&secrets)?)
+                };
+                let parameters: Vec<String> =
+                    parameters_file.iter().map(|f| f.parameter()).collect();
+                if self.dry_run {
+                    self.preview(&service_name, &json_file_path, &parameters)?;
+                    // Later services interpolate the FQDN as they would when
+                    // deployed, so it's predicted from the environment.
+                    if let Some(service_fqdn) =
+                        self.predicted_fqdn(&container_file, &mut default_domain)?
+                    {
+                        let env_var_name = format!("{}_FQDN", &service_name.to_uppercase());
+                        debug!(
+                            "Setting enviroment variable {} to {}",
+                            &env_var_name, &service_fqdn
+                        );
+                        env::set_var(&env_var_name, &service_fqdn);
+                    }
+                } else {
+                    let service_fqdn = deploy_containerapps(
+                        self.backend()?,
+                        &service_name,
+                        &self.resource_group()?,
+                        &json_file_path,
+                        &parameters,
+                    )?;
+                    let env_var_name = format!("{}_FQDN", &service_name.to_uppercase());
+                    debug!(
+                        "Setting enviroment variable {} to {}",
+                        &env_var_name, &service_fqdn
+                    );
+                    env::set_var(&env_var_name, &service_fqdn);
+                    fqdn = Some(service_fqdn);
+                }
             };
             containerapps.push(ConvertedComposeConfiguration {
                 resource_group: self.resource_group()?.to_owned(),
                 path: new_path,
                 configuration: container_file,
                 url: fqdn,
+                certificates,
+                depends_on: compose_document.service_depends_on(&service_name),
+                build: compose_document.service_build(&service_name),
             });
         }
         Ok(containerapps)
     }
 
+    /// Prints what deploying the template would change.  The resource group is
+    /// created with the environment, so before that there's nothing to compare.
+    fn preview(&self, name: &str, template_path: &Path, parameters: &[String]) -> Result<()> {
+        let resource_group = self.resource_group()?;
+        match what_if_arm_resources(
+            self.backend()?,
+            name,
+            &resource_group,
+            template_path,
+            parameters,
+        )? {
+            Some(changes) => print_what_if_changes(name, &changes),
+            None => println!(
+                "{}: not previewed, the resource group {} doesn't exist yet and everything in it would be created.",
+                name, resource_group
+            ),
+        }
+        Ok(())
+    }
+
+    /// The FQDN a ContainerApp with ingress gets: its name under the
+    /// environment's default domain, or `internal.` of it without external
+    /// ingress.  An environment that doesn't exist yet has no domain, so a
+    /// placeholder stands in.
+    fn predicted_fqdn(
+        &self,
+        config: &ContainerAppConfig,
+        default_domain: &mut Option<String>,
+    ) -> Result<Option<String>> {
+        let ingress = match config.properties.configuration.ingress.as_ref() {
+            Some(ingress) => ingress,
+            None => return Ok(None),
+        };
+        if default_domain.is_none() {
+            let environment_id = self.containerapps_environment_id()?;
+            let domain = self
+                .backend()?
+                .get_containerapp_environment(None, None, Some(&environment_id))
+                .ok()
+                .and_then(|e| e["properties"]["defaultDomain"].as_str().map(str::to_owned))
+                .unwrap_or_else(|| {
+                    format!(
+                        "{}.{}.azurecontainerapps.io",
+                        environment_id.rsplit('/').next().unwrap_or_default(),
+                        config.location
+                    )
+                });
+            *default_domain = Some(domain);
+        }
+        let domain = default_domain.as_deref().unwrap_or_default();
+        Ok(Some(if ingress.external {
+            format!("{}.{}", config.name, domain)
+        } else {
+            format!("{}.internal.{}", config.name, domain)
+        }))
+    }
+
+    fn template_parameters(&self) -> Result<Vec<TemplateParameter>> {
+        TemplateParameter::from_names(self.template_parameters.as_deref())
+    }
+
     fn transport(&self) -> Result<Transport> {
         Ok(self.transport.clone())
     }
 
+    /// Traffic weights for one service.  Named revisions are `{app}--{suffix}`,
+    /// so they only apply to their app, and a service with none of its own
+    /// keeps the traffic from its extension.
+    fn traffic(&self, service_name: &str) -> Result<Vec<TrafficConfiguration>> {
+        let mut traffic = Vec::new();
+        if let Some(t) = &self.traffic {
+            for entry in t.split(',').filter(|e| !e.trim().is_empty()) {
+                traffic.push(TrafficConfiguration::from_str(entry)?);
+            }
+        }
+        let prefix = format!("{}--", service_name);
+        let is_for_service = |t: &TrafficConfiguration| match t.revision_name.as_deref() {
+            Some(name) => name.starts_with(&prefix),
+            None => true,
+        };
+        let names_revisions = traffic.iter().any(|t| t.revision_name.is_some());
+        if names_revisions
+            && !traffic
+                .iter()
+                .any(|t| t.revision_name.is_some() && is_for_service(t))
+        {
+            debug!(
+                "--traffic names no revisions of {}, so it keeps its own traffic.",
+                service_name
+            );
+            return Ok(Vec::new());
+        }
+        Ok(traffic.into_iter().filter(is_for_service).collect())
+    }
+
+    fn identities(&self, service_name: &str) -> Result<Vec<String>> {
+        let mut identities = Vec::new();
+        for identity in self.identities.iter() {
+            match identity.split_once('=') {
+                Some((service, id)) if service == service_name => identities.push(id.to_owned()),
+                Some(_) => {}
+                None => {
+                    return Err(anyhow!(
+                        "Identities are set as <service>=<resource id or system>, found {}",
+                        identity
+                    ))
+                }
+            }
+        }
+        Ok(identities)
+    }
+
+    fn key_vault_secrets(&self) -> Result<Vec<(String, String)>> {
+        let mut key_vault_secrets = Vec::new();
+        for mapping in self.key_vault_secrets.iter() {
+            match mapping.split_once('=') {
+                Some((env_name, url)) if url.starts_with("https://") => {
+                    key_vault_secrets.push((env_name.to_owned(), url.to_owned()))
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Key Vault secrets are set as <ENV_VAR>=<secret uri>, found {}",
+                        mapping
+                    ))
+                }
+            }
+        }
+        Ok(key_vault_secrets)
+    }
+
+    fn domains(&self, service_name: &str) -> Result<Vec<String>> {
+        let mut domains = Vec::new();
+        for domain in self.domains.iter() {
+            match domain.split_once('=') {
+                Some((service, host)) if service == service_name => domains.push(host.to_owned()),
+                Some(_) => {}
+                None => {
+                    return Err(anyhow!(
+                        "Custom domains are set as <service>=<host name>, found {}",
+                        domain
+                    ))
+                }
+            }
+        }
+        Ok(domains)
+    }
+
+    fn lookup_containerapps_environment_id(&mut self) -> Result<()> {
+        if self.containerapps_environment_id.is_some() || self.skip_azure {
+            return Ok(());
+        }
+        if let (Some(resource_group), Some(environment_name)) = (
+            self.resource_group.as_ref(),
+            self.containerapps_environment_name.as_ref(),
+        ) {
+            debug!(
+                "Looking up the ContainerApps environment {} in {}",
+                environment_name, resource_group
+            );
+            self.containerapps_environment_id = get_az_containerapp_environment_resource_id(
+                self.backend()?,
+                resource_group,
+                environment_name,
+            )?;
+            if self.containerapps_environment_id.is_none() {
+                warn!(
+                    "Unable to find the ContainerApps environment {} in {}",
+                    environment_name, resource_group
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn backend(&self) -> Result<&dyn AzureBackend> {
+        match self.backend.as_ref() {
+            Some(backend) => Ok(backend.as_ref()),
+            None => azure_backend(),
+        }
+    }
+
+    /// Asks for a value that wasn't supplied.  When the Compose file comes from
+    /// stdin the prompt can't read an answer, so the option is asked for instead.
+    fn prompt(&self, prompt: &str, option: &str, variable: &str) -> Result<String> {
+        if self.compose_path == Path::new("-") {
+            return Err(anyhow!(
+                "The Compose file is read from stdin, so there is no prompt for {}; supply it with {} or {}.",
+                option,
+                option,
+                variable
+            ));
+        }
+        Ok(Input::new().with_prompt(prompt).interact_text()?)
+    }
+
     fn resource_group(&self) -> Result<String> {
         let resource_group: String = match &self.resource_group {
             Some(rg) => {
                 debug!("ContainerApps resource group set to {}", &rg);
                 rg.to_string()
             }
-            None => Input::new()
-                .with_prompt(
-                    "Please supply the Resource Group Name for the Azure ContainerApps instance",
-                )
-                .interact_text()?,
+            None => self.prompt(
+                "Please supply the Resource Group Name for the Azure ContainerApps instance",
+                "--resource-group",
+                "RESOURCE_GROUP",
+            )?,
         };
         Ok(resource_group)
     }
@@ -188,9 +1066,11 @@ impl ConvertComposeCommand {
                 debug!("ContainerApps location set to {}", &l);
                 l.to_string()
             }
-            None => Input::new()
-                .with_prompt("Please supply an Azure region for the ContainerApps instance")
-                .interact_text()?,
+            None => self.prompt(
+                "Please supply an Azure region for the ContainerApps instance",
+                "--location",
+                "LOCATION",
+            )?,
         };
         Ok(location)
     }
@@ -201,12 +1081,224 @@ impl ConvertComposeCommand {
                 debug!("ContainerApps Environment Id set to {}", &i);
                 i.to_string()
             }
-            None => Input::new()
-                .with_prompt(
-                    "Please supply the Resource ID for the Azure ContainerApps Environment",
-                )
-                .interact_text()?,
+            None => self.prompt(
+                "Please supply the Resource ID for the Azure ContainerApps Environment",
+                "--containerapps-environment-id",
+                "CONTAINERAPPS_ENVIRONMENT_ID",
+            )?,
         };
         Ok(containerapps_environment_id)
     }
 }
+
+/// azd resolves service projects against `azure.yaml`, so build contexts are
+/// made relative to the project directory.
+fn get_azd_project_path(context: &str, project_directory: &Path) -> String {
+    match Path::new(context).strip_prefix(project_directory) {
+        Ok(p) if p.as_os_str().is_empty() => ".".to_string(),
+        Ok(p) => p.display().to_string(),
+        Err(_) => context.to_string(),
+    }
+}
+
+/// Names the azd environment after the ContainerApps environment when there is
+/// one, otherwise after the project.
+fn get_azd_environment_name(
+    containerapps_environment_id: Option<&str>,
+    project_name: &str,
+) -> String {
+    let name = containerapps_environment_id
+        .and_then(|id| id.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(project_name);
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn get_parameters_path(template_path: &Path) -> PathBuf {
+    template_path.with_extension("parameters.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn services_are_deployed_through_the_runner() {
+        let out_dir = env::temp_dir().join(format!(
+            "convert-compose-deploy-test-{}",
+            uuid::Uuid::new_v4()
+        ));
+        let template_path = out_dir.join("ghost-containerapps.json");
+        let template_path = template_path.to_str().unwrap();
+        let runner = Arc::new(ReplayRunner::default().with_response(
+            &[
+                "deployment",
+                "group",
+                "create",
+                "--resource-group",
+                "rg",
+                "--template-file",
+                template_path,
+            ],
+            r#"{"properties": {"provisioningState": "Succeeded", "outputs": {"containerappFqdn": {"type": "String", "value": "ghost.azurecontainerapps.io"}}}}"#,
+        ));
+
+        let command = ConvertComposeCommand::default()
+            .with_compose_path("test/docker-compose.yml")
+            .with_containerapps_path("containerapps.yml")
+            .with_out_dir(out_dir.to_str())
+            .with_resource_group(Some("rg"))
+            .with_location(Some("eastus"))
+            .with_containerapps_environment_id(Some(
+                "/subscriptions/1234/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env",
+            ))
+            .with_deploy_azure(true)
+            .with_runner(runner.clone())
+            .convert()
+            .unwrap();
+
+        let configurations = command.get_configurations();
+        assert_eq!(
+            configurations[0].url.as_deref(),
+            Some("ghost.azurecontainerapps.io")
+        );
+        assert_eq!(runner.calls().len(), 1);
+        assert!(!Path::new(template_path).exists());
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn a_dry_run_skips_the_preview_of_a_missing_resource_group() {
+        let out_dir =
+            env::temp_dir().join(format!("convert-compose-dry-run-{}", uuid::Uuid::new_v4()));
+        let runner = Arc::new(
+            ReplayRunner::default().with_response(&["group", "exists", "--name", "rg"], "false\n"),
+        );
+
+        let command = ConvertComposeCommand::default()
+            .with_compose_path("test/docker-compose.yml")
+            .with_containerapps_path("containerapps.yml")
+            .with_out_dir(out_dir.to_str())
+            .with_resource_group(Some("rg"))
+            .with_location(Some("eastus"))
+            .with_containerapps_environment_id(Some(
+                "/subscriptions/1234/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env",
+            ))
+            .with_deploy_azure(true)
+            .with_dry_run(true)
+            .with_runner(runner.clone())
+            .convert()
+            .unwrap();
+
+        assert!(command.get_configurations()[0].url.is_none());
+        assert!(!runner
+            .calls()
+            .iter()
+            .any(|call| call.contains(&"what-if".to_string())));
+        assert!(!out_dir.join("ghost-containerapps.json").exists());
+        std::fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    #[test]
+    fn named_revision_traffic_only_applies_to_its_service() {
+        let command =
+            ConvertComposeCommand::default().with_traffic(Some("latest=20,myapp--blue=80"));
+
+        let traffic = command.traffic("myapp").unwrap();
+        assert_eq!(traffic.len(), 2);
+        assert_eq!(traffic[1].revision_name.as_deref(), Some("myapp--blue"));
+        assert!(command.traffic("other").unwrap().is_empty());
+        assert_eq!(
+            ConvertComposeCommand::default()
+                .with_traffic(Some("latest=100"))
+                .traffic("other")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn combined_needs_the_arm_format_or_deploy() {
+        let error = ConvertComposeCommand::default()
+            .with_compose_path("test/docker-compose.yml")
+            .with_format(Some("bicep"))
+            .unwrap()
+            .with_combined(true)
+            .convert()
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().contains("--combined only applies"));
+    }
+
+    #[test]
+    fn unknown_formats_and_transports_are_errors() {
+        assert!(ConvertComposeCommand::default()
+            .with_format(Some("helm"))
+            .is_err());
+        assert!(ConvertComposeCommand::default()
+            .with_transport(Some("tcp"))
+            .is_err());
+    }
+
+    #[test]
+    fn a_name_template_needs_the_service() {
+        let error = ConvertComposeCommand::default()
+            .with_compose_path("test/docker-compose.yml")
+            .with_name_template(Some("{env}/containerapp.yml"))
+            .convert()
+            .err()
+            .unwrap();
+
+        assert!(error
+            .to_string()
+            .contains("--name-template needs {service}"));
+    }
+
+    #[test]
+    fn the_environment_id_is_only_looked_up_when_azure_is_allowed() {
+        let environment_id =
+            "/subscriptions/1234/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env";
+        let runner = Arc::new(ReplayRunner::default().with_response(
+            &[
+                "containerapp",
+                "env",
+                "show",
+                "--resource-group",
+                "rg",
+                "--name",
+                "env",
+            ],
+            &format!(r#"{{"id": "{}"}}"#, environment_id),
+        ));
+        let command = || {
+            ConvertComposeCommand::default()
+                .with_compose_path("test/docker-compose.yml")
+                .with_resource_group(Some("rg"))
+                .with_containerapps_environment_name(Some("env"))
+                .with_runner(runner.clone())
+        };
+
+        let mut offline = command().with_skip_azure(true);
+        offline.lookup_containerapps_environment_id().unwrap();
+        assert!(offline.containerapps_environment_id.is_none());
+        assert!(runner.calls().is_empty());
+
+        let mut online = command();
+        online.lookup_containerapps_environment_id().unwrap();
+        assert_eq!(
+            online.containerapps_environment_id.as_deref(),
+            Some(environment_id)
+        );
+        assert_eq!(runner.calls().len(), 1);
+    }
+}
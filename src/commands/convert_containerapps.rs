@@ -0,0 +1,105 @@
+use crate::convert::{convert_to_compose, ComposeConversion};
+use crate::VERBOSE;
+use anyhow::{anyhow, Result};
+use log::{debug, trace};
+use serde::Deserialize;
+use std::fs::{read_to_string, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+#[derive(Default)]
+pub struct ConvertContainerAppsCommand {
+    containerapps_paths: Vec<PathBuf>,
+    compose_path: PathBuf,
+    stdout: bool,
+    conversion: Option<ComposeConversion>,
+}
+
+impl ConvertContainerAppsCommand {
+    pub fn with_containerapps_paths(mut self, containerapps_file_paths: Vec<&str>) -> Self {
+        self.containerapps_paths = containerapps_file_paths
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        self
+    }
+
+    /// Where to write the Compose file.  `-` writes it to stdout.
+    pub fn with_compose_path(mut self, compose_file_path: &str) -> Self {
+        self.stdout = compose_file_path == "-";
+        self.compose_path = PathBuf::from(compose_file_path);
+        self
+    }
+
+    pub fn convert(mut self) -> Result<Self> {
+        let mut documents = Vec::new();
+        for path in self.containerapps_paths.iter() {
+            debug!("Reading {}", path.display());
+            let content = read_to_string(path)?;
+            // Single-file output holds one app per document.
+            for document in serde_yaml::Deserializer::from_str(&content) {
+                let value = serde_yaml::Value::deserialize(document)?;
+                if !value.is_null() {
+                    documents.push(value);
+                }
+            }
+        }
+        if documents.is_empty() {
+            return Err(anyhow!("No ContainerApps configurations were found"));
+        }
+
+        let conversion = convert_to_compose(&documents)?;
+        trace!("Compose document: {:?}", &conversion.document);
+
+        // Keep stdout for the Compose file when streaming it.
+        let report = |line: &str| {
+            if self.stdout {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+        };
+        report(&format!(
+            "Converted {} ContainerApps to Docker Compose.",
+            documents.len()
+        ));
+        if !conversion.untranslated.is_empty() {
+            report("The following have no local equivalent:");
+            for note in conversion.untranslated.iter() {
+                report(&format!("  - {}", note));
+            }
+        }
+        if let Some(serde_yaml::Value::Mapping(secrets)) = conversion.document.get("secrets") {
+            report("Secret values are not copied, set these before running docker compose up:");
+            for (_, source) in secrets.iter() {
+                if let Some(variable) = source["environment"].as_str() {
+                    report(&format!("  - {}", variable));
+                }
+            }
+        }
+        if *VERBOSE {
+            report("");
+            report("Each container becomes a service.  External ingress maps to ports and");
+            report("internal ingress to expose, the minimum replica count maps to");
+            report("deploy.replicas, and secrets are read from environment variables.");
+            report("");
+        }
+
+        self.conversion = Some(conversion);
+        Ok(self)
+    }
+
+    pub fn write(self) -> Result<()> {
+        if let Some(conversion) = self.conversion.as_ref() {
+            let output_content = serde_yaml::to_string(&conversion.document)?;
+            if self.stdout {
+                print!("{}", output_content);
+            } else {
+                debug!("Writing {}", self.compose_path.display());
+                let mut file = File::create(&self.compose_path)?;
+                file.write_all(output_content.into_bytes().as_ref())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use super::ConvertedComposeConfiguration;
+use crate::azure::{azure_backend, get_az_containerapp, AzCliError};
+use crate::containerapps::*;
+use crate::VERBOSE;
+use anyhow::Result;
+use log::{debug, trace};
+use std::process::ExitCode;
+
+/// The outcome of a diff, which is also the exit code of the command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffResult {
+    NoDrift,
+    Drift,
+}
+
+impl From<DiffResult> for ExitCode {
+    fn from(result: DiffResult) -> Self {
+        match result {
+            DiffResult::NoDrift => ExitCode::SUCCESS,
+            DiffResult::Drift => ExitCode::from(2),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DiffContainerAppsCommand {
+    configurations: Vec<ConvertedComposeConfiguration>,
+    drift: bool,
+}
+
+impl DiffContainerAppsCommand {
+    /// The ContainerApps converted from the Compose file.
+    pub fn with_configurations(
+        mut self,
+        configurations: Vec<ConvertedComposeConfiguration>,
+    ) -> Self {
+        self.configurations = configurations;
+        self
+    }
+
+    pub fn diff(mut self) -> Result<Self> {
+        azure_backend()?.ensure_available()?;
+        for generated in self.configurations.iter() {
+            let name = &generated.configuration.name;
+            debug!("Comparing {} in {}", name, &generated.resource_group);
+            let value = match get_az_containerapp(azure_backend()?, &generated.resource_group, name)
+            {
+                Ok(value) => value,
+                Err(e)
+                    if matches!(
+                        e.downcast_ref::<AzCliError>(),
+                        Some(AzCliError::ContainerAppNotFound { .. })
+                    ) =>
+                {
+                    println!("{}: not deployed to {}", name, &generated.resource_group);
+                    self.drift = true;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let deployed = export_container_app_value(value)?.config;
+            trace!("Deployed configuration: {:?}", &deployed);
+
+            let differences = diff_container_apps(&deployed, &generated.configuration);
+            if differences.is_empty() {
+                println!("{}: no changes", name);
+            } else {
+                println!("{}:", name);
+                for difference in differences.iter() {
+                    println!("  {}", difference);
+                }
+                self.drift = true;
+            }
+        }
+
+        if *VERBOSE {
+            println!();
+            println!("Lines starting with - are only in the deployed app, + only in the");
+            println!("Compose file and ~ show the deployed value, then the Compose file's.  Only");
+            println!(
+                "images, environment variables, secret names, ingress and scale are compared."
+            );
+            println!();
+        }
+        Ok(self)
+    }
+
+    /// Drift when any app differs from the Compose file or isn't deployed.
+    pub fn result(&self) -> DiffResult {
+        if self.drift {
+            DiffResult::Drift
+        } else {
+            DiffResult::NoDrift
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::azure::{azure_backend, get_az_containerapp};
+use crate::containerapps::*;
+use crate::VERBOSE;
+use anyhow::Result;
+use dialoguer::Input;
+use log::{debug, trace};
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Default)]
+pub struct ExportContainerAppCommand {
+    resource_group: String,
+    name: String,
+    output_path: Option<PathBuf>,
+    api_version: ApiVersion,
+    export: Option<Export>,
+}
+
+impl ExportContainerAppCommand {
+    pub fn with_resource_group(mut self, resource_group: Option<&str>) -> Self {
+        if let Some(v) = resource_group {
+            self.resource_group = v.to_owned();
+        }
+        self
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    /// Where to write the configuration.  `-` writes it to stdout.
+    pub fn with_output_path(mut self, output_file_path: Option<&str>) -> Self {
+        self.output_path = output_file_path.map(PathBuf::from);
+        self
+    }
+
+    pub fn with_api_version(mut self, api_version: Option<&str>) -> Result<Self> {
+        if let Some(v) = api_version {
+            self.api_version = ApiVersion::from_str(v)?;
+        } else {
+            self.api_version = ApiVersion::default();
+        };
+        Ok(self)
+    }
+
+    pub fn export(mut self) -> Result<Self> {
+        azure_backend()?.ensure_available()?;
+        if self.resource_group.is_empty() {
+            debug!("Resource group is an empty string.");
+            self.resource_group = Input::new()
+                .with_prompt("Please supply the Resource Group Name for the Azure ContainerApp")
+                .interact_text()?;
+        }
+
+        let value = get_az_containerapp(azure_backend()?, &self.resource_group, &self.name)?;
+        trace!("az containerapp show output: {:?}", &value);
+        let export = export_container_app_value(value)?;
+
+        // Keep stdout for the configuration when streaming it.
+        let report = |line: &str| {
+            if self.is_stdout() {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+        };
+        report(&format!(
+            "Exported {} from {}.",
+            self.name, self.resource_group
+        ));
+        if !export.untranslated.is_empty() {
+            report("Review the following, they could not be exported:");
+            for note in export.untranslated.iter() {
+                report(&format!("  - {}", note));
+            }
+        }
+        if *VERBOSE {
+            report("");
+            report("The configuration is read with az containerapp show.  Properties Azure sets");
+            report("at runtime, like the provisioning state, FQDN and latest revision, are left");
+            report("out so the file can be kept in source control and deployed again.");
+            report("");
+        }
+
+        self.export = Some(export);
+        Ok(self)
+    }
+
+    pub fn write(self) -> Result<()> {
+        if let Some(export) = self.export.as_ref() {
+            let output_content = get_containerapps_yaml(&export.config, self.api_version)?;
+            if self.is_stdout() {
+                print!("{}", output_content);
+            } else {
+                let output_path = self
+                    .output_path
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(format!("{}-containerapps.yml", self.name)));
+                debug!("Writing {}", output_path.display());
+                let mut file = File::create(&output_path)?;
+                file.write_all(output_content.into_bytes().as_ref())?;
+                println!("Wrote {}", output_path.display());
+            }
+        }
+        Ok(())
+    }
+
+    fn is_stdout(&self) -> bool {
+        self.output_path
+            .as_ref()
+            .is_some_and(|p| p.as_os_str() == "-")
+    }
+}
@@ -0,0 +1,933 @@
+use crate::compose::{read_compose_document, ServiceBuild};
+use crate::containerapps::{
+    get_environment_variable_name, get_parameter_name, get_secret_parameter_name, Certificate,
+    TemplateParameter, TrafficConfiguration,
+};
+use crate::convert::{get_certificate_name, get_extension_from_service, is_daprd_service};
+use crate::VERSION;
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::env;
+use std::fmt;
+use std::fs::{create_dir_all, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const CONTAINERAPPS_PATH: &str = "containerapps.yml";
+const RELEASES_URL: &str = "https://github.com/smurawski/compose2containerapps/releases/download";
+
+/// CI system to generate the deployment workflow for.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum WorkflowPipeline {
+    #[default]
+    GitHub,
+    AzurePipelines,
+}
+impl WorkflowPipeline {
+    pub fn variants() -> [&'static str; 2] {
+        ["github", "azure-pipelines"]
+    }
+
+    fn default_path(self) -> &'static str {
+        match self {
+            WorkflowPipeline::GitHub => ".github/workflows/containerapps.yml",
+            WorkflowPipeline::AzurePipelines => "azure-pipelines.yml",
+        }
+    }
+
+    fn secret_reference(self, name: &str) -> String {
+        match self {
+            WorkflowPipeline::GitHub => format!("${{{{ secrets.{} }}}}", name),
+            WorkflowPipeline::AzurePipelines => format!("$({})", name),
+        }
+    }
+}
+impl fmt::Display for WorkflowPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl FromStr for WorkflowPipeline {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "github" => Ok(WorkflowPipeline::GitHub),
+            "azure-pipelines" => Ok(WorkflowPipeline::AzurePipelines),
+            _ => Err(anyhow!("Unknown pipeline {}", s)),
+        }
+    }
+}
+
+/// A script step in the generated workflow, with the secrets it needs mapped
+/// into its environment.
+struct WorkflowStep {
+    name: String,
+    script: Vec<String>,
+    secrets: Vec<String>,
+}
+impl WorkflowStep {
+    fn new(name: &str, script: Vec<String>) -> Self {
+        WorkflowStep {
+            name: name.to_owned(),
+            script,
+            secrets: Vec::new(),
+        }
+    }
+}
+
+/// What the workflow needs to know about a service, read from the Compose
+/// file.
+struct WorkflowService {
+    name: String,
+    image: String,
+    build: Option<ServiceBuild>,
+    /// Secrets with a value, which the templates lift into secure parameters.
+    secrets: Vec<String>,
+}
+impl WorkflowService {
+    /// The file `convert` writes the service to, without `--out-dir` or
+    /// `--name-template`.
+    fn path(&self) -> PathBuf {
+        PathBuf::from(format!("{}-{}", &self.name, CONTAINERAPPS_PATH))
+    }
+}
+
+#[derive(Default)]
+pub struct GenerateWorkflowCommand {
+    compose_path: String,
+    workflow_path: Option<PathBuf>,
+    pipeline: WorkflowPipeline,
+    branch: String,
+    resource_group: Option<String>,
+    location: Option<String>,
+    containerapps_environment_id: Option<String>,
+    transport: Option<String>,
+    revision_mode: Option<String>,
+    revision_suffix: Option<String>,
+    traffic: Option<String>,
+    identities: Vec<String>,
+    registry_server: Option<String>,
+    registry_identity: Option<String>,
+    key_vault_secrets: Vec<String>,
+    domains: Vec<String>,
+    certificate: Option<String>,
+    combined: bool,
+    template_parameters: Option<Vec<String>>,
+    api_version: Option<String>,
+    services: Vec<WorkflowService>,
+    /// Names of the certificates for the custom domains.
+    certificates: Vec<String>,
+}
+
+impl GenerateWorkflowCommand {
+    pub fn with_compose_path(mut self, compose_file_path: &str) -> Self {
+        self.compose_path = compose_file_path.to_owned();
+        self
+    }
+
+    pub fn with_workflow_path(mut self, workflow_path: Option<&str>) -> Self {
+        self.workflow_path = workflow_path.map(PathBuf::from);
+        self
+    }
+
+    pub fn with_pipeline(mut self, pipeline: Option<&str>) -> Result<Self> {
+        if let Some(v) = pipeline {
+            self.pipeline = WorkflowPipeline::from_str(v)?;
+        } else {
+            self.pipeline = WorkflowPipeline::default();
+        };
+        Ok(self)
+    }
+
+    pub fn with_branch(mut self, branch: Option<&str>) -> Self {
+        self.branch = branch.unwrap_or("main").to_owned();
+        self
+    }
+
+    pub fn with_resource_group(mut self, resource_group: Option<&str>) -> Self {
+        self.resource_group = resource_group.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_location(mut self, location: Option<&str>) -> Self {
+        self.location = location.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_containerapps_environment_id(
+        mut self,
+        containerapps_environment_id: Option<&str>,
+    ) -> Self {
+        self.containerapps_environment_id = containerapps_environment_id.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_transport(mut self, transport: Option<&str>) -> Self {
+        self.transport = transport.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_revision_mode(mut self, revision_mode: Option<&str>) -> Self {
+        self.revision_mode = revision_mode.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_revision_suffix(mut self, revision_suffix: Option<&str>) -> Self {
+        self.revision_suffix = revision_suffix.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_traffic(mut self, traffic: Option<&str>) -> Self {
+        self.traffic = traffic.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_identities(mut self, identities: Option<Vec<&str>>) -> Self {
+        self.identities = identities
+            .unwrap_or_default()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        self
+    }
+
+    pub fn with_registry_server(mut self, registry_server: Option<&str>) -> Self {
+        self.registry_server = registry_server.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_registry_identity(mut self, registry_identity: Option<&str>) -> Self {
+        self.registry_identity = registry_identity.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_key_vault_secrets(mut self, key_vault_secrets: Option<Vec<&str>>) -> Self {
+        self.key_vault_secrets = key_vault_secrets
+            .unwrap_or_default()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        self
+    }
+
+    pub fn with_domains(mut self, domains: Option<Vec<&str>>) -> Self {
+        self.domains = domains
+            .unwrap_or_default()
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        self
+    }
+
+    pub fn with_certificate(mut self, certificate: Option<&str>) -> Self {
+        self.certificate = certificate.map(|v| v.to_owned());
+        self
+    }
+
+    pub fn with_combined(mut self, combined: bool) -> Self {
+        self.combined = combined;
+        self
+    }
+
+    pub fn with_template_parameters(mut self, template_parameters: Option<Vec<&str>>) -> Self {
+        self.template_parameters =
+            template_parameters.map(|v| v.iter().map(|p| p.to_string()).collect());
+        self
+    }
+
+    pub fn with_api_version(mut self, api_version: Option<&str>) -> Self {
+        self.api_version = api_version.map(|v| v.to_owned());
+        self
+    }
+
+    /// Reads the services, their builds, secrets and certificates from the
+    /// Compose file.  Nothing is converted, so there are no prompts and no
+    /// calls to Azure.
+    pub fn generate(mut self) -> Result<Self> {
+        if self.compose_path == "-" {
+            return Err(anyhow!(
+                "The workflow converts the Compose file in the repository, so give its path instead of -"
+            ));
+        }
+        TemplateParameter::from_names(self.template_parameters.as_deref())?;
+        if let Some(traffic) = self.traffic.as_ref() {
+            for entry in traffic.split(',').filter(|e| !e.trim().is_empty()) {
+                TrafficConfiguration::from_str(entry)?;
+            }
+        }
+        let missing: Vec<&str> = [
+            ("--resource-group", &self.resource_group),
+            ("--location", &self.location),
+            (
+                "--containerapps-environment-id",
+                &self.containerapps_environment_id,
+            ),
+        ]
+        .iter()
+        .filter(|(_, value)| value.is_none())
+        .map(|(option, _)| *option)
+        .collect();
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "The workflow needs {} for its environment",
+                missing.join(", ")
+            ));
+        }
+
+        let compose_document = read_compose_document(Path::new(&self.compose_path))?;
+        let compose_file = compose_document.compose()?;
+        // The workflow runs in a checkout of the repository, so the paths it
+        // uses have to be relative to its root.
+        let repository_root = get_repository_root(Path::new(&self.compose_path))?;
+        self.compose_path = get_repository_path(&repository_root, Path::new(&self.compose_path))?;
+        for (service_name, service) in compose_file.services.iter() {
+            if is_daprd_service(&compose_document, service_name) {
+                debug!(
+                    "Skipping the {} service, ContainerApps runs the Dapr sidecar itself.",
+                    service_name
+                );
+                continue;
+            }
+            let extension = get_extension_from_service(&compose_document, service_name)?;
+            let image = match &service.image {
+                Some(image) => image.value()?.to_string(),
+                None => String::new(),
+            };
+            let mut certificate_paths: Vec<PathBuf> = extension
+                .ingress
+                .custom_domains
+                .iter()
+                .filter_map(|d| d.certificate.clone())
+                .collect();
+            if let Some(certificate) = self.certificate.as_ref() {
+                if self
+                    .domains
+                    .iter()
+                    .any(|d| d.split_once('=').map(|(s, _)| s) == Some(service_name.as_str()))
+                {
+                    certificate_paths.push(PathBuf::from(certificate));
+                }
+            }
+            for path in certificate_paths {
+                let name = get_certificate_name(compose_document.project_directory(), &path)?;
+                if !self.certificates.contains(&name) {
+                    self.certificates.push(name);
+                }
+            }
+            let build = match compose_document.service_build(service_name) {
+                Some(mut build) => {
+                    build.context =
+                        get_repository_path(&repository_root, Path::new(&build.context))?;
+                    if let Some(dockerfile) = build.dockerfile.as_mut() {
+                        if Path::new(dockerfile).is_absolute() {
+                            *dockerfile = get_repository_path(
+                                &repository_root.join(&build.context),
+                                Path::new(dockerfile),
+                            )?;
+                        }
+                    }
+                    Some(build)
+                }
+                None => None,
+            };
+            self.services.push(WorkflowService {
+                name: service_name.to_owned(),
+                image,
+                build,
+                secrets: extension
+                    .secrets
+                    .iter()
+                    .filter(|s| s.value.is_some())
+                    .map(|s| s.name.to_owned())
+                    .collect(),
+            });
+        }
+        Ok(self)
+    }
+
+    pub fn write(self) -> Result<()> {
+        let workflow_path = self
+            .workflow_path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(self.pipeline.default_path()));
+        let output_content = self.get_workflow()?;
+        if let Some(parent) = workflow_path.parent() {
+            if !parent.as_os_str().is_empty() {
+                create_dir_all(parent)?;
+            }
+        }
+        debug!("Writing the workflow to {}.", workflow_path.display());
+        let mut file = File::create(&workflow_path)?;
+        file.write_all(output_content.into_bytes().as_ref())?;
+
+        println!("Wrote {}.", workflow_path.display());
+        for secret in self.get_secret_names() {
+            println!("Add the {} secret before running the workflow.", secret);
+        }
+        Ok(())
+    }
+
+    fn get_workflow(&self) -> Result<String> {
+        let build_steps = self.get_build_steps()?;
+        let deploy_steps = self.get_deploy_steps();
+        let output_content = match self.pipeline {
+            WorkflowPipeline::GitHub => self.render_github(&build_steps, &deploy_steps),
+            WorkflowPipeline::AzurePipelines => {
+                self.render_azure_pipelines(&build_steps, &deploy_steps)
+            }
+        };
+        Ok(output_content)
+    }
+
+    fn get_build_steps(&self) -> Result<Vec<WorkflowStep>> {
+        let builds: Vec<&WorkflowService> =
+            self.services.iter().filter(|s| s.build.is_some()).collect();
+        if builds.is_empty() {
+            return Ok(Vec::new());
+        }
+        let registry_server = self.registry_server.as_ref().ok_or_else(|| {
+            anyhow!(
+                "Services with a build need --registry-server so the workflow can push the images"
+            )
+        })?;
+        let registry_name = registry_server.split('.').next().unwrap_or_default();
+
+        let mut steps = vec![WorkflowStep::new(
+            "Log in to the container registry",
+            vec![format!(
+                "az acr login --name {}",
+                get_shell_string(registry_name)
+            )],
+        )];
+        for service in builds {
+            let build = service.build.as_ref().unwrap();
+            let image = service.image.as_str();
+            if image.is_empty() {
+                return Err(anyhow!(
+                    "Set an image for {} in the Compose file so the workflow knows where to push it",
+                    &service.name
+                ));
+            }
+            let dockerfile = Path::new(&build.context)
+                .join(build.dockerfile.as_deref().unwrap_or("Dockerfile"))
+                .display()
+                .to_string();
+            steps.push(WorkflowStep::new(
+                &format!("Build {}", &service.name),
+                vec![
+                    format!(
+                        "docker build --tag {} --file {} {}",
+                        get_shell_string(image),
+                        get_shell_string(&dockerfile),
+                        get_shell_string(&build.context)
+                    ),
+                    format!("docker push {}", get_shell_string(image)),
+                ],
+            ));
+        }
+        Ok(steps)
+    }
+
+    fn get_deploy_steps(&self) -> Vec<WorkflowStep> {
+        let mut steps = vec![
+            WorkflowStep::new(
+                "Install compose2containerapp",
+                vec![
+                    format!(
+                        "curl --fail --location --silent --show-error --output compose2containerapp {}/{}/compose2containerapp-linux",
+                        RELEASES_URL, &*VERSION
+                    ),
+                    "chmod +x compose2containerapp".to_string(),
+                ],
+            ),
+            WorkflowStep::new("Convert the Compose file", vec![self.get_convert_command()]),
+            WorkflowStep::new(
+                "Deploy Dapr components",
+                vec![
+                    "if [ -f dapr-components-containerapps.json ]; then".to_string(),
+                    "  az deployment group create --resource-group \"$RESOURCE_GROUP\" --name DaprComponents --template-file dapr-components-containerapps.json".to_string(),
+                    "fi".to_string(),
+                ],
+            ),
+        ];
+        // The apps' custom domains reference the certificates, so they go
+        // first.
+        if !self.certificates.is_empty() {
+            let mut step = WorkflowStep::new("Deploy certificates", Vec::new());
+            let mut command = "az deployment group create --resource-group \"$RESOURCE_GROUP\" --name Certificates --template-file certificates-containerapps.json".to_string();
+            for (parameter, variable) in self.get_certificate_passwords() {
+                command.push_str(&format!(" --parameters {}=\"${}\"", parameter, &variable));
+                step.secrets.push(variable);
+            }
+            step.script.push(command);
+            steps.push(step);
+        }
+
+        if self.combined {
+            let mut step = WorkflowStep::new("Deploy ContainerApps", Vec::new());
+            let mut command =
+                get_deployment_command("ContainerApps", Path::new(CONTAINERAPPS_PATH));
+            for service in self.services.iter() {
+                for secret in service.secrets.iter() {
+                    let variable = get_environment_variable_name(&service.name, secret);
+                    command.push_str(&format!(
+                        " --parameters {}=\"${}\"",
+                        get_secret_parameter_name(&service.name, secret),
+                        &variable
+                    ));
+                    step.secrets.push(variable);
+                }
+            }
+            step.script.push(command);
+            steps.push(step);
+        } else {
+            for service in self.services.iter() {
+                let name = &service.name;
+                let mut step = WorkflowStep::new(&format!("Deploy {}", name), Vec::new());
+                let mut command = get_deployment_command(name, &service.path());
+                for secret in service.secrets.iter() {
+                    let variable = get_environment_variable_name(name, secret);
+                    command.push_str(&format!(
+                        " --parameters {}=\"${}\"",
+                        get_parameter_name(secret),
+                        &variable
+                    ));
+                    step.secrets.push(variable);
+                }
+                step.script.push(command);
+                steps.push(step);
+            }
+        }
+        steps
+    }
+
+    /// The `convert` command line for the workflow.  Values that have an
+    /// environment variable are set in the workflow's environment instead.
+    fn get_convert_command(&self) -> String {
+        let mut command = format!(
+            "./compose2containerapp convert {} {} --format arm",
+            get_shell_string(&self.compose_path),
+            CONTAINERAPPS_PATH
+        );
+        if let Some(transport) = self.transport.as_ref() {
+            command.push_str(&format!(" --transport {}", get_shell_string(transport)));
+        }
+        if let Some(revision_mode) = self.revision_mode.as_ref() {
+            command.push_str(&format!(
+                " --revision-mode {}",
+                get_shell_string(revision_mode)
+            ));
+        }
+        if let Some(revision_suffix) = self.revision_suffix.as_ref() {
+            command.push_str(&format!(
+                " --revision-suffix {}",
+                get_shell_string(revision_suffix)
+            ));
+        }
+        if let Some(traffic) = self.traffic.as_ref() {
+            command.push_str(&format!(" --traffic {}", get_shell_string(traffic)));
+        }
+        for identity in self.identities.iter() {
+            command.push_str(&format!(" --identity {}", get_shell_string(identity)));
+        }
+        for secret in self.key_vault_secrets.iter() {
+            command.push_str(&format!(" --keyvault {}", get_shell_string(secret)));
+        }
+        for domain in self.domains.iter() {
+            command.push_str(&format!(" --domain {}", get_shell_string(domain)));
+        }
+        if let Some(certificate) = self.certificate.as_ref() {
+            command.push_str(&format!(" --certificate {}", get_shell_string(certificate)));
+        }
+        if self.combined {
+            command.push_str(" --combined");
+        }
+        if let Some(template_parameters) = self.template_parameters.as_ref() {
+            command.push_str(&format!(
+                " --template-parameters {}",
+                get_shell_string(&template_parameters.join(","))
+            ));
+        }
+        if let Some(api_version) = self.api_version.as_ref() {
+            command.push_str(&format!(" --api-version {}", get_shell_string(api_version)));
+        }
+        command
+    }
+
+    fn get_environment(&self) -> Vec<(&'static str, String)> {
+        let mut environment = Vec::new();
+        let values = [
+            ("RESOURCE_GROUP", &self.resource_group),
+            ("LOCATION", &self.location),
+            (
+                "CONTAINERAPPS_ENVIRONMENT_ID",
+                &self.containerapps_environment_id,
+            ),
+            ("REGISTRY_SERVER", &self.registry_server),
+            ("REGISTRY_IDENTITY", &self.registry_identity),
+        ];
+        for (name, value) in values {
+            if let Some(v) = value {
+                environment.push((name, v.to_owned()));
+            }
+        }
+        environment
+    }
+
+    /// The password parameter of each certificate and the secret that
+    /// supplies it, like `CERTS_WEB_PASSWORD`.
+    fn get_certificate_passwords(&self) -> Vec<(String, String)> {
+        self.certificates
+            .iter()
+            .map(|name| {
+                let certificate = Certificate {
+                    name: name.to_owned(),
+                    ..Certificate::default()
+                };
+                (
+                    certificate.password_parameter_name(),
+                    get_environment_variable_name(name, "password"),
+                )
+            })
+            .collect()
+    }
+
+    fn get_secret_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        if self.pipeline == WorkflowPipeline::GitHub {
+            names.push("AZURE_CLIENT_ID".to_string());
+            names.push("AZURE_TENANT_ID".to_string());
+            names.push("AZURE_SUBSCRIPTION_ID".to_string());
+        }
+        for (_, variable) in self.get_certificate_passwords() {
+            names.push(variable);
+        }
+        for service in self.services.iter() {
+            for secret in service.secrets.iter() {
+                names.push(get_environment_variable_name(&service.name, secret));
+            }
+        }
+        names
+    }
+
+    fn render_github(&self, build_steps: &[WorkflowStep], deploy_steps: &[WorkflowStep]) -> String {
+        let mut output = get_header();
+        output.push_str("name: Deploy to Azure ContainerApps\n\n");
+        output.push_str(&format!(
+            "on:\n  push:\n    branches:\n      - {}\n  workflow_dispatch:\n\n",
+            get_yaml_string(&self.branch)
+        ));
+        output.push_str("permissions:\n  id-token: write\n  contents: read\n\n");
+        output.push_str("env:\n");
+        for (name, value) in self.get_environment() {
+            output.push_str(&format!("  {}: {}\n", name, get_yaml_string(&value)));
+        }
+        output.push_str("\njobs:\n");
+
+        let mut jobs = Vec::new();
+        if !build_steps.is_empty() {
+            jobs.push(("build", "Build images", build_steps));
+        }
+        jobs.push(("deploy", "Deploy ContainerApps", deploy_steps));
+        for (index, (id, name, steps)) in jobs.iter().enumerate() {
+            if index > 0 {
+                output.push('\n');
+            }
+            output.push_str(&format!(
+                "  {}:\n    name: {}\n    runs-on: ubuntu-latest\n",
+                id, name
+            ));
+            if index > 0 {
+                output.push_str(&format!("    needs: {}\n", jobs[index - 1].0));
+            }
+            output.push_str("    steps:\n");
+            output
+                .push_str("      - name: Checkout sources\n        uses: actions/checkout@v4\n\n");
+            output.push_str(
+                "      - name: Log in to Azure\n        uses: azure/login@v2\n        with:\n",
+            );
+            output.push_str("          client-id: ${{ secrets.AZURE_CLIENT_ID }}\n");
+            output.push_str("          tenant-id: ${{ secrets.AZURE_TENANT_ID }}\n");
+            output.push_str("          subscription-id: ${{ secrets.AZURE_SUBSCRIPTION_ID }}\n");
+            for step in steps.iter() {
+                output.push_str(&format!(
+                    "\n      - name: {}\n",
+                    get_yaml_string(&step.name)
+                ));
+                push_secrets(&mut output, step, self.pipeline, "        ");
+                push_script(&mut output, "run", &step.script, "        ");
+            }
+        }
+        output
+    }
+
+    fn render_azure_pipelines(
+        &self,
+        build_steps: &[WorkflowStep],
+        deploy_steps: &[WorkflowStep],
+    ) -> String {
+        let mut output = get_header();
+        output.push_str(&format!(
+            "trigger:\n  branches:\n    include:\n      - {}\n\n",
+            get_yaml_string(&self.branch)
+        ));
+        output.push_str("pool:\n  vmImage: ubuntu-latest\n\n");
+        output.push_str("variables:\n");
+        output.push_str("  # An Azure Resource Manager service connection using workload identity federation.\n");
+        output.push_str("  AZURE_SERVICE_CONNECTION: containerapps\n");
+        for (name, value) in self.get_environment() {
+            output.push_str(&format!("  {}: {}\n", name, get_yaml_string(&value)));
+        }
+        output.push_str("\nstages:\n");
+
+        let mut stages = Vec::new();
+        if !build_steps.is_empty() {
+            stages.push(("build", "Build images", build_steps));
+        }
+        stages.push(("deploy", "Deploy ContainerApps", deploy_steps));
+        for (index, (id, name, steps)) in stages.iter().enumerate() {
+            if index > 0 {
+                output.push('\n');
+            }
+            output.push_str(&format!("  - stage: {}\n    displayName: {}\n", id, name));
+            if index > 0 {
+                output.push_str(&format!("    dependsOn: {}\n", stages[index - 1].0));
+            }
+            output.push_str(&format!("    jobs:\n      - job: {}\n        steps:\n", id));
+            output.push_str("          - checkout: self\n");
+            for step in steps.iter() {
+                output.push_str("\n          - task: AzureCLI@2\n");
+                output.push_str(&format!(
+                    "            displayName: {}\n",
+                    get_yaml_string(&step.name)
+                ));
+                push_secrets(&mut output, step, self.pipeline, "            ");
+                output.push_str("            inputs:\n");
+                output.push_str(
+                    "              azureSubscription: ${{ variables.AZURE_SERVICE_CONNECTION }}\n",
+                );
+                output.push_str("              scriptType: bash\n");
+                output.push_str("              scriptLocation: inlineScript\n");
+                push_script(&mut output, "inlineScript", &step.script, "              ");
+            }
+        }
+        output
+    }
+}
+
+fn get_header() -> String {
+    format!(
+        "# Generated by compose2containerapp {} generate-workflow.\n\n",
+        &*VERSION
+    )
+}
+
+/// The repository the Compose file is in, the closest directory above it
+/// with a `.git`, or the current directory outside of a repository.
+fn get_repository_root(compose_path: &Path) -> Result<PathBuf> {
+    let compose_path = compose_path.canonicalize()?;
+    match compose_path
+        .ancestors()
+        .skip(1)
+        .find(|directory| directory.join(".git").exists())
+    {
+        Some(root) => Ok(root.to_path_buf()),
+        None => Ok(env::current_dir()?.canonicalize()?),
+    }
+}
+
+/// `path` relative to the root of the repository, for the workflow.  Paths
+/// outside of the repository won't be in its checkout.
+fn get_repository_path(repository_root: &Path, path: &Path) -> Result<String> {
+    let full_path = path
+        .canonicalize()
+        .map_err(|e| anyhow!("Unable to find {} - {}", path.display(), e))?;
+    match full_path.strip_prefix(repository_root) {
+        Ok(relative_path) if relative_path.as_os_str().is_empty() => Ok(".".to_string()),
+        Ok(relative_path) => Ok(relative_path.display().to_string()),
+        Err(_) => Err(anyhow!(
+            "{} is outside of the repository at {}, so the workflow can't use it",
+            path.display(),
+            repository_root.display()
+        )),
+    }
+}
+
+fn get_deployment_command(name: &str, path: &Path) -> String {
+    let template_path = path.with_extension("json");
+    format!(
+        "az deployment group create --resource-group \"$RESOURCE_GROUP\" --name {} --template-file {} --parameters @{}",
+        get_shell_string(name),
+        get_shell_string(&template_path.display().to_string()),
+        get_shell_string(&template_path.with_extension("parameters.json").display().to_string())
+    )
+}
+
+fn push_secrets(
+    output: &mut String,
+    step: &WorkflowStep,
+    pipeline: WorkflowPipeline,
+    indent: &str,
+) {
+    if step.secrets.is_empty() {
+        return;
+    }
+    output.push_str(&format!("{}env:\n", indent));
+    for secret in step.secrets.iter() {
+        output.push_str(&format!(
+            "{}  {}: {}\n",
+            indent,
+            secret,
+            pipeline.secret_reference(secret)
+        ));
+    }
+}
+
+fn push_script(output: &mut String, key: &str, script: &[String], indent: &str) {
+    output.push_str(&format!("{}{}: |\n", indent, key));
+    for line in script {
+        output.push_str(&format!("{}  {}\n", indent, line));
+    }
+}
+
+/// Quotes a YAML scalar only when it needs it.
+fn get_yaml_string(value: &str) -> String {
+    let rendered = serde_yaml::to_string(value).unwrap_or_default();
+    rendered.trim_start_matches("---").trim().to_string()
+}
+
+/// Quotes a shell word only when it needs it.
+fn get_shell_string(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:@%+=,".contains(c));
+    if is_plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_service(name: &str, image: &str) -> WorkflowService {
+        WorkflowService {
+            name: name.to_string(),
+            image: image.to_string(),
+            build: None,
+            secrets: Vec::new(),
+        }
+    }
+
+    fn get_command() -> GenerateWorkflowCommand {
+        let mut web = get_service("web", "myacr.azurecr.io/web:1.0");
+        web.build = Some(ServiceBuild {
+            context: "./web".to_string(),
+            dockerfile: None,
+        });
+        web.secrets.push("db-password".to_string());
+        let mut command = GenerateWorkflowCommand::default()
+            .with_compose_path("./docker-compose.yml")
+            .with_branch(None)
+            .with_resource_group(Some("rg"))
+            .with_registry_server(Some("myacr.azurecr.io"))
+            .with_api_version(Some("2023-05-01"));
+        command.services = vec![web, get_service("redis", "redis")];
+        command
+    }
+
+    #[test]
+    fn github_workflow_builds_and_deploys_services() {
+        let workflow = get_command().get_workflow().unwrap();
+
+        assert!(workflow.contains("id-token: write"));
+        assert!(workflow.contains("az acr login --name myacr"));
+        assert!(workflow
+            .contains("docker build --tag myacr.azurecr.io/web:1.0 --file ./web/Dockerfile ./web"));
+        assert!(!workflow.contains("docker build --tag redis"));
+        assert!(workflow.contains("needs: build"));
+        assert!(workflow.contains("--format arm --api-version 2023-05-01"));
+        assert!(workflow.contains("WEB_DB_PASSWORD: ${{ secrets.WEB_DB_PASSWORD }}"));
+        assert!(workflow.contains(
+            "--template-file web-containerapps.json --parameters @web-containerapps.parameters.json --parameters dbPassword=\"$WEB_DB_PASSWORD\""
+        ));
+        assert!(!workflow.contains("hunter2"));
+    }
+
+    #[test]
+    fn azure_pipelines_map_secret_variables() {
+        let workflow = get_command()
+            .with_pipeline(Some("azure-pipelines"))
+            .unwrap()
+            .get_workflow()
+            .unwrap();
+
+        assert!(workflow.contains("task: AzureCLI@2"));
+        assert!(workflow.contains("dependsOn: build"));
+        assert!(workflow.contains("WEB_DB_PASSWORD: $(WEB_DB_PASSWORD)"));
+    }
+
+    #[test]
+    fn builds_need_a_registry() {
+        let command = get_command().with_registry_server(None);
+        assert!(command.get_workflow().is_err());
+    }
+
+    #[test]
+    fn services_are_read_from_the_compose_file_without_dapr_sidecars() {
+        let project_directory =
+            std::env::temp_dir().join(format!("generate-workflow-{}", uuid::Uuid::new_v4()));
+        create_dir_all(project_directory.join(".git")).unwrap();
+        create_dir_all(project_directory.join("web")).unwrap();
+        let compose_path = project_directory.join("docker-compose.yml");
+        std::fs::write(
+            &compose_path,
+            r#"
+version: "3"
+services:
+  web:
+    image: myacr.azurecr.io/web:1.0
+    build: ./web
+  web-dapr:
+    image: daprio/daprd
+    command: ./daprd -app-id web
+"#,
+        )
+        .unwrap();
+        let command = GenerateWorkflowCommand::default()
+            .with_compose_path(compose_path.to_str().unwrap())
+            .with_branch(None)
+            .with_resource_group(Some("rg"))
+            .with_location(Some("eastus"))
+            .with_containerapps_environment_id(Some(
+                "/subscriptions/1234/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env",
+            ))
+            .with_registry_server(Some("myacr.azurecr.io"))
+            .with_traffic(Some("latest=100"))
+            .with_revision_suffix(Some("{git}"))
+            .with_domains(Some(vec!["web=www.contoso.com"]))
+            .with_certificate(Some("certs/web.pfx"))
+            .generate()
+            .unwrap();
+        std::fs::remove_dir_all(&project_directory).unwrap();
+        let workflow = command.get_workflow().unwrap();
+
+        let names: Vec<&str> = command.services.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["web"]);
+        assert!(workflow.contains("convert docker-compose.yml containerapps.yml"));
+        assert!(workflow.contains("--file web/Dockerfile web"));
+        assert!(!workflow.contains(project_directory.to_str().unwrap()));
+        assert!(workflow.contains(
+            "--revision-suffix '{git}' --traffic latest=100 --domain web=www.contoso.com --certificate certs/web.pfx"
+        ));
+        assert!(workflow.contains(
+            "--template-file certificates-containerapps.json --parameters certsWebPassword=\"$CERTS_WEB_PASSWORD\""
+        ));
+        assert!(!workflow.contains("Deploy web-dapr"));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::containerapps::*;
+use crate::VERBOSE;
+use anyhow::Result;
+use log::{debug, trace};
+use std::fs::{read_to_string, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Default)]
+pub struct MigrateContainerAppsCommand {
+    input_path: PathBuf,
+    output_path: Option<PathBuf>,
+    api_version: ApiVersion,
+    migration: Option<Migration>,
+}
+
+impl MigrateContainerAppsCommand {
+    pub fn with_input_path(mut self, input_file_path: &str) -> Self {
+        self.input_path = PathBuf::from(input_file_path);
+        self
+    }
+
+    pub fn with_output_path(mut self, output_file_path: Option<&str>) -> Self {
+        self.output_path = output_file_path.map(PathBuf::from);
+        self
+    }
+
+    pub fn with_api_version(mut self, api_version: Option<&str>) -> Result<Self> {
+        if let Some(v) = api_version {
+            self.api_version = ApiVersion::from_str(v)?;
+        } else {
+            self.api_version = ApiVersion::default();
+        };
+        Ok(self)
+    }
+
+    pub fn migrate(mut self) -> Result<Self> {
+        debug!("Reading {}", self.input_path.display());
+        let content = read_to_string(&self.input_path)?;
+        let value: serde_yaml::Value = serde_yaml::from_str(&content)?;
+        let migration = migrate_container_app_value(value, self.api_version)?;
+        trace!("Migrated configuration: {:?}", &migration.value);
+
+        println!(
+            "Migrated {} from {} to {}.",
+            self.input_path.display(),
+            migration.source,
+            self.api_version
+        );
+        if !migration.untranslated.is_empty() {
+            println!("Review the following, they could not be migrated automatically:");
+            for note in migration.untranslated.iter() {
+                println!("  - {}", note);
+            }
+        }
+        if *VERBOSE {
+            println!();
+            println!("ContainerApps moved from the Microsoft.Web preview to Microsoft.App, which renamed");
+            println!("kubeEnvironmentId and moved Dapr into the configuration.  The current ContainerApps");
+            println!("configuration file is documented at https://aka.ms/containerapps/spec.");
+            println!();
+        }
+
+        self.migration = Some(migration);
+        Ok(self)
+    }
+
+    /// Writes the migrated configuration, over the input file when no output
+    /// path was given.
+    pub fn write(self) -> Result<()> {
+        if let Some(migration) = self.migration.as_ref() {
+            let output_path = self.output_path.as_ref().unwrap_or(&self.input_path);
+            debug!("Writing {}", output_path.display());
+            let output_content = serde_yaml::to_string(&migration.value)?;
+            let mut file = File::create(output_path)?;
+            file.write_all(output_content.into_bytes().as_ref())?;
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,19 @@
 mod convert_compose;
+mod convert_containerapps;
+mod diff_containerapps;
+mod export_containerapp;
+mod generate_workflow;
+mod migrate_containerapps;
+mod output_manifest;
 mod retrieve_logs;
 mod validate_azure;
 
 pub use convert_compose::*;
+pub use convert_containerapps::*;
+pub use diff_containerapps::*;
+pub use export_containerapp::*;
+pub use generate_workflow::*;
+pub use migrate_containerapps::*;
+pub use output_manifest::*;
 pub use retrieve_logs::*;
 pub use validate_azure::*;
@@ -0,0 +1,48 @@
+use crate::containerapps::{ApiVersion, OutputFormat};
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// Lists what a conversion generated so scripts can pick up the files without
+/// knowing the naming rules.
+#[derive(Debug, Serialize)]
+pub struct OutputManifest {
+    format: String,
+    #[serde(rename = "apiVersion")]
+    api_version: String,
+    files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    service: Option<String>,
+}
+
+impl OutputManifest {
+    pub fn new(format: &OutputFormat, api_version: ApiVersion) -> Self {
+        OutputManifest {
+            format: format.to_string().to_lowercase(),
+            api_version: api_version.to_string(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Adds a generated file, or directory, along with the service it is for.
+    pub fn add(&mut self, path: &Path, service: Option<&str>) {
+        self.files.push(ManifestEntry {
+            path: path.display().to_string(),
+            service: service.map(|s| s.to_owned()),
+        });
+    }
+
+    pub fn write(&self, file_path: &Path) -> Result<()> {
+        let output_content = serde_json::to_string_pretty(self)?;
+        let mut file = File::create(file_path)?;
+        file.write_all(output_content.into_bytes().as_ref())?;
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@ use anyhow::Result;
 use dialoguer::Input;
 use log::{debug, trace};
 use std::str::FromStr;
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct RetrieveLogsCommand {
@@ -12,6 +13,7 @@ pub struct RetrieveLogsCommand {
     containerapps_environment_name: Option<String>,
     containerapps_environment_resource_id: Option<String>,
     max_results: Option<u32>,
+    backend: Option<Arc<dyn AzureBackend>>,
 }
 
 impl RetrieveLogsCommand {
@@ -72,10 +74,19 @@ impl RetrieveLogsCommand {
         self
     }
 
+    /// Runs az CLI commands through `runner` rather than the `az` binary.
+    #[cfg(test)]
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.backend = Some(Arc::new(AzCliBackend::default().with_runner(runner)));
+        self
+    }
+
     pub fn run(self) -> Result<()> {
         trace!("Starting to retrieve logs.");
+        self.backend()?.ensure_available()?;
         let local_self = self.validate_before_run()?;
         let mut result = get_az_monitor_logs(
+            local_self.backend()?,
             &local_self.log_analytics_client_id,
             &local_self.name,
             &local_self.max_results,
@@ -125,6 +136,7 @@ impl RetrieveLogsCommand {
                 containerapps_env_name, &self.resource_group
             );
             if let Some(v) = get_az_containerapp_environment_log_workspace_id(
+                self.backend()?,
                 Some(&self.resource_group),
                 Some(containerapps_env_name),
                 None,
@@ -138,6 +150,7 @@ impl RetrieveLogsCommand {
                 containerapps_resource_id
             );
             if let Some(v) = get_az_containerapp_environment_log_workspace_id(
+                self.backend()?,
                 None,
                 None,
                 Some(containerapps_resource_id),
@@ -148,6 +161,13 @@ impl RetrieveLogsCommand {
         Ok(self)
     }
 
+    fn backend(&self) -> Result<&dyn AzureBackend> {
+        match self.backend.as_ref() {
+            Some(backend) => Ok(backend.as_ref()),
+            None => azure_backend(),
+        }
+    }
+
     fn prompt_for_workspace_client_id(mut self) -> Result<Self> {
         if self.log_analytics_client_id.is_empty() {
             self.log_analytics_client_id = Input::new()
@@ -161,3 +181,52 @@ impl RetrieveLogsCommand {
         Ok(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_are_queried_from_the_environment_workspace() {
+        let runner = Arc::new(
+            ReplayRunner::default()
+                .with_response(
+                    &[
+                        "containerapp",
+                        "env",
+                        "show",
+                        "--resource-group",
+                        "rg",
+                        "--name",
+                        "env",
+                    ],
+                    r#"{"appLogsConfiguration": {"logAnalyticsConfiguration": {"customerId": "workspace"}}}"#,
+                )
+                .with_response(
+                    &[
+                        "monitor",
+                        "log-analytics",
+                        "query",
+                        "--workspace",
+                        "workspace",
+                        "--analytics-query",
+                        "ContainerAppConsoleLogs_CL | project ContainerAppName=ContainerAppName_s, Log=Log_s, TimeGenerated | where ContainerAppName == \"web\" | order by TimeGenerated desc nulls last | take 5",
+                    ],
+                    r#"[{"TimeGenerated": "2022-01-01T00:00:00Z", "ContainerAppName": "web", "TableName": "PrimaryResult", "Log": "started"}]"#,
+                ),
+        );
+
+        RetrieveLogsCommand::default()
+            .with_resource_group(Some("rg"))
+            .with_containerapps_environment_name(Some("env"))
+            .with_name(Some("web"))
+            .with_max_results(Some("5"))
+            .with_runner(runner.clone())
+            .run()
+            .unwrap();
+
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[1][4], "workspace");
+    }
+}
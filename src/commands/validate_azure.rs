@@ -2,6 +2,7 @@ use crate::azure::*;
 use anyhow::{anyhow, Result};
 use dialoguer::Input;
 use log::{debug, trace};
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct ValidateAzureCommand<'a> {
@@ -10,6 +11,8 @@ pub struct ValidateAzureCommand<'a> {
     location: Option<&'a str>,
     containerapps_environment_name: Option<&'a str>,
     containerapps_environment_resource_id: Option<String>,
+    dry_run: bool,
+    backend: Option<Arc<dyn AzureBackend>>,
 }
 
 impl<'a> ValidateAzureCommand<'a> {
@@ -46,8 +49,22 @@ impl<'a> ValidateAzureCommand<'a> {
         self
     }
 
+    /// Previews creating a missing environment instead of deploying it.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Runs az CLI commands through `runner` rather than the `az` binary.
+    #[cfg(test)]
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.backend = Some(Arc::new(AzCliBackend::default().with_runner(runner)));
+        self
+    }
+
     pub fn validate_azure_login(self) -> Result<Self> {
         trace!("Checking for the az CLI and if we are logged into Azure.");
+        self.backend()?.ensure_available()?;
         let subscription: String = if let Some(s) = self.subscription_name {
             s.to_string()
         } else {
@@ -56,22 +73,51 @@ impl<'a> ValidateAzureCommand<'a> {
                 .interact_text()?
         };
         debug!("Logging in to Azure subscription {}", &subscription);
-        set_azure_environment(&subscription)?;
+        set_azure_environment(self.backend()?, &subscription)?;
         debug!("Logged in to Azure subscription {}", &subscription);
         Ok(self)
     }
 
     pub fn retrieve_containerapps_environment(mut self) -> Result<Self> {
-        setup_extensions_and_preview_commands()?;
+        if !self.dry_run {
+            setup_extensions_and_preview_commands(self.backend()?)?;
+        }
         if self.resource_group.is_some() && self.containerapps_environment_name.is_some() {
             self.containerapps_environment_resource_id =
                 get_az_containerapp_environment_resource_id(
+                    self.backend()?,
                     self.get_resource_group()?,
                     self.get_containerapps_environment_name()?,
                 )?;
         }
+        if self.containerapps_environment_resource_id.is_none() && self.dry_run {
+            let changes = what_if_containerapps_env(
+                self.backend()?,
+                self.get_resource_group()?,
+                self.get_containerapps_environment_name()?,
+                self.get_location()?,
+            )?;
+            print_what_if_changes("ContainerApps environment", &changes);
+            // The apps are previewed against the id the environment would get.
+            let environment_suffix = format!(
+                "/managedEnvironments/{}",
+                self.get_containerapps_environment_name()?
+            );
+            let environment = changes
+                .iter()
+                .find(|c| {
+                    c.resource_id
+                        .to_lowercase()
+                        .ends_with(&environment_suffix.to_lowercase())
+                })
+                .ok_or_else(|| {
+                    anyhow!("The preview did not include the ContainerApps environment")
+                })?;
+            self.containerapps_environment_resource_id = Some(environment.resource_id.to_owned());
+        }
         if self.containerapps_environment_resource_id.is_none() {
             self.containerapps_environment_resource_id = Some(deploy_containerapps_env(
+                self.backend()?,
                 self.get_resource_group()?,
                 self.get_containerapps_environment_name()?,
                 self.get_location()?,
@@ -90,6 +136,13 @@ impl<'a> ValidateAzureCommand<'a> {
         }
     }
 
+    fn backend(&self) -> Result<&dyn AzureBackend> {
+        match self.backend.as_ref() {
+            Some(backend) => Ok(backend.as_ref()),
+            None => azure_backend(),
+        }
+    }
+
     fn get_resource_group(&self) -> Result<&str> {
         let value = self.resource_group.unwrap();
         Ok(value)
@@ -105,3 +158,78 @@ impl<'a> ValidateAzureCommand<'a> {
         Ok(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_in_and_finds_the_environment() {
+        let runner = Arc::new(
+            ReplayRunner::default()
+                .with_failure(
+                    &["account", "show", "--output", "json"],
+                    "ERROR: Please run 'az login' to setup account.",
+                )
+                .with_response(
+                    &["account", "show", "--output", "json"],
+                    r#"{"id": "1234", "name": "other", "tenantId": "5678"}"#,
+                )
+                .with_failure(&["login"], "WARNING: You have logged in.")
+                .with_response(&["account", "set", "--subscription", "demo"], "")
+                .with_response(
+                    &[
+                        "extension",
+                        "add",
+                        "--name",
+                        "containerapp",
+                        "--upgrade",
+                        "--yes",
+                    ],
+                    "",
+                )
+                .with_response(
+                    &["extension", "add", "--name", "log-anaytics", "--yes"],
+                    "",
+                )
+                .with_response(
+                    &["provider", "register", "--namespace", "Microsoft.App"],
+                    "",
+                )
+                .with_response(
+                    &[
+                        "containerapp",
+                        "env",
+                        "show",
+                        "--resource-group",
+                        "rg",
+                        "--name",
+                        "env",
+                    ],
+                    r#"{"id": "/subscriptions/1234/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env"}"#,
+                ),
+        );
+
+        let command = ValidateAzureCommand::default()
+            .with_subscription_name(Some("demo"))
+            .with_resource_group(Some("rg"))
+            .with_location(Some("eastus"))
+            .with_containerapps_environment_name(Some("env"))
+            .with_runner(runner.clone())
+            .validate_azure_login()
+            .unwrap()
+            .retrieve_containerapps_environment()
+            .unwrap();
+
+        assert_eq!(
+            command.containerapps_environment_id().unwrap(),
+            "/subscriptions/1234/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env"
+        );
+        let calls: Vec<String> = runner.calls().iter().map(|c| c.join(" ")).collect();
+        assert_eq!(calls[0], "account show --output json");
+        assert_eq!(calls[1], "login");
+        assert_eq!(calls[2], "account show --output json");
+        assert_eq!(calls[3], "account set --subscription demo");
+        assert_eq!(calls.len(), 8);
+    }
+}
@@ -0,0 +1,398 @@
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde_yaml::{Mapping, Value};
+use std::env;
+use std::fs::{read_to_string, remove_file, write, File};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+use super::Compose;
+
+const EXTENSION_PREFIX: &str = "x-";
+
+/// Where a service's image is built from, as written in the Compose file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServiceBuild {
+    pub context: String,
+    pub dockerfile: Option<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ComposeDocument {
+    document: Value,
+    project_directory: PathBuf,
+}
+
+impl ComposeDocument {
+    pub fn new(document: Value, project_directory: &Path) -> Self {
+        ComposeDocument {
+            document,
+            project_directory: project_directory.to_path_buf(),
+        }
+    }
+
+    pub fn compose(&self) -> Result<Compose> {
+        let mut document = self.document.clone();
+        strip_extensions(&mut document);
+        if let Some(services) = document.get_mut("services").and_then(Value::as_mapping_mut) {
+            for (_, service) in services.iter_mut() {
+                strip_extensions(service);
+                self.merge_env_files(service)?;
+            }
+        }
+        // compose_yml validates against the Compose schema when it reads a
+        // file, so the document without extensions goes through one.
+        let path = env::temp_dir().join(format!("compose2containerapp-{}.yml", Uuid::new_v4()));
+        write(&path, serde_yaml::to_string(&document)?)?;
+        let config = Compose::read_from_path(&path);
+        remove_file(&path)?;
+        config.map_err(|e| anyhow!("The Compose file is not valid - {}", e))
+    }
+
+    pub fn project_directory(&self) -> &Path {
+        &self.project_directory
+    }
+
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        if get_drive_prefix_length(path) > 0 {
+            return PathBuf::from(path);
+        }
+        let path = Path::new(path);
+        if path.is_absolute() {
+            return path.to_path_buf();
+        }
+        let relative: PathBuf = path
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .collect();
+        if relative.as_os_str().is_empty() {
+            self.project_directory.to_path_buf()
+        } else if self.project_directory == Path::new(".") {
+            relative
+        } else {
+            self.project_directory.join(relative)
+        }
+    }
+
+    /// Adds the variables from a service's `env_file`s to its `environment`,
+    /// where they aren't already set, the way Compose does.
+    fn merge_env_files(&self, service: &mut Value) -> Result<()> {
+        let env_files: Vec<(String, bool)> = match service.get("env_file") {
+            Some(Value::Sequence(files)) => files
+                .iter()
+                .filter_map(|file| match file {
+                    Value::Mapping(_) => Some((
+                        file.get("path").and_then(scalar)?,
+                        file.get("required")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(true),
+                    )),
+                    _ => scalar(file).map(|f| (f, true)),
+                })
+                .collect(),
+            Some(file) => scalar(file).map(|f| (f, true)).into_iter().collect(),
+            None => return Ok(()),
+        };
+        if let Some(service) = service.as_mapping_mut() {
+            service.remove(&Value::from("env_file"));
+        }
+        for (file, required) in env_files {
+            let path = self.resolve_path(&file);
+            let content = match read_to_string(&path) {
+                Ok(content) => content,
+                Err(_) if !required => continue,
+                Err(e) => {
+                    return Err(anyhow!(
+                        "Unable to read the env_file {} - {}",
+                        path.display(),
+                        e
+                    ))
+                }
+            };
+            debug!("Reading environment variables from {}", path.display());
+            for (name, value) in get_env_file_variables(&content) {
+                set_default_environment_variable(service, &name, &value);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn extension(&self, name: &str) -> Option<&Value> {
+        self.document.get(name)
+    }
+
+    pub fn service(&self, service_name: &str) -> Option<&Value> {
+        self.document.get("services")?.get(service_name)
+    }
+
+    pub fn service_extension(&self, service_name: &str, name: &str) -> Option<&Value> {
+        self.service(service_name)?.get(name)
+    }
+
+    pub fn service_command(&self, service_name: &str) -> Vec<String> {
+        let mut command = Vec::new();
+        for key in &["entrypoint", "command"] {
+            match self.service(service_name).and_then(|s| s.get(key)) {
+                // Compose splits string commands the way a shell would.
+                Some(Value::String(s)) => match shell_words::split(s) {
+                    Ok(words) => command.extend(words),
+                    Err(e) => debug!("Unable to split the {} of {}: {}", key, service_name, e),
+                },
+                Some(Value::Sequence(items)) => command.extend(items.iter().filter_map(scalar)),
+                _ => {}
+            }
+        }
+        command
+    }
+
+    pub fn service_volume_sources(&self, service_name: &str) -> Vec<PathBuf> {
+        let volumes = match self
+            .service(service_name)
+            .and_then(|s| s.get("volumes"))
+            .and_then(Value::as_sequence)
+        {
+            Some(v) => v,
+            None => return Vec::new(),
+        };
+        volumes
+            .iter()
+            .filter_map(|volume| match volume {
+                Value::String(s) => get_volume_source(s).map(|source| source.to_owned()),
+                Value::Mapping(_) => volume
+                    .get("source")
+                    .and_then(Value::as_str)
+                    .map(|s| s.to_owned()),
+                _ => None,
+            })
+            .filter(|source| {
+                source.starts_with('.')
+                    || Path::new(source).is_absolute()
+                    || get_drive_prefix_length(source) > 0
+            })
+            .map(|source| self.resolve_path(&source))
+            .collect()
+    }
+
+    /// Services listed in `depends_on`, in either the list or the long form.
+    /// Compose `secrets` of a service that are read from a file, by name.
+    pub fn service_secret_files(&self, service_name: &str) -> Vec<(String, PathBuf)> {
+        let secrets = match self
+            .service(service_name)
+            .and_then(|s| s.get("secrets"))
+            .and_then(Value::as_sequence)
+        {
+            Some(secrets) => secrets,
+            None => return Vec::new(),
+        };
+        secrets
+            .iter()
+            .filter_map(|secret| match secret {
+                Value::Mapping(_) => secret.get("source").and_then(scalar),
+                _ => scalar(secret),
+            })
+            .filter_map(|name| {
+                let file = self
+                    .document
+                    .get("secrets")?
+                    .get(name.as_str())?
+                    .get("file")
+                    .and_then(scalar)?;
+                Some((name, self.resolve_path(&file)))
+            })
+            .collect()
+    }
+
+    pub fn service_depends_on(&self, service_name: &str) -> Vec<String> {
+        match self.service(service_name).and_then(|s| s.get("depends_on")) {
+            Some(Value::Sequence(services)) => services.iter().filter_map(scalar).collect(),
+            Some(Value::Mapping(services)) => {
+                services.iter().filter_map(|(k, _)| scalar(k)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The `build` of a service, in either the short or the long form.  The
+    /// context is resolved against the project directory.
+    pub fn service_build(&self, service_name: &str) -> Option<ServiceBuild> {
+        match self.service(service_name)?.get("build")? {
+            Value::String(context) => Some(ServiceBuild {
+                context: self.resolve_path(context).display().to_string(),
+                dockerfile: None,
+            }),
+            build @ Value::Mapping(_) => Some(ServiceBuild {
+                context: self
+                    .resolve_path(
+                        &build
+                            .get("context")
+                            .and_then(scalar)
+                            .unwrap_or_else(|| ".".to_string()),
+                    )
+                    .display()
+                    .to_string(),
+                dockerfile: build.get("dockerfile").and_then(scalar),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The source of a short syntax volume, `SOURCE:TARGET[:MODE]`.  Windows
+/// sources start with a drive, like `C:\\components`, whose colon isn't a
+/// separator.
+fn get_volume_source(volume: &str) -> Option<&str> {
+    let drive = get_drive_prefix_length(volume);
+    let separator = volume[drive..].find(':')? + drive;
+    Some(&volume[..separator])
+}
+
+fn get_drive_prefix_length(path: &str) -> usize {
+    let bytes = path.as_bytes();
+    if bytes.len() > 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/')
+    {
+        2
+    } else {
+        0
+    }
+}
+
+pub fn read_compose_document(path: &Path) -> Result<ComposeDocument> {
+    read_compose_document_from_reader(File::open(path)?, &get_project_directory(path))
+}
+
+/// Reads a Compose document from any reader, such as stdin.  There's no file
+/// to resolve relative paths from, so they resolve against `project_directory`.
+pub fn read_compose_document_from_reader<R: Read>(
+    mut reader: R,
+    project_directory: &Path,
+) -> Result<ComposeDocument> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    let document: Value = serde_yaml::from_str(&content)?;
+    debug!(
+        "Resolving relative Compose paths from {}",
+        project_directory.display()
+    );
+    Ok(ComposeDocument::new(document, project_directory))
+}
+
+/// The directory a Compose file's relative paths resolve from, the one it's in.
+pub fn get_project_directory(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Replaces `${VARIABLE}` references in extension values with the matching
+/// environment variable, leaving unresolved references untouched.
+pub fn interpolate_extension_value(value: &str) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        match rest[start..].find('}') {
+            Some(end) => {
+                let reference = &rest[start..start + end + 1];
+                let name = &reference[2..reference.len() - 1];
+                match env::var(name) {
+                    Ok(v) => result.push_str(&v),
+                    Err(_) => {
+                        debug!("Unable to resolve {} in a Compose extension.", name);
+                        result.push_str(reference);
+                    }
+                }
+                rest = &rest[start + end + 1..];
+            }
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn strip_extensions(value: &mut Value) {
+    if let Some(mapping) = value.as_mapping_mut() {
+        let extension_keys: Vec<Value> = mapping
+            .iter()
+            .filter(|(k, _)| is_extension_key(k))
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in extension_keys {
+            mapping.remove(&key);
+        }
+    }
+}
+
+fn is_extension_key(key: &Value) -> bool {
+    match key.as_str() {
+        Some(k) => k.starts_with(EXTENSION_PREFIX),
+        None => false,
+    }
+}
+
+/// `NAME=VALUE` lines of an env file, skipping blank lines and comments.
+fn get_env_file_variables(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            (name.trim().to_owned(), value.to_owned())
+        })
+        .collect()
+}
+
+/// Sets a variable in a service's `environment`, in either the mapping or the
+/// list form, unless it's already there.
+fn set_default_environment_variable(service: &mut Value, name: &str, value: &str) {
+    let service = match service.as_mapping_mut() {
+        Some(service) => service,
+        None => return,
+    };
+    let environment = service
+        .entry(Value::from("environment"))
+        .or_insert_with(|| Value::Mapping(Mapping::new()));
+    match environment {
+        Value::Mapping(variables) => {
+            let name = Value::from(name);
+            if !variables.contains_key(&name) {
+                variables.insert(name, Value::from(value));
+            }
+        }
+        Value::Sequence(variables) => {
+            let prefix = format!("{}=", name);
+            if !variables
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|v| v == name || v.starts_with(&prefix))
+            {
+                variables.push(Value::from(format!("{}={}", name, value)));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.to_owned()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
@@ -1,18 +1,14 @@
-use anyhow::Result;
-use std::path::Path;
+mod document;
 
 pub use compose_yml::v3::File as Compose;
 pub use compose_yml::v3::{PortMapping, Ports, Protocol, Service};
-
-pub fn read_compose_file(path: &Path) -> Result<Compose> {
-    let config: Compose = Compose::read_from_path(path)?;
-    Ok(config)
-}
+pub use document::*;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs::File;
+    use std::path::Path;
 
     #[test]
     fn default_compose_can_serialize() {
@@ -25,4 +21,100 @@ mod tests {
         let file = File::open("test/docker-compose.yml").unwrap();
         let _config: Compose = serde_yaml::from_reader(file).unwrap();
     }
+
+    #[test]
+    fn compose_with_extensions_deserializes_properly() {
+        let path = Path::new("test/dapr_components_convert/docker-compose.yml");
+        let config = read_compose_document(path).unwrap().compose().unwrap();
+        assert!(config.services.contains_key("app"));
+    }
+
+    #[test]
+    fn compose_from_a_reader_resolves_paths_from_the_project_directory() {
+        let content = "version: \"3\"\nservices:\n  web:\n    image: nginx\n";
+        let document =
+            read_compose_document_from_reader(content.as_bytes(), Path::new("/srv/app")).unwrap();
+        assert!(document.compose().unwrap().services.contains_key("web"));
+        assert_eq!(
+            document.resolve_path("certs/web.pfx"),
+            Path::new("/srv/app/certs/web.pfx")
+        );
+    }
+
+    #[test]
+    fn commands_split_like_a_shell_and_windows_volumes_keep_their_drive() {
+        let content = r#"
+version: "3"
+services:
+  app-dapr:
+    image: daprio/daprd
+    command: ./daprd -app-id "my app" -components-path /components
+    volumes:
+      - C:\components:/components:ro
+      - ./local:/local
+"#;
+        let document =
+            read_compose_document_from_reader(content.as_bytes(), Path::new("/srv/app")).unwrap();
+
+        assert_eq!(
+            document.service_command("app-dapr"),
+            vec![
+                "./daprd",
+                "-app-id",
+                "my app",
+                "-components-path",
+                "/components"
+            ]
+        );
+        assert_eq!(
+            document.service_volume_sources("app-dapr"),
+            vec![
+                Path::new("C:\\components").to_path_buf(),
+                Path::new("/srv/app/./local").to_path_buf()
+            ]
+        );
+    }
+
+    #[test]
+    fn env_files_build_contexts_and_secret_files_resolve_from_the_project_directory() {
+        let project_directory =
+            std::env::temp_dir().join(format!("compose-project-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&project_directory).unwrap();
+        std::fs::write(
+            project_directory.join("web.env"),
+            "# settings\nLOG_LEVEL=debug\nGREETING=\"hello\"\n",
+        )
+        .unwrap();
+        let content = r#"
+version: "3"
+services:
+  web:
+    image: nginx
+    build: ./web
+    env_file: ./web.env
+    environment:
+      LOG_LEVEL: info
+    secrets:
+      - api_key
+secrets:
+  api_key:
+    file: ./api_key.txt
+"#;
+        let document =
+            read_compose_document_from_reader(content.as_bytes(), &project_directory).unwrap();
+        let config = document.compose().unwrap();
+        std::fs::remove_dir_all(&project_directory).unwrap();
+
+        let environment = &config.services["web"].environment;
+        assert_eq!(environment["LOG_LEVEL"].to_string(), "info");
+        assert_eq!(environment["GREETING"].to_string(), "hello");
+        assert_eq!(
+            document.service_build("web").unwrap().context,
+            project_directory.join("web").display().to_string()
+        );
+        assert_eq!(
+            document.service_secret_files("web"),
+            vec![("api_key".to_string(), project_directory.join("api_key.txt"))]
+        );
+    }
 }
@@ -0,0 +1,230 @@
+use super::ContainerAppConfig;
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// The ContainerApps API version to write resources for.  2021-03-01 is the
+/// original `Microsoft.Web` preview, later versions target `Microsoft.App`.
+/// Variants are in release order so versions compare oldest to newest.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    V2021_03_01,
+    V2022_03_01,
+    #[default]
+    V2023_05_01,
+}
+impl ApiVersion {
+    pub fn variants() -> [&'static str; 3] {
+        ["2021-03-01", "2022-03-01", "2023-05-01"]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiVersion::V2021_03_01 => "2021-03-01",
+            ApiVersion::V2022_03_01 => "2022-03-01",
+            ApiVersion::V2023_05_01 => "2023-05-01",
+        }
+    }
+
+    pub fn container_apps_type(&self) -> &'static str {
+        match self {
+            ApiVersion::V2021_03_01 => "Microsoft.Web/containerApps",
+            _ => "Microsoft.App/containerApps",
+        }
+    }
+
+    pub fn environment_type(&self) -> &'static str {
+        match self {
+            ApiVersion::V2021_03_01 => "Microsoft.Web/kubeEnvironments",
+            _ => "Microsoft.App/managedEnvironments",
+        }
+    }
+
+    pub fn dapr_components_type(&self) -> String {
+        format!("{}/daprComponents", self.environment_type())
+    }
+
+    pub fn certificates_type(&self) -> String {
+        format!("{}/certificates", self.environment_type())
+    }
+
+    /// Property of a ContainerApp that holds the environment resource id.
+    /// The 2023-05-01 schema (`Microsoft.App/stable/2023-05-01/ContainerApps.json`
+    /// in azure-rest-api-specs) adds `environmentId` and marks
+    /// `managedEnvironmentId` as deprecated.
+    pub fn environment_id_property(&self) -> &'static str {
+        match self {
+            ApiVersion::V2021_03_01 => "kubeEnvironmentId",
+            ApiVersion::V2022_03_01 => "managedEnvironmentId",
+            ApiVersion::V2023_05_01 => "environmentId",
+        }
+    }
+
+    /// Serializes a ContainerApp in the shape this API version expects,
+    /// keeping the field order of the model.
+    pub fn to_container_app_value(self, config: &ContainerAppConfig) -> Result<Value> {
+        self.check_supported(config)?;
+        let value = serde_yaml::to_value(config)?;
+        let mut versioned = Mapping::new();
+        if let Value::Mapping(fields) = value {
+            for (key, field) in fields {
+                match key.as_str() {
+                    Some("kind") if self != ApiVersion::V2021_03_01 => {}
+                    Some("type") => {
+                        versioned.insert(key, Value::from(self.container_apps_type()));
+                    }
+                    Some("properties") => {
+                        versioned.insert(key, self.to_properties_value(field));
+                    }
+                    _ => {
+                        versioned.insert(key, field);
+                    }
+                }
+            }
+        }
+        Ok(Value::Mapping(versioned))
+    }
+
+    /// Fails when the app uses properties this API version doesn't have, as
+    /// Azure would reject or silently drop them.  Key Vault secrets, CORS,
+    /// sticky sessions, client certificates, IP restrictions and registry
+    /// identities are all newer than 2022-03-01.
+    pub fn check_supported(self, config: &ContainerAppConfig) -> Result<()> {
+        if self >= ApiVersion::V2023_05_01 {
+            return Ok(());
+        }
+        let configuration = &config.properties.configuration;
+        let mut unsupported = Vec::new();
+        if configuration
+            .secrets
+            .iter()
+            .any(|s| s.key_vault_url.is_some() || s.identity.is_some())
+        {
+            unsupported.push("Key Vault secret references");
+        }
+        if configuration
+            .registries
+            .iter()
+            .any(|r| r.identity.is_some())
+        {
+            unsupported.push("registry identities");
+        }
+        if let Some(ingress) = configuration.ingress.as_ref() {
+            if ingress.cors_policy.is_some() {
+                unsupported.push("CORS policies");
+            }
+            if ingress.sticky_sessions.is_some() {
+                unsupported.push("sticky sessions");
+            }
+            if ingress.client_certificate_mode.is_some() {
+                unsupported.push("client certificate modes");
+            }
+            if !ingress.ip_security_restrictions.is_empty() {
+                unsupported.push("IP security restrictions");
+            }
+        }
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{} uses {} which API version {} doesn't support, use --api-version {}",
+                config.name,
+                unsupported.join(", "),
+                self,
+                ApiVersion::V2023_05_01
+            ))
+        }
+    }
+
+    fn to_properties_value(self, properties: Value) -> Value {
+        let mut versioned = Mapping::new();
+        if let Value::Mapping(fields) = properties {
+            for (key, field) in fields {
+                if key.as_str() == Some("kubeEnvironmentId") {
+                    versioned.insert(Value::from(self.environment_id_property()), field);
+                } else {
+                    versioned.insert(key, field);
+                }
+            }
+        }
+        Value::Mapping(versioned)
+    }
+}
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl FromStr for ApiVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2021-03-01" => Ok(ApiVersion::V2021_03_01),
+            "2022-03-01" => Ok(ApiVersion::V2022_03_01),
+            "2023-05-01" => Ok(ApiVersion::V2023_05_01),
+            _ => Err(anyhow!(
+                "Unsupported ContainerApps API version {}, use one of {}",
+                s,
+                ApiVersion::variants().join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::containerapps::{CorsPolicy, IngressConfiguration, SecretsConfiguration};
+
+    #[test]
+    fn managed_environment_versions_rename_the_environment_id() {
+        let config = ContainerAppConfig::default();
+
+        let legacy = ApiVersion::V2021_03_01
+            .to_container_app_value(&config)
+            .unwrap();
+        assert_eq!(legacy["type"], "Microsoft.Web/containerApps");
+        assert!(legacy["properties"]["kubeEnvironmentId"].is_string());
+
+        let current = ApiVersion::V2023_05_01
+            .to_container_app_value(&config)
+            .unwrap();
+        assert_eq!(current["type"], "Microsoft.App/containerApps");
+        assert!(current["properties"]["environmentId"].is_string());
+        assert!(current["properties"].get("kubeEnvironmentId").is_none());
+        assert!(current.get("kind").is_none());
+    }
+
+    #[test]
+    fn newer_properties_need_a_newer_version() {
+        let mut config = ContainerAppConfig {
+            name: "web".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        config.properties.configuration.ingress = Some(IngressConfiguration {
+            cors_policy: Some(CorsPolicy::default()),
+            ..IngressConfiguration::default()
+        });
+        config
+            .properties
+            .configuration
+            .secrets
+            .push(SecretsConfiguration {
+                name: "db".to_string(),
+                key_vault_url: Some("https://vault.vault.azure.net/secrets/db".to_string()),
+                ..SecretsConfiguration::default()
+            });
+
+        let error = ApiVersion::V2022_03_01
+            .to_container_app_value(&config)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("Key Vault secret references, CORS policies"));
+        assert!(error.contains("--api-version 2023-05-01"));
+        assert!(ApiVersion::default()
+            .to_container_app_value(&config)
+            .is_ok());
+    }
+}
@@ -0,0 +1,298 @@
+use super::{get_parameter_name, ArmParameter, ContainerAppConfig};
+use anyhow::{anyhow, Result};
+use log::{trace, warn};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs::{remove_file, File, OpenOptions};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Values in a ContainerApp that can be lifted into ARM template parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateParameter {
+    Environment,
+    Location,
+    Secrets,
+    Env,
+    Images,
+}
+impl TemplateParameter {
+    pub fn defaults() -> Vec<TemplateParameter> {
+        vec![
+            TemplateParameter::Environment,
+            TemplateParameter::Location,
+            TemplateParameter::Secrets,
+        ]
+    }
+
+    /// Reads a `--template-parameters` selection, or the defaults without one.
+    /// Secrets are always lifted so their values never end up in a template.
+    pub fn from_names(names: Option<&[String]>) -> Result<Vec<TemplateParameter>> {
+        let mut selection = match names {
+            Some(names) => names
+                .iter()
+                .map(|n| TemplateParameter::from_str(n))
+                .collect::<Result<Vec<TemplateParameter>>>()?,
+            None => TemplateParameter::defaults(),
+        };
+        if !selection.contains(&TemplateParameter::Secrets) {
+            selection.push(TemplateParameter::Secrets);
+        }
+        Ok(selection)
+    }
+}
+impl fmt::Display for TemplateParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl FromStr for TemplateParameter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "environment" => Ok(TemplateParameter::Environment),
+            "location" => Ok(TemplateParameter::Location),
+            "secrets" => Ok(TemplateParameter::Secrets),
+            "env" => Ok(TemplateParameter::Env),
+            "images" => Ok(TemplateParameter::Images),
+            _ => Err(anyhow!(
+                "Unknown template parameter {}, use environment, location, secrets, env or images",
+                s
+            )),
+        }
+    }
+}
+
+/// Replaces the selected values in `config` with `[parameters('...')]`
+/// expressions, adding the parameters to the template.  Secrets become secure
+/// parameters, the rest default to their current value.  Secret names are
+/// prefixed with `prefix` so apps in the same template don't collide.
+///
+/// Returns the values of the non-secure parameters for the parameters file.
+pub(super) fn lift_template_parameters(
+    config: &mut ContainerAppConfig,
+    prefix: Option<&str>,
+    selection: &[TemplateParameter],
+    parameters: &mut BTreeMap<String, ArmParameter>,
+) -> BTreeMap<String, String> {
+    let mut values = BTreeMap::new();
+    let mut lift = |name: String, value: &mut String| {
+        parameters
+            .entry(name.clone())
+            .or_insert_with(|| ArmParameter::string(value));
+        values.entry(name.clone()).or_insert_with(|| value.clone());
+        *value = format!("[parameters('{}')]", name);
+    };
+
+    if selection.contains(&TemplateParameter::Environment) {
+        lift(
+            "environmentId".to_string(),
+            &mut config.properties.kube_environment_id,
+        );
+    }
+    if selection.contains(&TemplateParameter::Location) {
+        lift("location".to_string(), &mut config.location);
+    }
+    for container in config.properties.template.containers.iter_mut() {
+        if selection.contains(&TemplateParameter::Images) {
+            let name = get_parameter_name(&format!("{}-image", &container.name));
+            lift(name, &mut container.image);
+        }
+        if selection.contains(&TemplateParameter::Env) {
+            for env in container.env.iter_mut() {
+                if let Some(value) = env.value.as_mut() {
+                    let name = get_parameter_name(&format!(
+                        "{}-{}",
+                        &container.name,
+                        env.name.to_lowercase()
+                    ));
+                    lift(name, value);
+                }
+            }
+        }
+    }
+    if selection.contains(&TemplateParameter::Secrets) {
+        for secret in config.properties.configuration.secrets.iter_mut() {
+            if secret.value.is_some() {
+                let name = match prefix {
+                    Some(p) => get_parameter_name(&format!("{}-{}", p, &secret.name)),
+                    None => get_parameter_name(&secret.name),
+                };
+                secret.value = Some(format!("[parameters('{}')]", &name));
+                parameters.insert(name, ArmParameter::secure_string());
+            }
+        }
+    }
+    values
+}
+
+#[derive(Serialize)]
+struct ArmParametersFile {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    #[serde(rename = "contentVersion")]
+    content_version: &'static str,
+    parameters: BTreeMap<String, ArmParameterValue>,
+}
+
+#[derive(Serialize)]
+struct ArmParameterValue {
+    value: String,
+}
+
+/// Writes a `*.parameters.json` file for a template.  Secure parameters are
+/// left out so no secret ends up on disk; supply them at deployment time.
+pub fn write_arm_parameters_file(
+    file_path: &Path,
+    values: &BTreeMap<String, String>,
+) -> Result<()> {
+    let mut file = File::create(file_path)?;
+    file.write_all(get_arm_parameters_content(values)?.as_bytes())?;
+    Ok(())
+}
+
+/// A parameters file for secure values, like secrets and certificate
+/// passwords, passed to a deployment as `@file` so they don't show up on the
+/// command line or in the logs.  Only the current user can read it, and it is
+/// removed when dropped.
+pub struct SecureParametersFile {
+    path: PathBuf,
+}
+impl SecureParametersFile {
+    pub fn write(values: &BTreeMap<String, String>) -> Result<SecureParametersFile> {
+        let path = env::temp_dir().join(format!(
+            "compose2containerapp-{}.parameters.json",
+            Uuid::new_v4()
+        ));
+        trace!("Writing secure parameters to {}", path.display());
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&path)?;
+        let parameters_file = SecureParametersFile { path };
+        file.write_all(get_arm_parameters_content(values)?.as_bytes())?;
+        Ok(parameters_file)
+    }
+
+    /// The deployment parameter that reads the file.
+    pub fn parameter(&self) -> String {
+        format!("@{}", self.path.display())
+    }
+}
+impl Drop for SecureParametersFile {
+    fn drop(&mut self) {
+        if let Err(e) = remove_file(&self.path) {
+            warn!("Unable to remove {} - {}", self.path.display(), e);
+        }
+    }
+}
+
+fn get_arm_parameters_content(values: &BTreeMap<String, String>) -> Result<String> {
+    let parameters_file = ArmParametersFile {
+        schema: "https://schema.management.azure.com/schemas/2019-04-01/deploymentParameters.json#",
+        content_version: "1.0.0.0",
+        parameters: values
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.to_owned(),
+                    ArmParameterValue {
+                        value: value.to_owned(),
+                    },
+                )
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&parameters_file)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::containerapps::{Container, EnvironmentConfiguration, SecretsConfiguration};
+
+    #[test]
+    fn selected_values_are_lifted_into_parameters() {
+        let mut config = ContainerAppConfig {
+            name: "web".to_string(),
+            location: "eastus".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        config.properties.template.containers.push(Container {
+            name: "web".to_string(),
+            image: "nginx".to_string(),
+            env: vec![EnvironmentConfiguration {
+                name: "DB_HOST".to_string(),
+                value: Some("db".to_string()),
+                secret_ref: None,
+            }],
+            ..Container::default()
+        });
+        config
+            .properties
+            .configuration
+            .secrets
+            .push(SecretsConfiguration {
+                name: "db-password".to_string(),
+                value: Some("hunter2".to_string()),
+                ..SecretsConfiguration::default()
+            });
+
+        let mut parameters = BTreeMap::new();
+        let selection = vec![
+            TemplateParameter::Location,
+            TemplateParameter::Env,
+            TemplateParameter::Secrets,
+        ];
+        let values = lift_template_parameters(&mut config, None, &selection, &mut parameters);
+
+        assert_eq!(config.location, "[parameters('location')]");
+        assert_eq!(
+            config.properties.template.containers[0].env[0].value,
+            Some("[parameters('webDbHost')]".to_string())
+        );
+        assert_eq!(config.properties.template.containers[0].image, "nginx");
+        assert_eq!(parameters["dbPassword"].parameter_type, "securestring");
+        assert_eq!(values.get("webDbHost"), Some(&"db".to_string()));
+        assert!(!values.contains_key("dbPassword"));
+    }
+
+    #[test]
+    fn secure_parameters_are_private_and_removed() {
+        let values = BTreeMap::from([("dbPassword".to_string(), "hunter2".to_string())]);
+        let parameters_file = SecureParametersFile::write(&values).unwrap();
+        let path = PathBuf::from(parameters_file.parameter().trim_start_matches('@'));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\"hunter2\""));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        drop(parameters_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn selections_reject_unknown_names_and_keep_secrets() {
+        let names = vec!["images".to_string()];
+        assert_eq!(
+            TemplateParameter::from_names(Some(&names)).unwrap(),
+            vec![TemplateParameter::Images, TemplateParameter::Secrets]
+        );
+
+        let names = vec!["image".to_string()];
+        assert!(TemplateParameter::from_names(Some(&names)).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+use super::bicep::{get_bicep_string, get_containerapps_bicep_module, get_secret_parameters};
+use super::{
+    get_environment_variable_name, get_parameter_name, get_secret_parameter_name,
+    write_arm_parameters_file, ApiVersion, ContainerAppConfig,
+};
+use anyhow::Result;
+use log::debug;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{create_dir_all, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+const AZURE_YAML_SCHEMA: &str = "# yaml-language-server: $schema=https://raw.githubusercontent.com/Azure/azure-dev/main/schemas/v1.0/azure.yaml.json\n\n";
+const LOG_ANALYTICS_MODULE: &str = include_str!("../support/modules/createLogAnalytics.bicep");
+const CONTAINERAPPS_ENVIRONMENT_MODULE: &str =
+    include_str!("../support/modules/createContainerAppEnv.bicep");
+
+/// A ContainerApp to scaffold as an azd service.  Services with a `project`
+/// are built by azd, the rest deploy the image in their configuration.
+pub struct AzdService<'a> {
+    pub config: &'a ContainerAppConfig,
+    pub project: Option<String>,
+    pub dockerfile: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AzdProject {
+    name: String,
+    services: BTreeMap<String, AzdProjectService>,
+}
+
+#[derive(Serialize)]
+struct AzdProjectService {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    host: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    docker: Option<AzdDocker>,
+}
+
+#[derive(Serialize)]
+struct AzdDocker {
+    path: String,
+}
+
+#[derive(Serialize)]
+struct AzdConfig {
+    version: u32,
+    #[serde(rename = "defaultEnvironment")]
+    default_environment: String,
+}
+
+/// Writes an Azure Developer CLI project into `project_directory`:
+/// `azure.yaml` with a service per ContainerApp, `infra/` with a subscription
+/// scoped Bicep template that creates the resource group, Log Analytics and
+/// ContainerApps environment before the apps, and an `.azure/` environment
+/// named `environment_name` when one doesn't already exist.
+///
+/// Secrets are wired to environment variables in `main.parameters.json`; their
+/// values are never written.  Returns the variables that need to be set.
+pub fn write_azd_project(
+    project_directory: &Path,
+    project_name: &str,
+    environment_name: &str,
+    location: &str,
+    services: &[AzdService],
+    api_version: ApiVersion,
+) -> Result<Vec<String>> {
+    let infra_directory = project_directory.join("infra");
+    create_dir_all(infra_directory.join("app"))?;
+    create_dir_all(infra_directory.join("modules"))?;
+
+    let project = AzdProject {
+        name: project_name.to_owned(),
+        services: services
+            .iter()
+            .map(|service| (service.config.name.to_owned(), get_project_service(service)))
+            .collect(),
+    };
+    let output_content = format!("{}{}", AZURE_YAML_SCHEMA, serde_yaml::to_string(&project)?);
+    write_file(&project_directory.join("azure.yaml"), &output_content)?;
+
+    write_file(
+        &infra_directory
+            .join("modules")
+            .join("createLogAnalytics.bicep"),
+        LOG_ANALYTICS_MODULE,
+    )?;
+    write_file(
+        &infra_directory
+            .join("modules")
+            .join("createContainerAppEnv.bicep"),
+        CONTAINERAPPS_ENVIRONMENT_MODULE,
+    )?;
+
+    let mut parameter_values = BTreeMap::new();
+    parameter_values.insert(
+        "environmentName".to_string(),
+        "${AZURE_ENV_NAME}".to_string(),
+    );
+    parameter_values.insert("location".to_string(), "${AZURE_LOCATION}".to_string());
+    let mut secret_variables = Vec::new();
+    for service in services {
+        let mut config = service.config.clone();
+        config
+            .tags
+            .get_or_insert_with(HashMap::new)
+            .insert("azd-service-name".to_string(), config.name.to_owned());
+        write_file(
+            &infra_directory
+                .join("app")
+                .join(format!("{}.bicep", &config.name)),
+            &get_containerapps_bicep_module(&config, api_version, true)?,
+        )?;
+        for (_, secret_parameter) in get_secret_parameters(&config) {
+            if let Some(module_parameter) = secret_parameter {
+                let variable = get_environment_variable_name(&config.name, &module_parameter);
+                parameter_values.insert(
+                    get_secret_parameter_name(&config.name, &module_parameter),
+                    format!("${{{}}}", &variable),
+                );
+                secret_variables.push(variable);
+            }
+        }
+    }
+    write_file(
+        &infra_directory.join("main.bicep"),
+        &get_azd_main(services, location),
+    )?;
+    write_arm_parameters_file(
+        &infra_directory.join("main.parameters.json"),
+        &parameter_values,
+    )?;
+
+    write_azd_environment(project_directory, environment_name, location)?;
+    Ok(secret_variables)
+}
+
+fn get_project_service(service: &AzdService) -> AzdProjectService {
+    match service.project.as_ref() {
+        Some(project) => AzdProjectService {
+            project: Some(project.to_owned()),
+            image: None,
+            host: "containerapp",
+            docker: service.dockerfile.as_ref().map(|path| AzdDocker {
+                path: path.to_owned(),
+            }),
+        },
+        None => AzdProjectService {
+            project: None,
+            image: service
+                .config
+                .properties
+                .template
+                .containers
+                .first()
+                .map(|c| c.image.to_owned()),
+            host: "containerapp",
+            docker: None,
+        },
+    }
+}
+
+fn get_azd_main(services: &[AzdService], location: &str) -> String {
+    let mut parameters = String::new();
+    let mut module_definitions = String::new();
+    let mut outputs = String::new();
+    for service in services {
+        let config = service.config;
+        // Prefixed so a service can't take the name of rg, logAnalytics or
+        // containerAppEnv.
+        let symbol = get_parameter_name(&format!("app-{}", &config.name));
+        module_definitions.push_str(&format!(
+            "\nmodule {} {} = {{\n  scope: rg\n  name: {}\n  params: {{\n    location: location\n    environmentId: containerAppEnv.outputs.id\n",
+            &symbol,
+            get_bicep_string(&format!("app/{}.bicep", &config.name)),
+            get_bicep_string(&config.name)
+        ));
+        for (_, secret_parameter) in get_secret_parameters(config) {
+            if let Some(module_parameter) = secret_parameter {
+                let parameter_name = get_secret_parameter_name(&config.name, &module_parameter);
+                parameters.push_str(&format!("\n@secure()\nparam {} string\n", &parameter_name));
+                module_definitions
+                    .push_str(&format!("    {}: {}\n", &module_parameter, &parameter_name));
+            }
+        }
+        module_definitions.push_str("  }\n}\n");
+        if config.properties.configuration.ingress.is_some() {
+            outputs.push_str(&format!(
+                "output SERVICE_{}_URI string = 'https://${{{}.outputs.fqdn}}'\n",
+                get_environment_variable_name(&config.name, ""),
+                &symbol
+            ));
+        }
+    }
+    let registry = services
+        .iter()
+        .flat_map(|s| s.config.properties.configuration.registries.iter())
+        .next();
+    if let Some(registry) = registry {
+        outputs.push_str(&format!(
+            "output AZURE_CONTAINER_REGISTRY_ENDPOINT string = {}\n",
+            get_bicep_string(&registry.server)
+        ));
+    }
+
+    let mut output_content = String::new();
+    output_content.push_str("targetScope = 'subscription'\n\n");
+    output_content.push_str("@minLength(1)\n@maxLength(64)\n@description('Name of the azd environment, used to name the resources.')\nparam environmentName string\n\n");
+    output_content.push_str("@minLength(1)\n@description('Location of Azure Resources')\n");
+    output_content.push_str(&format!(
+        "param location string = {}\n",
+        get_bicep_string(location)
+    ));
+    output_content.push_str(&parameters);
+    output_content.push_str(
+        r#"
+var tags = {
+  'azd-env-name': environmentName
+}
+
+resource rg 'Microsoft.Resources/resourceGroups@2021-04-01' = {
+  name: 'rg-${environmentName}'
+  location: location
+  tags: tags
+}
+
+module logAnalytics 'modules/createLogAnalytics.bicep' = {
+  scope: rg
+  name: 'logAnalyticsWorkspace'
+  params: {
+    name: 'log-${environmentName}'
+    location: location
+  }
+}
+
+module containerAppEnv 'modules/createContainerAppEnv.bicep' = {
+  scope: rg
+  name: 'containerAppEnv'
+  params: {
+    name: 'cae-${environmentName}'
+    location: location
+    workspaceClientId: logAnalytics.outputs.clientId
+    workspaceClientSecret: logAnalytics.outputs.clientSecret
+  }
+}
+"#,
+    );
+    output_content.push_str(&module_definitions);
+    output_content.push_str("\noutput AZURE_LOCATION string = location\n");
+    output_content.push_str("output AZURE_RESOURCE_GROUP string = rg.name\n");
+    output_content.push_str(
+        "output AZURE_CONTAINER_APPS_ENVIRONMENT_ID string = containerAppEnv.outputs.id\n",
+    );
+    output_content.push_str(&outputs);
+    output_content
+}
+
+/// Creates `.azure/<environment>/.env` and makes it the default environment,
+/// leaving an existing environment alone so azd's own values are kept.
+fn write_azd_environment(
+    project_directory: &Path,
+    environment_name: &str,
+    location: &str,
+) -> Result<()> {
+    let azure_directory = project_directory.join(".azure");
+    let environment_directory = azure_directory.join(environment_name);
+    if environment_directory.exists() {
+        debug!(
+            "The azd environment {} already exists, leaving it as is.",
+            environment_name
+        );
+        return Ok(());
+    }
+    create_dir_all(&environment_directory)?;
+    write_file(
+        &environment_directory.join(".env"),
+        &format!(
+            "AZURE_ENV_NAME=\"{}\"\nAZURE_LOCATION=\"{}\"\n",
+            environment_name, location
+        ),
+    )?;
+    let config = AzdConfig {
+        version: 1,
+        default_environment: environment_name.to_owned(),
+    };
+    write_file(
+        &azure_directory.join("config.json"),
+        &serde_json::to_string_pretty(&config)?,
+    )
+}
+
+fn write_file(file_path: &Path, content: &str) -> Result<()> {
+    let mut file = File::create(file_path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::containerapps::{Container, IngressConfiguration, SecretsConfiguration};
+    use std::fs::{read_to_string, remove_dir_all};
+
+    #[test]
+    fn azd_project_wires_services_and_secrets() {
+        let mut web = ContainerAppConfig {
+            name: "web".to_string(),
+            location: "eastus".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        web.properties.template.containers.push(Container {
+            name: "web".to_string(),
+            image: "web:latest".to_string(),
+            ..Container::default()
+        });
+        web.properties
+            .configuration
+            .secrets
+            .push(SecretsConfiguration {
+                name: "db-password".to_string(),
+                value: Some("hunter2".to_string()),
+                ..SecretsConfiguration::default()
+            });
+        let mut cache = ContainerAppConfig {
+            name: "cache".to_string(),
+            location: "eastus".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        cache.properties.template.containers.push(Container {
+            name: "cache".to_string(),
+            image: "redis".to_string(),
+            ..Container::default()
+        });
+        cache.properties.configuration.ingress = Some(IngressConfiguration::default());
+        let services = vec![
+            AzdService {
+                config: &web,
+                project: Some("./web".to_string()),
+                dockerfile: Some("Dockerfile.prod".to_string()),
+            },
+            AzdService {
+                config: &cache,
+                project: None,
+                dockerfile: None,
+            },
+        ];
+
+        let directory =
+            std::env::temp_dir().join(format!("azd-project-test-{}", uuid::Uuid::new_v4()));
+        let variables = write_azd_project(
+            &directory,
+            "shop",
+            "shop-dev",
+            "eastus",
+            &services,
+            ApiVersion::default(),
+        )
+        .unwrap();
+        let azure_yaml = read_to_string(directory.join("azure.yaml")).unwrap();
+        let main = read_to_string(directory.join("infra/main.bicep")).unwrap();
+        let parameters = read_to_string(directory.join("infra/main.parameters.json")).unwrap();
+        let web_module = read_to_string(directory.join("infra/app/web.bicep")).unwrap();
+        let environment = read_to_string(directory.join(".azure/shop-dev/.env")).unwrap();
+        remove_dir_all(&directory).unwrap();
+
+        assert_eq!(variables, vec!["WEB_DB_PASSWORD".to_string()]);
+        assert!(azure_yaml.contains("project: "));
+        assert!(azure_yaml.contains("path: Dockerfile.prod"));
+        assert!(azure_yaml.contains("image: redis"));
+        assert!(main.contains("module appWeb 'app/web.bicep'"));
+        assert!(main.contains("'https://${appCache.outputs.fqdn}'"));
+        assert!(main.contains("dbPassword: webDbPassword"));
+        assert!(main.contains("output SERVICE_CACHE_URI string"));
+        assert!(parameters.contains("${WEB_DB_PASSWORD}"));
+        assert!(parameters.contains("${AZURE_ENV_NAME}"));
+        assert!(web_module.contains("  environmentId: environmentId"));
+        assert!(web_module.contains("azd-service-name"));
+        assert!(!web_module.contains("hunter2"));
+        assert!(environment.contains("AZURE_ENV_NAME=\"shop-dev\""));
+    }
+}
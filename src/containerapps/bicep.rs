@@ -0,0 +1,272 @@
+use super::{get_parameter_name, get_secret_parameter_name, ApiVersion, ContainerAppConfig};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+/// Writes a Bicep module that deploys one ContainerApp.  Secret values become
+/// `@secure()` parameters so they never land in the generated file.
+pub fn write_containerapps_bicep_module(
+    file_path: &Path,
+    config: &ContainerAppConfig,
+    api_version: ApiVersion,
+) -> Result<()> {
+    let output_content = get_containerapps_bicep_module(config, api_version, false)?;
+    let mut file = File::create(file_path)?;
+    file.write_all(output_content.into_bytes().as_ref())?;
+    Ok(())
+}
+
+/// Renders the Bicep module for a ContainerApp.  With `environment_parameter`
+/// the environment id comes from an `environmentId` parameter instead of the
+/// configuration, for templates that create the environment themselves.
+pub(super) fn get_containerapps_bicep_module(
+    config: &ContainerAppConfig,
+    api_version: ApiVersion,
+    environment_parameter: bool,
+) -> Result<String> {
+    let mut expressions = BTreeMap::new();
+    expressions.insert("/location".to_string(), "location".to_string());
+
+    let mut output_content = String::new();
+    output_content.push_str("@description('Location of the ContainerApp.')\n");
+    output_content.push_str(&format!(
+        "param location string = {}\n",
+        get_bicep_string(&config.location)
+    ));
+    if environment_parameter {
+        output_content.push_str(
+            "\n@description('Resource ID for the ContainerApps environment.')\nparam environmentId string\n",
+        );
+        expressions.insert(
+            format!("/properties/{}", api_version.environment_id_property()),
+            "environmentId".to_string(),
+        );
+    }
+    for (index, secret_parameter) in get_secret_parameters(config) {
+        if let Some(parameter_name) = secret_parameter {
+            output_content.push_str(&format!("\n@secure()\nparam {} string\n", &parameter_name));
+            expressions.insert(
+                format!("/properties/configuration/secrets/{}/value", index),
+                parameter_name,
+            );
+        }
+    }
+
+    let mut resource = serde_json::to_value(api_version.to_container_app_value(config)?)?;
+    if let Value::Object(map) = &mut resource {
+        map.remove("apiVersion");
+        map.remove("resourceGroup");
+        map.remove("type");
+    }
+    output_content.push_str(&format!(
+        "\nresource containerApp '{}@{}' = ",
+        api_version.container_apps_type(),
+        api_version
+    ));
+    render_bicep_value(&resource, "", &expressions, 0, &mut output_content);
+    if config.properties.configuration.ingress.is_some() {
+        output_content.push_str(
+            "\n\noutput fqdn string = containerApp.properties.configuration.ingress.fqdn\n",
+        );
+    } else {
+        output_content.push('\n');
+    }
+    Ok(output_content)
+}
+
+/// Writes the Bicep file that composes the per service modules, passing the
+/// secure parameters through and collecting each app's FQDN.  Module paths are
+/// relative to the main file.
+pub fn write_containerapps_bicep_main(
+    file_path: &Path,
+    modules: &[(&Path, &ContainerAppConfig)],
+) -> Result<()> {
+    let location = modules
+        .first()
+        .map(|(_, config)| config.location.as_str())
+        .unwrap_or_default();
+
+    let mut parameters = String::new();
+    let mut module_definitions = String::new();
+    let mut outputs = String::new();
+    for (module_path, config) in modules {
+        let symbol = get_parameter_name(&config.name);
+        let module_file = module_path.display().to_string().replace('\\', "/");
+
+        module_definitions.push_str(&format!(
+            "\nmodule {} {} = {{\n  name: {}\n  params: {{\n    location: location\n",
+            &symbol,
+            get_bicep_string(&module_file),
+            get_bicep_string(&config.name)
+        ));
+        for (_, secret_parameter) in get_secret_parameters(config) {
+            if let Some(module_parameter) = secret_parameter {
+                let parameter_name = get_secret_parameter_name(&config.name, &module_parameter);
+                parameters.push_str(&format!("\n@secure()\nparam {} string\n", &parameter_name));
+                module_definitions
+                    .push_str(&format!("    {}: {}\n", &module_parameter, &parameter_name));
+            }
+        }
+        module_definitions.push_str("  }\n}\n");
+        if config.properties.configuration.ingress.is_some() {
+            outputs.push_str(&format!(
+                "output {}Fqdn string = {}.outputs.fqdn\n",
+                &symbol, &symbol
+            ));
+        }
+    }
+
+    let mut output_content = String::new();
+    output_content.push_str("@description('Location of the ContainerApps.')\n");
+    output_content.push_str(&format!(
+        "param location string = {}\n",
+        get_bicep_string(location)
+    ));
+    output_content.push_str(&parameters);
+    output_content.push_str(&module_definitions);
+    output_content.push('\n');
+    output_content.push_str(&outputs);
+
+    let mut file = File::create(file_path)?;
+    file.write_all(output_content.into_bytes().as_ref())?;
+    Ok(())
+}
+
+pub(super) fn get_secret_parameters(config: &ContainerAppConfig) -> Vec<(usize, Option<String>)> {
+    config
+        .properties
+        .configuration
+        .secrets
+        .iter()
+        .enumerate()
+        .map(|(index, secret)| {
+            (
+                index,
+                secret
+                    .value
+                    .as_ref()
+                    .map(|_| get_parameter_name(&secret.name)),
+            )
+        })
+        .collect()
+}
+
+fn render_bicep_value(
+    value: &Value,
+    pointer: &str,
+    expressions: &BTreeMap<String, String>,
+    indent: usize,
+    output: &mut String,
+) {
+    if let Some(expression) = expressions.get(pointer) {
+        output.push_str(expression);
+        return;
+    }
+    let padding = "  ".repeat(indent + 1);
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(b) => output.push_str(&b.to_string()),
+        Value::Number(n) => output.push_str(&n.to_string()),
+        Value::String(s) => output.push_str(&get_bicep_string(s)),
+        Value::Array(items) if items.is_empty() => output.push_str("[]"),
+        Value::Array(items) => {
+            output.push_str("[\n");
+            for (index, item) in items.iter().enumerate() {
+                output.push_str(&padding);
+                let item_pointer = format!("{}/{}", pointer, index);
+                render_bicep_value(item, &item_pointer, expressions, indent + 1, output);
+                output.push('\n');
+            }
+            output.push_str(&"  ".repeat(indent));
+            output.push(']');
+        }
+        Value::Object(map) if map.is_empty() => output.push_str("{}"),
+        Value::Object(map) => {
+            output.push_str("{\n");
+            for (key, item) in map {
+                output.push_str(&padding);
+                output.push_str(&get_bicep_key(key));
+                output.push_str(": ");
+                let item_pointer = format!("{}/{}", pointer, key);
+                render_bicep_value(item, &item_pointer, expressions, indent + 1, output);
+                output.push('\n');
+            }
+            output.push_str(&"  ".repeat(indent));
+            output.push('}');
+        }
+    }
+}
+
+fn get_bicep_key(key: &str) -> String {
+    let is_identifier = key
+        .chars()
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        key.to_string()
+    } else {
+        get_bicep_string(key)
+    }
+}
+
+pub(super) fn get_bicep_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace("${", "\\${")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("'{}'", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::containerapps::SecretsConfiguration;
+    use std::fs::{read_to_string, remove_file};
+
+    #[test]
+    fn bicep_module_uses_secure_parameters_for_secrets() {
+        let mut config = ContainerAppConfig {
+            name: "web".to_string(),
+            location: "eastus".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        config
+            .properties
+            .configuration
+            .secrets
+            .push(SecretsConfiguration {
+                name: "db-password".to_string(),
+                value: Some("hunter2".to_string()),
+                ..SecretsConfiguration::default()
+            });
+
+        let path =
+            std::env::temp_dir().join(format!("bicep-module-test-{}.bicep", uuid::Uuid::new_v4()));
+        write_containerapps_bicep_module(&path, &config, ApiVersion::default()).unwrap();
+        let content = read_to_string(&path).unwrap();
+        remove_file(&path).unwrap();
+
+        assert!(content.contains("@secure()\nparam dbPassword string"));
+        assert!(content.contains("value: dbPassword"));
+        assert!(content.contains("location: location"));
+        assert!(!content.contains("hunter2"));
+    }
+
+    #[test]
+    fn bicep_strings_are_escaped() {
+        assert_eq!(get_bicep_string("it's ${x}"), "'it\\'s \\${x}'");
+        assert_eq!(get_bicep_key("targetPort"), "targetPort");
+        assert_eq!(
+            get_bicep_key("app.kubernetes.io/name"),
+            "'app.kubernetes.io/name'"
+        );
+    }
+}
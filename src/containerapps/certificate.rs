@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use super::get_parameter_name;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Certificate {
+    #[serde(rename = "apiVersion", skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    pub location: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    pub properties: CertificateProperties,
+}
+impl Default for Certificate {
+    fn default() -> Certificate {
+        Certificate {
+            api_version: None,
+            location: String::default(),
+            name: String::default(),
+            resource_type: "Microsoft.Web/kubeEnvironments/certificates".to_string(),
+            properties: CertificateProperties::default(),
+        }
+    }
+}
+impl Certificate {
+    /// Name of the secure template parameter that carries the PFX password.
+    pub fn password_parameter_name(&self) -> String {
+        let certificate_name = self.name.rsplit('/').next().unwrap_or_default();
+        format!("{}Password", get_parameter_name(certificate_name))
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct CertificateProperties {
+    pub value: String,
+    pub password: String,
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClientCertificateMode {
+    #[serde(rename = "ignore")]
+    Ignore,
+    #[serde(rename = "accept")]
+    Accept,
+    #[serde(rename = "require")]
+    Require,
+}
@@ -3,6 +3,14 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct ContainerRegistry {
     pub server: String,
-    pub username: String,
-    pub password_secret_ref: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(
+        rename = "passwordSecretRef",
+        alias = "password_secret_ref",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub password_secret_ref: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
 }
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct CorsPolicy {
+    #[serde(rename = "allowedOrigins", default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(
+        rename = "allowedMethods",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub allowed_methods: Vec<String>,
+    #[serde(
+        rename = "allowedHeaders",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub allowed_headers: Vec<String>,
+    #[serde(
+        rename = "exposeHeaders",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub expose_headers: Vec<String>,
+    #[serde(rename = "maxAge", skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u32>,
+    #[serde(rename = "allowCredentials", default)]
+    pub allow_credentials: bool,
+}
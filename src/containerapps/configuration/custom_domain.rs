@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BindingType {
+    Disabled,
+    SniEnabled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CustomDomain {
+    pub name: String,
+    #[serde(rename = "bindingType")]
+    pub binding_type: BindingType,
+    #[serde(rename = "certificateId", skip_serializing_if = "Option::is_none")]
+    pub certificate_id: Option<String>,
+}
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use super::{TrafficConfiguration, Transport};
+use super::{
+    ClientCertificateMode, CorsPolicy, CustomDomain, IpSecurityRestriction, StickySessions,
+    TrafficConfiguration, Transport,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct IngressConfiguration {
@@ -14,6 +17,27 @@ pub struct IngressConfiguration {
     pub traffic: Vec<TrafficConfiguration>,
     #[serde(default)]
     pub transport: Transport,
+    #[serde(
+        rename = "customDomains",
+        default = "Vec::new",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub custom_domains: Vec<CustomDomain>,
+    #[serde(
+        rename = "ipSecurityRestrictions",
+        default = "Vec::new",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub ip_security_restrictions: Vec<IpSecurityRestriction>,
+    #[serde(rename = "corsPolicy", skip_serializing_if = "Option::is_none")]
+    pub cors_policy: Option<CorsPolicy>,
+    #[serde(
+        rename = "clientCertificateMode",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub client_certificate_mode: Option<ClientCertificateMode>,
+    #[serde(rename = "stickySessions", skip_serializing_if = "Option::is_none")]
+    pub sticky_sessions: Option<StickySessions>,
 }
 impl IngressConfiguration {
     pub fn new() -> Self {
@@ -28,6 +52,11 @@ impl Default for IngressConfiguration {
             target_port: Some(80),
             traffic: Vec::new(),
             transport: Transport::default(),
+            custom_domains: Vec::new(),
+            ip_security_restrictions: Vec::new(),
+            cors_policy: None,
+            client_certificate_mode: None,
+            sticky_sessions: None,
         }
     }
 }
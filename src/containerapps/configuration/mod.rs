@@ -1,16 +1,26 @@
 use serde::{Deserialize, Serialize};
 
+mod client_certificate_mode;
 mod container_registry;
+mod cors_policy;
+mod custom_domain;
 mod ingress;
+mod ip_security_restriction;
 mod revision_mode;
 mod secrets;
+mod sticky_sessions;
 mod traffic_configuration;
 mod transport;
 
+pub use self::client_certificate_mode::ClientCertificateMode;
 pub use self::container_registry::ContainerRegistry;
+pub use self::cors_policy::CorsPolicy;
+pub use self::custom_domain::{BindingType, CustomDomain};
 pub use self::ingress::IngressConfiguration;
+pub use self::ip_security_restriction::{IpSecurityRestriction, IpSecurityRestrictionAction};
 pub use self::revision_mode::RevisionMode;
 pub use self::secrets::SecretsConfiguration;
+pub use self::sticky_sessions::StickySessions;
 pub use self::traffic_configuration::TrafficConfiguration;
 pub use self::transport::Transport;
 
@@ -20,7 +30,8 @@ pub struct Configuration {
     pub active_revisions_mode: RevisionMode,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub secrets: Vec<SecretsConfiguration>,
-    pub ingress: IngressConfiguration,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ingress: Option<IngressConfiguration>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub registries: Vec<ContainerRegistry>,
 }
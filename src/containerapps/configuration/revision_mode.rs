@@ -1,14 +1,48 @@
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RevisionMode {
-    #[serde(rename = "multiple")]
+    #[serde(rename = "multiple", alias = "Multiple")]
     Multiple,
-    #[serde(rename = "single")]
+    #[default]
+    #[serde(rename = "single", alias = "Single")]
     Single,
 }
-impl Default for RevisionMode {
-    fn default() -> Self {
-        RevisionMode::Single
+impl fmt::Display for RevisionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl FromStr for RevisionMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "multiple" => Ok(RevisionMode::Multiple),
+            "single" => Ok(RevisionMode::Single),
+            _ => Err(anyhow!(
+                "The revision mode is single or multiple, found {}",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `--revision-mode` offers the values of the clap enum, so each has to parse into
+    /// the variant of the same name.
+    #[test]
+    fn every_revision_mode_value_parses() {
+        for value in crate::cli::RevisionMode::variants() {
+            let parsed = RevisionMode::from_str(value).unwrap();
+            assert_eq!(parsed.to_string().to_lowercase(), value.to_lowercase());
+        }
+        assert!(RevisionMode::from_str("several").is_err());
     }
 }
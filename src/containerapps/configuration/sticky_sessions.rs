@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Affinity {
+    #[serde(rename = "none")]
+    None,
+    #[serde(rename = "sticky")]
+    Sticky,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StickySessions {
+    pub affinity: Affinity,
+}
@@ -1,9 +1,48 @@
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TrafficConfiguration {
+    #[serde(rename = "revisionName", skip_serializing_if = "Option::is_none")]
+    pub revision_name: Option<String>,
     #[serde(rename = "latestRevision", skip_serializing_if = "Option::is_none")]
     pub latest_revision: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub weight: Option<u32>,
 }
+impl FromStr for TrafficConfiguration {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (revision, weight) = match s.split_once('=') {
+            Some((r, w)) => (r.trim(), w.trim()),
+            None => {
+                return Err(anyhow!(
+                    "Traffic weights are set as <revision>=<weight>, found {}",
+                    s
+                ))
+            }
+        };
+        let weight = u32::from_str(weight).map_err(|_| {
+            anyhow!(
+                "Traffic weight for {} must be a number, found {}",
+                revision,
+                weight
+            )
+        })?;
+        let result = match revision {
+            "latest" => TrafficConfiguration {
+                revision_name: None,
+                latest_revision: Some(true),
+                weight: Some(weight),
+            },
+            _ => TrafficConfiguration {
+                revision_name: Some(revision.to_owned()),
+                latest_revision: None,
+                weight: Some(weight),
+            },
+        };
+        Ok(result)
+    }
+}
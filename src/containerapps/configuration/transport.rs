@@ -1,21 +1,18 @@
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Transport {
-    #[serde(rename = "auto")]
+    #[default]
+    #[serde(rename = "auto", alias = "Auto")]
     Auto,
-    #[serde(rename = "http")]
+    #[serde(rename = "http", alias = "Http")]
     Http,
-    #[serde(rename = "http2")]
+    #[serde(rename = "http2", alias = "Http2")]
     Http2,
 }
-impl Default for Transport {
-    fn default() -> Self {
-        Transport::Auto
-    }
-}
 impl fmt::Display for Transport {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -25,12 +22,27 @@ impl FromStr for Transport {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let result = match s {
-            "Auto" => Transport::Auto,
-            "Http" => Transport::Http,
-            "Http2" => Transport::Http2,
-            _ => Transport::default(),
-        };
-        Ok(result)
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Transport::Auto),
+            "http" => Ok(Transport::Http),
+            "http2" => Ok(Transport::Http2),
+            _ => Err(anyhow!("The transport is Auto, Http or Http2, found {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `--transport` offers the values of the clap enum, so each has to parse into
+    /// the variant of the same name.
+    #[test]
+    fn every_transport_value_parses() {
+        for value in crate::cli::Transport::variants() {
+            let parsed = Transport::from_str(value).unwrap();
+            assert_eq!(parsed.to_string().to_lowercase(), value.to_lowercase());
+        }
+        assert!(Transport::from_str("tcp").is_err());
     }
 }
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use super::SecretsConfiguration;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DaprComponent {
+    #[serde(rename = "apiVersion", skip_serializing_if = "Option::is_none")]
+    pub api_version: Option<String>,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    pub properties: DaprComponentProperties,
+}
+impl Default for DaprComponent {
+    fn default() -> DaprComponent {
+        DaprComponent {
+            api_version: None,
+            name: String::default(),
+            resource_type: "Microsoft.Web/kubeEnvironments/daprComponents".to_string(),
+            properties: DaprComponentProperties::default(),
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct DaprComponentProperties {
+    #[serde(rename = "componentType")]
+    pub component_type: String,
+    pub version: String,
+    #[serde(rename = "ignoreErrors", default)]
+    pub ignore_errors: bool,
+    #[serde(rename = "initTimeout", skip_serializing_if = "Option::is_none")]
+    pub init_timeout: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<SecretsConfiguration>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metadata: Vec<DaprMetadata>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct DaprMetadata {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(rename = "secretRef", skip_serializing_if = "Option::is_none")]
+    pub secret_ref: Option<String>,
+}
@@ -0,0 +1,192 @@
+use super::ContainerAppConfig;
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// ContainerApps runs up to ten replicas when no maximum is set.
+const DEFAULT_MAX_REPLICAS: u32 = 10;
+
+/// One field that differs between the deployed app and the generated
+/// configuration.  A missing side means the field only exists on the other.
+#[derive(Debug, PartialEq)]
+pub struct Difference {
+    pub path: String,
+    pub deployed: Option<Value>,
+    pub generated: Option<Value>,
+}
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.deployed, &self.generated) {
+            (Some(deployed), Some(generated)) => {
+                write!(f, "~ {}: {} -> {}", self.path, deployed, generated)
+            }
+            (None, Some(generated)) => write!(f, "+ {}: {}", self.path, generated),
+            (Some(deployed), None) => write!(f, "- {}: {}", self.path, deployed),
+            (None, None) => write!(f, "  {}", self.path),
+        }
+    }
+}
+
+/// Compares the parts of two ContainerApps that a Compose file controls:
+/// container images and environment, secret names, ingress and scale.
+/// Secret values are never compared, Azure doesn't return them.
+pub fn diff_container_apps(
+    deployed: &ContainerAppConfig,
+    generated: &ContainerAppConfig,
+) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_values(
+        "",
+        &get_comparable_value(deployed),
+        &get_comparable_value(generated),
+        &mut differences,
+    );
+    differences
+}
+
+/// The fields `diff_container_apps` compares, keyed so the order of
+/// containers, variables and secrets doesn't matter.  Fields are compared in
+/// alphabetical order.
+fn get_comparable_value(config: &ContainerAppConfig) -> Value {
+    let properties = &config.properties;
+    let mut containers = Map::new();
+    for container in properties.template.containers.iter() {
+        let mut env = Map::new();
+        for variable in container.env.iter() {
+            let value = match (&variable.value, &variable.secret_ref) {
+                (_, Some(secret)) => format!("secretRef:{}", secret),
+                (Some(value), None) => value.to_owned(),
+                (None, None) => String::new(),
+            };
+            env.insert(variable.name.to_owned(), Value::from(value));
+        }
+        containers.insert(
+            container.name.to_owned(),
+            json!({ "image": container.image, "env": env }),
+        );
+    }
+
+    let mut secrets: Vec<&str> = properties
+        .configuration
+        .secrets
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    secrets.sort_unstable();
+
+    let ingress = properties.configuration.ingress.as_ref().map(|ingress| {
+        json!({
+            "external": ingress.external,
+            "targetPort": ingress.target_port,
+            "transport": ingress.transport,
+            "allowInsecure": ingress.allow_insecure,
+        })
+    });
+    let scale = &properties.template.scale;
+    json!({
+        "containers": containers,
+        "secrets": secrets,
+        "ingress": ingress,
+        "scale": {
+            "minReplicas": scale.min_replicas,
+            "maxReplicas": scale.max_replicas.unwrap_or(DEFAULT_MAX_REPLICAS),
+        },
+    })
+}
+
+fn diff_values(path: &str, deployed: &Value, generated: &Value, differences: &mut Vec<Difference>) {
+    match (deployed, generated) {
+        (Value::Object(deployed), Value::Object(generated)) => {
+            for (key, deployed_field) in deployed.iter() {
+                let field_path = get_field_path(path, key);
+                match generated.get(key) {
+                    Some(generated_field) => {
+                        diff_values(&field_path, deployed_field, generated_field, differences)
+                    }
+                    None => differences.push(Difference {
+                        path: field_path,
+                        deployed: Some(deployed_field.clone()),
+                        generated: None,
+                    }),
+                }
+            }
+            for (key, generated_field) in generated.iter() {
+                if !deployed.contains_key(key) {
+                    differences.push(Difference {
+                        path: get_field_path(path, key),
+                        deployed: None,
+                        generated: Some(generated_field.clone()),
+                    });
+                }
+            }
+        }
+        (deployed, generated) if deployed != generated => differences.push(Difference {
+            path: path.to_owned(),
+            deployed: Some(deployed.clone()),
+            generated: Some(generated.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn get_field_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTAINER_APP: &str = r#"
+location: eastus
+name: web
+type: Microsoft.App/containerApps
+properties:
+  kubeEnvironmentId: env
+  configuration:
+    activeRevisionsMode: single
+    secrets:
+      - name: db-password
+        value: hunter2
+    ingress:
+      external: true
+      targetPort: 80
+  template:
+    containers:
+      - name: web
+        image: contoso/web:1.0
+        env:
+          - name: MODE
+            value: production
+          - name: DB_PASSWORD
+            secretRef: db-password
+    scale:
+      minReplicas: 1
+"#;
+
+    #[test]
+    fn only_changed_fields_are_reported() {
+        let deployed: ContainerAppConfig = serde_yaml::from_str(CONTAINER_APP).unwrap();
+        let mut generated = deployed.clone();
+        generated.properties.configuration.secrets[0].value = Some("changed".to_owned());
+        assert!(diff_container_apps(&deployed, &generated).is_empty());
+
+        generated.properties.template.containers[0].image = "contoso/web:1.1".to_owned();
+        generated.properties.template.containers[0].env.remove(0);
+        generated.properties.template.scale.max_replicas = Some(DEFAULT_MAX_REPLICAS);
+        let differences = diff_container_apps(&deployed, &generated);
+
+        assert_eq!(differences.len(), 2);
+        assert_eq!(
+            differences[0].to_string(),
+            "- containers.web.env.MODE: \"production\""
+        );
+        assert_eq!(
+            differences[1].to_string(),
+            "~ containers.web.image: \"contoso/web:1.0\" -> \"contoso/web:1.1\""
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use super::{ContainerAppConfig, IdentityType};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Map, Value};
+
+/// Settings a deployed app can have that the model doesn't hold, so they are
+/// lost on export.  Paths are relative to `properties`.
+const UNSUPPORTED_PROPERTIES: [&[&str]; 10] = [
+    &["workloadProfileName"],
+    &["configuration", "dapr"],
+    &["configuration", "maxInactiveRevisions"],
+    &["configuration", "service"],
+    &["configuration", "ingress", "exposedPort"],
+    &["configuration", "ingress", "additionalPortMappings"],
+    &["template", "initContainers"],
+    &["template", "volumes"],
+    &["template", "serviceBinds"],
+    &["template", "scale", "rules"],
+];
+
+/// A deployed ContainerApp read back into the model, along with anything
+/// that could not be carried over.
+#[derive(Debug)]
+pub struct Export {
+    pub config: ContainerAppConfig,
+    pub untranslated: Vec<String>,
+}
+
+/// Reads the output of `az containerapp show` into the model.  Runtime-only
+/// properties, like the provisioning state or the latest revision, aren't
+/// part of the model so they are dropped when the app is written back out.
+pub fn export_container_app_value(value: Value) -> Result<Export> {
+    let mut value = remove_nulls(value);
+    let mut untranslated = Vec::new();
+
+    let properties = value
+        .get_mut("properties")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| anyhow!("The exported resource has no properties"))?;
+    for path in UNSUPPORTED_PROPERTIES.iter() {
+        if get_path(properties, path).is_some() {
+            untranslated.push(format!(
+                "{} is not supported and was not exported",
+                path.join(".")
+            ));
+        }
+    }
+    // Newer API versions return the environment under both names.
+    if properties.contains_key("managedEnvironmentId") {
+        properties.remove("environmentId");
+    }
+
+    let configuration = properties
+        .entry("configuration")
+        .or_insert_with(|| json!({}));
+    if !configuration.is_object() {
+        return Err(anyhow!("The exported configuration is not an object"));
+    }
+
+    let template = properties.entry("template").or_insert_with(|| json!({}));
+    if !template.is_object() {
+        return Err(anyhow!("The exported template is not an object"));
+    }
+    if !template["scale"].is_object() {
+        template["scale"] = json!({});
+    }
+    // A missing minimum means the app scales to zero.
+    if template["scale"].get("minReplicas").is_none() {
+        template["scale"]["minReplicas"] = json!(0);
+    }
+    if let Some(containers) = template["containers"].as_array_mut() {
+        for container in containers.iter_mut() {
+            if container["probes"]
+                .as_array()
+                .is_some_and(|p| !p.is_empty())
+            {
+                untranslated.push(format!(
+                    "probes on container {} are not supported and were not exported",
+                    container["name"].as_str().unwrap_or_default()
+                ));
+            }
+            if container["volumeMounts"]
+                .as_array()
+                .is_some_and(|m| !m.is_empty())
+            {
+                untranslated.push(format!(
+                    "volume mounts on container {} are not supported and were not exported",
+                    container["name"].as_str().unwrap_or_default()
+                ));
+            }
+            // The API returns cpu as a number, the model as a string.
+            if let Some(cpu) = container["resources"]["cpu"].as_f64() {
+                container["resources"]["cpu"] = Value::from(cpu.to_string());
+            }
+        }
+    }
+
+    let mut config: ContainerAppConfig = serde_json::from_value(value)
+        .map_err(|e| anyhow!("Unable to read the exported ContainerApp - {}", e))?;
+    if config
+        .identity
+        .as_ref()
+        .is_some_and(|i| i.identity_type == IdentityType::None)
+    {
+        config.identity = None;
+    }
+    if config
+        .properties
+        .template
+        .revision_suffix
+        .as_ref()
+        .is_some_and(|s| s.is_empty())
+    {
+        config.properties.template.revision_suffix = None;
+    }
+    config.kind = None;
+    config.api_version = None;
+
+    let unset_secrets: Vec<&str> = config
+        .properties
+        .configuration
+        .secrets
+        .iter()
+        .filter(|s| s.value.is_none() && s.key_vault_url.is_none())
+        .map(|s| s.name.as_str())
+        .collect();
+    if !unset_secrets.is_empty() {
+        untranslated.push(format!(
+            "secret values are not returned by Azure, set {} before deploying",
+            unset_secrets.join(", ")
+        ));
+    }
+
+    Ok(Export {
+        config,
+        untranslated,
+    })
+}
+
+fn get_path<'a>(object: &'a Map<String, Value>, path: &[&str]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    let value = object.get(*first)?;
+    if rest.is_empty() {
+        Some(value)
+    } else {
+        get_path(value.as_object()?, rest)
+    }
+}
+
+/// The API returns unset optional properties as nulls, which the model
+/// doesn't accept for lists.
+fn remove_nulls(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .filter(|(_, field)| !field.is_null())
+                .map(|(key, field)| (key, remove_nulls(field)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(remove_nulls).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHOW_OUTPUT: &str = r#"{
+  "id": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/containerapps/web",
+  "identity": { "type": "None" },
+  "location": "East US",
+  "name": "web",
+  "resourceGroup": "rg",
+  "systemData": { "createdBy": "someone@example.com" },
+  "type": "Microsoft.App/containerApps",
+  "properties": {
+    "provisioningState": "Succeeded",
+    "runningStatus": "Running",
+    "managedEnvironmentId": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env",
+    "environmentId": "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env",
+    "latestRevisionName": "web--abc123",
+    "outboundIpAddresses": ["20.0.0.1"],
+    "workloadProfileName": null,
+    "configuration": {
+      "activeRevisionsMode": "Single",
+      "dapr": null,
+      "ingress": {
+        "external": true,
+        "fqdn": "web.example.eastus.azurecontainerapps.io",
+        "targetPort": 80,
+        "transport": "Auto",
+        "traffic": [{ "latestRevision": true, "weight": 100 }],
+        "customDomains": null
+      },
+      "registries": null,
+      "secrets": [{ "name": "db-password" }]
+    },
+    "template": {
+      "revisionSuffix": "",
+      "containers": [
+        {
+          "name": "web",
+          "image": "nginx",
+          "command": null,
+          "env": [{ "name": "DB_PASSWORD", "secretRef": "db-password" }],
+          "probes": [{ "type": "Liveness", "httpGet": { "path": "/", "port": 80 } }],
+          "volumeMounts": [{ "volumeName": "data", "mountPath": "/data" }],
+          "resources": { "cpu": 0.5, "memory": "1Gi", "ephemeralStorage": "2Gi" }
+        }
+      ],
+      "scale": { "minReplicas": null, "maxReplicas": 10, "rules": null }
+    }
+  }
+}"#;
+
+    #[test]
+    fn deployed_apps_are_read_into_the_model() {
+        let value: Value = serde_json::from_str(SHOW_OUTPUT).unwrap();
+        let export = export_container_app_value(value).unwrap();
+        let config = &export.config;
+
+        assert_eq!(config.name, "web");
+        assert_eq!(config.resource_group.as_deref(), Some("rg"));
+        assert!(config.identity.is_none());
+        assert!(config.properties.kube_environment_id.ends_with("/env"));
+        assert!(
+            config
+                .properties
+                .configuration
+                .ingress
+                .as_ref()
+                .unwrap()
+                .external
+        );
+        assert_eq!(config.properties.template.scale.min_replicas, 0);
+        assert!(config.properties.template.revision_suffix.is_none());
+        assert_eq!(
+            config.properties.template.containers[0]
+                .resources
+                .as_ref()
+                .unwrap()
+                .cpu
+                .as_deref(),
+            Some("0.5")
+        );
+        assert_eq!(export.untranslated.len(), 3);
+        assert!(export.untranslated[0].contains("probes"));
+        assert!(export.untranslated[1].contains("volume mounts on container web"));
+        assert!(export.untranslated[2].contains("db-password"));
+    }
+
+    #[test]
+    fn apps_without_ingress_are_exported_without_ingress() {
+        let mut value: Value = serde_json::from_str(SHOW_OUTPUT).unwrap();
+        value["properties"]["configuration"]["ingress"] = Value::Null;
+        let export = export_container_app_value(value).unwrap();
+
+        assert!(export.config.properties.configuration.ingress.is_none());
+        assert!(!export.untranslated.iter().any(|u| u.contains("ingress")));
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdentityType {
+    None,
+    SystemAssigned,
+    UserAssigned,
+    #[serde(rename = "SystemAssigned,UserAssigned")]
+    SystemAssignedUserAssigned,
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Identity {
+    #[serde(rename = "type")]
+    pub identity_type: IdentityType,
+    #[serde(
+        rename = "userAssignedIdentities",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub user_assigned_identities: BTreeMap<String, UserAssignedIdentity>,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct UserAssignedIdentity {}
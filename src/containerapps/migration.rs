@@ -0,0 +1,275 @@
+use super::ApiVersion;
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+
+const ENVIRONMENT_ID_PROPERTIES: [&str; 3] =
+    ["kubeEnvironmentId", "managedEnvironmentId", "environmentId"];
+
+/// A ContainerApp document upgraded to a newer API version, along with
+/// anything that could not be carried over.
+#[derive(Debug)]
+pub struct Migration {
+    pub source: ApiVersion,
+    pub value: Value,
+    pub untranslated: Vec<String>,
+}
+
+/// Works out which API version a ContainerApp document was written for, from
+/// its `apiVersion`, resource `type` or environment id property.
+pub fn detect_api_version(value: &Value) -> Option<ApiVersion> {
+    if let Some(version) = value.get("apiVersion").and_then(Value::as_str) {
+        if let Ok(api_version) = version.parse() {
+            return Some(api_version);
+        }
+    }
+    if let Some(resource_type) = value.get("type").and_then(Value::as_str) {
+        if resource_type.eq_ignore_ascii_case(ApiVersion::V2021_03_01.container_apps_type()) {
+            return Some(ApiVersion::V2021_03_01);
+        }
+    }
+    let properties = value.get("properties")?;
+    if properties.get("kubeEnvironmentId").is_some() {
+        Some(ApiVersion::V2021_03_01)
+    } else if properties.get("managedEnvironmentId").is_some() {
+        Some(ApiVersion::V2022_03_01)
+    } else if properties.get("environmentId").is_some() {
+        Some(ApiVersion::V2023_05_01)
+    } else {
+        None
+    }
+}
+
+/// Upgrades a ContainerApp document to `target`.  Works on the raw document
+/// rather than the model so unknown fields and manual edits survive, and
+/// field order is kept.
+pub fn migrate_container_app_value(value: Value, target: ApiVersion) -> Result<Migration> {
+    let source = detect_api_version(&value).ok_or_else(|| {
+        anyhow!("Unable to tell which ContainerApps API version the configuration was written for")
+    })?;
+    if source > target {
+        return Err(anyhow!(
+            "The configuration targets {}, which is newer than {}",
+            source,
+            target
+        ));
+    }
+
+    let mut untranslated = Vec::new();
+    let fields = match value {
+        Value::Mapping(fields) => fields,
+        _ => return Err(anyhow!("The configuration is not a ContainerApp resource")),
+    };
+    let mut migrated = Mapping::new();
+    for (key, field) in fields {
+        match key.as_str() {
+            Some("kind") if target != ApiVersion::V2021_03_01 => {
+                if field.as_str().is_some_and(|k| k != "containerapp") {
+                    untranslated.push(format!(
+                        "kind '{}' is not used by {} and was removed",
+                        field.as_str().unwrap_or_default(),
+                        target.container_apps_type()
+                    ));
+                }
+            }
+            Some("type") => {
+                migrated.insert(key, Value::from(target.container_apps_type()));
+            }
+            Some("apiVersion") => {
+                migrated.insert(key, Value::from(target.as_str()));
+            }
+            Some("properties") => {
+                let properties = migrate_properties(field, source, target, &mut untranslated);
+                migrated.insert(key, properties);
+            }
+            _ => {
+                migrated.insert(key, field);
+            }
+        }
+    }
+
+    Ok(Migration {
+        source,
+        value: Value::Mapping(migrated),
+        untranslated,
+    })
+}
+
+fn migrate_properties(
+    properties: Value,
+    source: ApiVersion,
+    target: ApiVersion,
+    untranslated: &mut Vec<String>,
+) -> Value {
+    let fields = match properties {
+        Value::Mapping(fields) => fields,
+        other => return other,
+    };
+    let mut migrated = Mapping::new();
+    let mut dapr = None;
+    for (key, field) in fields {
+        match key.as_str() {
+            Some(name) if ENVIRONMENT_ID_PROPERTIES.contains(&name) => {
+                let environment_id = migrate_environment_id(field, source, target, untranslated);
+                migrated.insert(
+                    Value::from(target.environment_id_property()),
+                    environment_id,
+                );
+            }
+            Some("template") if source == ApiVersion::V2021_03_01 => {
+                let (template, template_dapr) = split_template_dapr(field, untranslated);
+                dapr = template_dapr;
+                migrated.insert(key, template);
+            }
+            _ => {
+                migrated.insert(key, field);
+            }
+        }
+    }
+
+    // Dapr moved from the template to the configuration once ContainerApps
+    // became Microsoft.App, so a new revision is no longer needed to change it.
+    if let Some(dapr) = dapr {
+        let configuration = migrated
+            .entry(Value::from("configuration"))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Value::Mapping(configuration) = configuration {
+            configuration.insert(Value::from("dapr"), dapr);
+        }
+    }
+    Value::Mapping(migrated)
+}
+
+fn migrate_environment_id(
+    environment_id: Value,
+    source: ApiVersion,
+    target: ApiVersion,
+    untranslated: &mut Vec<String>,
+) -> Value {
+    if source.environment_type() == target.environment_type() {
+        return environment_id;
+    }
+    match environment_id.as_str() {
+        Some(id) if id.contains(source.environment_type()) => {
+            untranslated.push(format!(
+                "the environment {} must exist as a {} before deploying",
+                id,
+                target.environment_type()
+            ));
+            Value::from(id.replace(source.environment_type(), target.environment_type()))
+        }
+        _ => environment_id,
+    }
+}
+
+/// Pulls `dapr` out of a preview template.  Components were declared inline in
+/// the preview but are environment resources in `Microsoft.App`, so they can't
+/// be carried over.
+fn split_template_dapr(template: Value, untranslated: &mut Vec<String>) -> (Value, Option<Value>) {
+    let fields = match template {
+        Value::Mapping(fields) => fields,
+        other => return (other, None),
+    };
+    let mut migrated = Mapping::new();
+    let mut dapr = None;
+    for (key, field) in fields {
+        if key.as_str() == Some("dapr") {
+            dapr = Some(remove_dapr_components(field, untranslated));
+        } else {
+            migrated.insert(key, field);
+        }
+    }
+    (Value::Mapping(migrated), dapr)
+}
+
+fn remove_dapr_components(dapr: Value, untranslated: &mut Vec<String>) -> Value {
+    let fields = match dapr {
+        Value::Mapping(fields) => fields,
+        other => return other,
+    };
+    let mut migrated = Mapping::new();
+    for (key, field) in fields {
+        match (key.as_str(), field) {
+            (Some("components"), Value::Sequence(components)) => {
+                for component in components {
+                    let name = component
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or("unnamed");
+                    untranslated.push(format!(
+                        "Dapr component '{}' is now a Microsoft.App/managedEnvironments/daprComponents resource and was removed",
+                        name
+                    ));
+                }
+            }
+            (_, field) => {
+                migrated.insert(key, field);
+            }
+        }
+    }
+    Value::Mapping(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PREVIEW: &str = r#"
+kind: containerapp
+location: eastus
+name: web
+resourceGroup: rg
+type: Microsoft.Web/containerApps
+tags:
+  team: checkout
+properties:
+  kubeEnvironmentId: /subscriptions/sub/resourceGroups/rg/providers/Microsoft.Web/kubeEnvironments/env
+  configuration:
+    activeRevisionsMode: single
+  template:
+    containers:
+      - name: web
+        image: nginx
+    dapr:
+      enabled: true
+      appId: web
+      components:
+        - name: statestore
+          type: state.redis
+"#;
+
+    #[test]
+    fn preview_configurations_are_upgraded_in_place() {
+        let value: Value = serde_yaml::from_str(PREVIEW).unwrap();
+        let migration = migrate_container_app_value(value, ApiVersion::V2022_03_01).unwrap();
+        let migrated = &migration.value;
+
+        assert_eq!(migration.source, ApiVersion::V2021_03_01);
+        assert!(migrated.get("kind").is_none());
+        assert_eq!(migrated["type"], "Microsoft.App/containerApps");
+        assert_eq!(migrated["tags"]["team"], "checkout");
+        assert_eq!(
+            migrated["properties"]["managedEnvironmentId"],
+            "/subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env"
+        );
+        assert!(migrated["properties"]["template"].get("dapr").is_none());
+        assert_eq!(
+            migrated["properties"]["configuration"]["dapr"]["appId"],
+            "web"
+        );
+        assert!(migrated["properties"]["configuration"]["dapr"]
+            .get("components")
+            .is_none());
+        assert_eq!(migration.untranslated.len(), 2);
+        assert!(migration.untranslated[1].contains("statestore"));
+    }
+
+    #[test]
+    fn configurations_are_not_downgraded() {
+        let value: Value = serde_yaml::from_str(
+            "type: Microsoft.App/containerApps\nproperties:\n  environmentId: env\n",
+        )
+        .unwrap();
+        assert_eq!(detect_api_version(&value), Some(ApiVersion::V2023_05_01));
+        assert!(migrate_container_app_value(value, ApiVersion::V2022_03_01).is_err());
+    }
+}
@@ -1,54 +1,398 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
 
+use arm_parameters::lift_template_parameters;
+
+mod api_version;
+mod arm_parameters;
+mod azd;
+mod bicep;
+mod certificate;
 mod configuration;
+mod dapr_component;
+mod diff;
+mod export;
+mod identity;
+mod migration;
+mod output_format;
 mod properties;
 mod template;
+mod terraform;
 
+pub use api_version::ApiVersion;
+pub use arm_parameters::{write_arm_parameters_file, SecureParametersFile, TemplateParameter};
+pub use azd::*;
+pub use bicep::*;
+pub use certificate::*;
 pub use configuration::*;
+pub use dapr_component::*;
+pub use diff::*;
+pub use export::*;
+pub use identity::*;
+pub use migration::*;
+pub use output_format::OutputFormat;
 pub use properties::Properties;
 pub use template::*;
+pub use terraform::*;
 
-pub fn write_to_containerapps_file(file_path: &Path, config: &ContainerAppConfig) -> Result<()> {
-    let output_content = serde_yaml::to_string(config)?;
+pub fn write_to_containerapps_file(
+    file_path: &Path,
+    config: &ContainerAppConfig,
+    api_version: ApiVersion,
+) -> Result<()> {
+    let output_content = get_containerapps_yaml(config, api_version)?;
     let mut file = File::create(file_path)
         .unwrap_or_else(|_| panic!("Failed to create the output file - {:?}.", file_path));
     file.write_all(output_content.into_bytes().as_ref())?;
     Ok(())
 }
 
+/// The ContainerApps YAML for one app.  Each starts with a `---` document
+/// marker, so several can be joined into one multi-document file.
+pub fn get_containerapps_yaml(
+    config: &ContainerAppConfig,
+    api_version: ApiVersion,
+) -> Result<String> {
+    Ok(serde_yaml::to_string(
+        &api_version.to_container_app_value(config)?,
+    )?)
+}
+
+/// Writes an ARM template for one ContainerApp, lifting the selected values
+/// into template parameters.  Returns the non-secure parameter values.
 pub fn write_containerapps_arm_template(
     file_path: &Path,
     config: &ContainerAppConfig,
-) -> Result<()> {
+    template_parameters: &[TemplateParameter],
+    api_version: ApiVersion,
+) -> Result<BTreeMap<String, String>> {
     let mut arm_template_outline = ArmWrapper::default();
 
     let mut container_config = config.clone();
     container_config.kind = None;
-    container_config.api_version = Some("2021-03-01".to_string());
+    container_config.api_version = Some(api_version.to_string());
     container_config.resource_group = None;
-    arm_template_outline.resources.push(container_config);
-    arm_template_outline.outputs.containerapp_fqdn = OutputValue::new(&config.name);
+    let parameter_values = lift_template_parameters(
+        &mut container_config,
+        None,
+        template_parameters,
+        &mut arm_template_outline.parameters,
+    );
+    arm_template_outline
+        .resources
+        .push(ArmResource::ContainerApp(Box::new(DependentResource {
+            depends_on: Vec::new(),
+            resource: api_version.to_container_app_value(&container_config)?,
+        })));
+    if config.properties.configuration.ingress.is_some() {
+        arm_template_outline.outputs.containerapp_fqdn =
+            Some(OutputValue::new(&config.name, api_version));
+    }
 
     let output_content = serde_json::to_string(&arm_template_outline)?;
     let mut file = File::create(file_path)
         .unwrap_or_else(|_| panic!("Failed to create the output file - {:?}.", file_path));
     file.write_all(output_content.into_bytes().as_ref())?;
+    Ok(parameter_values)
+}
+
+/// Writes the Dapr components as an ARM template.  Secret values become
+/// secure parameters so they are never written to the template.
+///
+/// Returns the secret values, for a `SecureParametersFile` at deployment time.
+pub fn write_dapr_components_arm_template(
+    file_path: &Path,
+    components: &[DaprComponent],
+    api_version: ApiVersion,
+) -> Result<BTreeMap<String, String>> {
+    let (output_content, secret_values) =
+        get_dapr_components_arm_template(components, api_version)?;
+    let mut file = File::create(file_path)
+        .unwrap_or_else(|_| panic!("Failed to create the output file - {:?}.", file_path));
+    file.write_all(output_content.into_bytes().as_ref())?;
+    Ok(secret_values)
+}
+
+/// The ARM template for the Dapr components, with the secret values it lifted
+/// into secure parameters.
+pub fn get_dapr_components_arm_template(
+    components: &[DaprComponent],
+    api_version: ApiVersion,
+) -> Result<(String, BTreeMap<String, String>)> {
+    let mut arm_template_outline = ArmWrapper::default();
+    let mut secret_values = BTreeMap::new();
+
+    for component in components {
+        let mut component = component.clone();
+        component.api_version = Some(api_version.to_string());
+        component.resource_type = api_version.dapr_components_type();
+        let component_name = component.name.rsplit('/').next().unwrap_or_default();
+        for secret in component.properties.secrets.iter_mut() {
+            if let Some(value) = secret.value.as_mut() {
+                let parameter_name =
+                    get_parameter_name(&format!("{}-{}", component_name, &secret.name));
+                secret_values.insert(parameter_name.clone(), value.clone());
+                *value = format!("[parameters('{}')]", &parameter_name);
+                arm_template_outline
+                    .parameters
+                    .insert(parameter_name, ArmParameter::secure_string());
+            }
+        }
+        arm_template_outline
+            .resources
+            .push(ArmResource::DaprComponent(component));
+    }
+
+    Ok((serde_json::to_string(&arm_template_outline)?, secret_values))
+}
+
+pub fn write_certificates_arm_template(
+    file_path: &Path,
+    certificates: &[Certificate],
+    api_version: ApiVersion,
+) -> Result<()> {
+    let output_content = get_certificates_arm_template(certificates, api_version)?;
+    let mut file = File::create(file_path)
+        .unwrap_or_else(|_| panic!("Failed to create the output file - {:?}.", file_path));
+    file.write_all(output_content.into_bytes().as_ref())?;
     Ok(())
 }
 
+/// The ARM template for the certificates.  Passwords are secure parameters.
+pub fn get_certificates_arm_template(
+    certificates: &[Certificate],
+    api_version: ApiVersion,
+) -> Result<String> {
+    let mut arm_template_outline = ArmWrapper::default();
+
+    for certificate in certificates {
+        let parameter_name = certificate.password_parameter_name();
+        let mut certificate = certificate.clone();
+        certificate.api_version = Some(api_version.to_string());
+        certificate.resource_type = api_version.certificates_type();
+        certificate.properties.password = format!("[parameters('{}')]", &parameter_name);
+        arm_template_outline
+            .parameters
+            .insert(parameter_name, ArmParameter::secure_string());
+        arm_template_outline
+            .resources
+            .push(ArmResource::Certificate(certificate));
+    }
+
+    Ok(serde_json::to_string(&arm_template_outline)?)
+}
+
+/// Turns a resource name like `db-password` into a template parameter or
+/// symbol name like `dbPassword`.
+pub fn get_parameter_name(name: &str) -> String {
+    let mut parameter_name = String::new();
+    let mut upper = false;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if upper && !parameter_name.is_empty() {
+                parameter_name.push(c.to_ascii_uppercase());
+            } else {
+                parameter_name.push(c);
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    if parameter_name.starts_with(|c: char| c.is_ascii_digit()) {
+        parameter_name.insert(0, '_');
+    }
+    parameter_name
+}
+
+/// Writes one ARM template for every ContainerApp in the project, so the
+/// whole project is a single deployment.  Each entry is an app and the names
+/// of the apps it depends on.  Returns the non-secure parameter values.
+pub fn write_combined_containerapps_arm_template(
+    file_path: &Path,
+    apps: &[(&ContainerAppConfig, Vec<String>)],
+    template_parameters: &[TemplateParameter],
+    api_version: ApiVersion,
+) -> Result<BTreeMap<String, String>> {
+    let mut arm_template_outline = ArmWrapper::default();
+    let mut parameter_values = BTreeMap::new();
+
+    let app_names: Vec<&str> = apps
+        .iter()
+        .map(|(config, _)| config.name.as_str())
+        .collect();
+    for (config, depends_on) in apps {
+        let mut container_config = (*config).clone();
+        container_config.kind = None;
+        container_config.api_version = Some(api_version.to_string());
+        container_config.resource_group = None;
+        let values = lift_template_parameters(
+            &mut container_config,
+            Some(&config.name),
+            template_parameters,
+            &mut arm_template_outline.parameters,
+        );
+        for (name, value) in values {
+            match parameter_values.get(&name) {
+                Some(existing) if existing != &value => {
+                    return Err(anyhow!(
+                        "The template parameter {} is {} in an earlier service and {} in {}, they can't share one parameter",
+                        name,
+                        existing,
+                        value,
+                        config.name
+                    ));
+                }
+                _ => {
+                    parameter_values.insert(name, value);
+                }
+            }
+        }
+
+        let depends_on = depends_on
+            .iter()
+            .filter(|d| app_names.contains(&d.as_str()))
+            .map(|d| {
+                format!(
+                    "[resourceId('{}', '{}')]",
+                    api_version.container_apps_type(),
+                    d
+                )
+            })
+            .collect();
+        arm_template_outline
+            .resources
+            .push(ArmResource::ContainerApp(Box::new(DependentResource {
+                depends_on,
+                resource: api_version.to_container_app_value(&container_config)?,
+            })));
+        if config.properties.configuration.ingress.is_some() {
+            arm_template_outline.outputs.fqdns.insert(
+                format!("{}Fqdn", get_parameter_name(&config.name)),
+                OutputValue::new(&config.name, api_version),
+            );
+        }
+    }
+
+    let output_content = serde_json::to_string(&arm_template_outline)?;
+    let mut file = File::create(file_path)
+        .unwrap_or_else(|_| panic!("Failed to create the output file - {:?}.", file_path));
+    file.write_all(output_content.into_bytes().as_ref())?;
+    Ok(parameter_values)
+}
+
+/// Values of the secure parameters that carry the secrets of one app's
+/// template, for a `SecureParametersFile`.
+pub fn get_secret_parameters(config: &ContainerAppConfig) -> BTreeMap<String, String> {
+    config
+        .properties
+        .configuration
+        .secrets
+        .iter()
+        .filter_map(|secret| {
+            secret
+                .value
+                .as_ref()
+                .map(|value| (get_parameter_name(&secret.name), value.to_owned()))
+        })
+        .collect()
+}
+
+/// Values of the secure parameters that carry the secrets of the combined
+/// template, for a `SecureParametersFile`.
+pub fn get_combined_secret_parameters(configs: &[&ContainerAppConfig]) -> BTreeMap<String, String> {
+    let mut parameters = BTreeMap::new();
+    for config in configs {
+        for secret in config.properties.configuration.secrets.iter() {
+            if let Some(value) = &secret.value {
+                parameters.insert(
+                    get_secret_parameter_name(&config.name, &secret.name),
+                    value.to_owned(),
+                );
+            }
+        }
+    }
+    parameters
+}
+
+/// Name of the parameter for one app's secret, like `webDbPassword`.
+pub fn get_secret_parameter_name(app_name: &str, secret_name: &str) -> String {
+    get_parameter_name(&format!("{}-{}", app_name, secret_name))
+}
+
+/// Environment variable for an app's parameter, `web` and `dbPassword` become
+/// `WEB_DB_PASSWORD`.
+pub fn get_environment_variable_name(app_name: &str, parameter_name: &str) -> String {
+    let mut name = String::new();
+    for c in format!("{}-{}", app_name, parameter_name)
+        .trim_end_matches('-')
+        .chars()
+    {
+        if c.is_ascii_uppercase() && !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_uppercase());
+        } else if !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    name
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DependentResource<T> {
+    #[serde(rename = "dependsOn", default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    #[serde(flatten)]
+    pub resource: T,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ArmResource {
+    ContainerApp(Box<DependentResource<serde_yaml::Value>>),
+    DaprComponent(DaprComponent),
+    Certificate(Certificate),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArmParameter {
+    #[serde(rename = "type")]
+    pub parameter_type: String,
+    #[serde(rename = "defaultValue", skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+}
+impl ArmParameter {
+    pub fn secure_string() -> ArmParameter {
+        ArmParameter {
+            parameter_type: "securestring".to_string(),
+            default_value: None,
+        }
+    }
+
+    pub fn string(default_value: &str) -> ArmParameter {
+        ArmParameter {
+            parameter_type: "string".to_string(),
+            default_value: Some(default_value.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ArmWrapper {
     #[serde(rename = "$schema")]
     pub schema: &'static str,
     #[serde(rename = "contentVersion")]
     pub content_version: &'static str,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, ArmParameter>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    pub resources: Vec<ContainerAppConfig>,
+    pub resources: Vec<ArmResource>,
     pub outputs: OutputWrapper,
 }
 impl Default for ArmWrapper {
@@ -57,6 +401,7 @@ impl Default for ArmWrapper {
             schema:
                 "https://schema.management.azure.com/schemas/2019-08-01/deploymentTemplate.json#",
             content_version: "1.0.0.0",
+            parameters: BTreeMap::new(),
             resources: Vec::new(),
             outputs: OutputWrapper::default(),
         }
@@ -65,8 +410,10 @@ impl Default for ArmWrapper {
 
 #[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct OutputWrapper {
-    #[serde(rename = "containerappFqdn")]
-    pub containerapp_fqdn: OutputValue,
+    #[serde(rename = "containerappFqdn", skip_serializing_if = "Option::is_none")]
+    pub containerapp_fqdn: Option<OutputValue>,
+    #[serde(flatten)]
+    pub fqdns: BTreeMap<String, OutputValue>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -76,19 +423,20 @@ pub struct OutputValue {
     pub value: String,
 }
 impl OutputValue {
-    pub fn new(service_name: &str) -> OutputValue {
+    pub fn new(service_name: &str, api_version: ApiVersion) -> OutputValue {
         OutputValue {
-            output_type: "string".to_string(), 
-            value: format!("[reference(resourceId('Microsoft.Web/containerApps', '{}')).configuration.ingress.fqdn]", service_name)
+            output_type: "string".to_string(),
+            value: format!(
+                "[reference(resourceId('{}', '{}')).configuration.ingress.fqdn]",
+                api_version.container_apps_type(),
+                service_name
+            ),
         }
     }
 }
 impl Default for OutputValue {
     fn default() -> OutputValue {
-        OutputValue {
-            output_type: "string".to_string(),
-            value: "[reference(resourceId('Microsoft.Web/containerApps', 'SERVICENAME')).configuration.ingress.fqdn]".to_string(),
-        }
+        OutputValue::new("SERVICENAME", ApiVersion::default())
     }
 }
 
@@ -106,6 +454,8 @@ pub struct ContainerAppConfig {
     pub resource_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<Identity>,
     pub properties: Properties,
 }
 impl Default for ContainerAppConfig {
@@ -118,6 +468,7 @@ impl Default for ContainerAppConfig {
             resource_group: None,
             resource_type: "Microsoft.Web/containerApps".to_string(),
             tags: None,
+            identity: None,
             properties: Properties::default(),
         }
     }
@@ -139,4 +490,95 @@ mod tests {
         let file = File::open("test/containerapps.yml").unwrap();
         let _config: ContainerAppConfig = serde_yaml::from_reader(file).unwrap();
     }
+
+    #[test]
+    fn combined_arm_template_links_apps_and_lifts_secrets() {
+        let mut web = ContainerAppConfig {
+            name: "web".to_string(),
+            location: "eastus".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        web.properties
+            .configuration
+            .secrets
+            .push(SecretsConfiguration {
+                name: "db-password".to_string(),
+                value: Some("hunter2".to_string()),
+                ..SecretsConfiguration::default()
+            });
+        let mut db = ContainerAppConfig {
+            name: "db".to_string(),
+            location: "eastus".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        db.properties.configuration.ingress = Some(IngressConfiguration::default());
+        let apps = vec![
+            (&web, vec!["db".to_string(), "cache".to_string()]),
+            (&db, Vec::new()),
+        ];
+
+        let path =
+            std::env::temp_dir().join(format!("combined-arm-test-{}.json", uuid::Uuid::new_v4()));
+        write_combined_containerapps_arm_template(
+            &path,
+            &apps,
+            &TemplateParameter::defaults(),
+            ApiVersion::V2021_03_01,
+        )
+        .unwrap();
+        let template: serde_json::Value =
+            serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            template["resources"][0]["dependsOn"],
+            serde_json::json!(["[resourceId('Microsoft.Web/containerApps', 'db')]"])
+        );
+        assert_eq!(
+            template["parameters"]["webDbPassword"]["type"],
+            "securestring"
+        );
+        assert_eq!(
+            template["resources"][0]["properties"]["configuration"]["secrets"][0]["value"],
+            "[parameters('webDbPassword')]"
+        );
+        assert!(template["outputs"]["dbFqdn"].is_object());
+        assert_eq!(
+            get_combined_secret_parameters(&[&web, &db]),
+            BTreeMap::from([("webDbPassword".to_string(), "hunter2".to_string())])
+        );
+        assert_eq!(
+            get_secret_parameters(&web),
+            BTreeMap::from([("dbPassword".to_string(), "hunter2".to_string())])
+        );
+    }
+
+    #[test]
+    fn combined_apps_cant_share_a_parameter_with_different_values() {
+        let web = ContainerAppConfig {
+            name: "web".to_string(),
+            location: "eastus".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        let db = ContainerAppConfig {
+            name: "db".to_string(),
+            location: "westus".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        let path = std::env::temp_dir().join(format!(
+            "combined-arm-conflict-test-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+
+        let error = write_combined_containerapps_arm_template(
+            &path,
+            &[(&web, Vec::new()), (&db, Vec::new())],
+            &TemplateParameter::defaults(),
+            ApiVersion::default(),
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("location is eastus"));
+        assert!(error.to_string().contains("westus in db"));
+    }
 }
@@ -0,0 +1,51 @@
+use anyhow::anyhow;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Yaml,
+    Arm,
+    Bicep,
+    Terraform,
+    Azd,
+}
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "yaml" => Ok(OutputFormat::Yaml),
+            "arm" => Ok(OutputFormat::Arm),
+            "bicep" => Ok(OutputFormat::Bicep),
+            "terraform" => Ok(OutputFormat::Terraform),
+            "azd" => Ok(OutputFormat::Azd),
+            _ => Err(anyhow!(
+                "The format is yaml, arm, bicep, terraform or azd, found {}",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `--format` offers the values of the clap enum, so each has to parse into
+    /// the variant of the same name.
+    #[test]
+    fn every_output_format_value_parses() {
+        for value in crate::cli::OutputFormat::variants() {
+            let parsed = OutputFormat::from_str(value).unwrap();
+            assert_eq!(parsed.to_string().to_lowercase(), value.to_lowercase());
+        }
+        assert!(OutputFormat::from_str("helm").is_err());
+    }
+}
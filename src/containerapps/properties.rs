@@ -4,7 +4,11 @@ use super::{Configuration, Template};
 
 #[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct Properties {
-    #[serde(rename = "kubeEnvironmentId")]
+    #[serde(
+        rename = "kubeEnvironmentId",
+        alias = "managedEnvironmentId",
+        alias = "environmentId"
+    )]
     pub kube_environment_id: String,
     pub configuration: Configuration,
     pub template: Template,
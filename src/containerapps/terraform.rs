@@ -0,0 +1,506 @@
+use super::{ContainerAppConfig, IdentityType, IngressConfiguration, TrafficConfiguration};
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::fs::{create_dir_all, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+const ENVIRONMENT_DATA_SOURCE: &str = "data.azurerm_container_app_environment.containerapps";
+
+/// Writes a directory of Terraform files for the ContainerApps: `main.tf` with
+/// the provider and the environment data source, `variables.tf` with the
+/// secrets as sensitive variables, `app_{name}.tf` per app and `outputs.tf`.
+pub fn write_containerapps_terraform(
+    directory: &Path,
+    configs: &[&ContainerAppConfig],
+) -> Result<()> {
+    if configs.is_empty() {
+        return Err(anyhow!(
+            "There are no ContainerApps to write Terraform files for"
+        ));
+    }
+    create_dir_all(directory)?;
+
+    let environment_id = configs
+        .first()
+        .map(|c| c.properties.kube_environment_id.as_str())
+        .unwrap_or_default();
+    write_terraform_file(
+        &directory.join("main.tf"),
+        &get_main_blocks(environment_id)?,
+    )?;
+
+    let mut variables = Vec::new();
+    let mut outputs = Vec::new();
+    for config in configs {
+        let label = get_terraform_label(&config.name);
+        for secret in config.properties.configuration.secrets.iter() {
+            if secret.value.is_some() {
+                variables.push(
+                    HclBlock::new(&format!(
+                        "variable \"{}\"",
+                        get_secret_variable_name(&config.name, &secret.name)
+                    ))
+                    .attribute("type", "string")
+                    .attribute("sensitive", "true"),
+                );
+            }
+        }
+        if config.properties.configuration.ingress.is_some() {
+            outputs.push(
+                HclBlock::new(&format!("output \"{}_fqdn\"", &label)).attribute(
+                    "value",
+                    &format!("azurerm_container_app.{}.ingress[0].fqdn", &label),
+                ),
+            );
+        }
+
+        let file_path = directory.join(get_app_file_name(&config.name));
+        debug!(
+            "Writing a Terraform configuration to {}.",
+            file_path.display()
+        );
+        write_terraform_file(&file_path, &[get_container_app_block(config)])?;
+    }
+    write_terraform_file(&directory.join("variables.tf"), &variables)?;
+    write_terraform_file(&directory.join("outputs.tf"), &outputs)?;
+    Ok(())
+}
+
+/// App files are prefixed so an app named `main`, `variables` or `outputs`
+/// doesn't overwrite the shared files.
+fn get_app_file_name(name: &str) -> String {
+    format!("app_{}.tf", name)
+}
+
+fn get_main_blocks(environment_id: &str) -> Result<Vec<HclBlock>> {
+    let (resource_group, environment_name) = get_environment_parts(environment_id)?;
+    Ok(vec![
+        HclBlock::new("terraform").block(
+            HclBlock::new("required_providers").block(
+                HclBlock::new("azurerm =")
+                    .attribute("source", &get_hcl_string("hashicorp/azurerm"))
+                    .attribute("version", &get_hcl_string(">= 3.43.0")),
+            ),
+        ),
+        HclBlock::new("provider \"azurerm\"").block(HclBlock::new("features")),
+        HclBlock::new("data \"azurerm_container_app_environment\" \"containerapps\"")
+            .attribute("name", &get_hcl_string(environment_name))
+            .attribute("resource_group_name", &get_hcl_string(resource_group)),
+    ])
+}
+
+fn get_environment_parts(environment_id: &str) -> Result<(&str, &str)> {
+    if environment_id.trim().is_empty() {
+        return Err(anyhow!(
+            "The ContainerApps have no environment id, which the Terraform data source needs"
+        ));
+    }
+    let segments: Vec<&str> = environment_id.trim_matches('/').split('/').collect();
+    let resource_group = segments
+        .iter()
+        .position(|s| s.eq_ignore_ascii_case("resourceGroups"))
+        .and_then(|i| segments.get(i + 1));
+    match (resource_group, segments.last()) {
+        (Some(resource_group), Some(name)) if !name.is_empty() => Ok((resource_group, name)),
+        _ => Err(anyhow!(
+            "Unable to determine the ContainerApps environment from {}",
+            environment_id
+        )),
+    }
+}
+
+fn get_container_app_block(config: &ContainerAppConfig) -> HclBlock {
+    let label = get_terraform_label(&config.name);
+    let configuration = &config.properties.configuration;
+    let template = &config.properties.template;
+    let resource_group = config
+        .resource_group
+        .as_ref()
+        .map(|rg| get_hcl_string(rg))
+        .unwrap_or_else(|| format!("{}.resource_group_name", ENVIRONMENT_DATA_SOURCE));
+
+    let mut block = HclBlock::new(&format!(
+        "resource \"azurerm_container_app\" \"{}\"",
+        &label
+    ))
+    .attribute("name", &get_hcl_string(&config.name))
+    .attribute(
+        "container_app_environment_id",
+        &format!("{}.id", ENVIRONMENT_DATA_SOURCE),
+    )
+    .attribute("resource_group_name", &resource_group)
+    .attribute(
+        "revision_mode",
+        &get_hcl_string(&configuration.active_revisions_mode.to_string()),
+    );
+
+    if let Some(tags) = &config.tags {
+        let mut tags: Vec<(&String, &String)> = tags.iter().collect();
+        tags.sort();
+        let mut tag_block = HclBlock::new("tags =");
+        for (key, value) in tags {
+            tag_block = tag_block.attribute(&get_hcl_string(key), &get_hcl_string(value));
+        }
+        block = block.block(tag_block);
+    }
+
+    if let Some(identity) = &config.identity {
+        let identity_type = match identity.identity_type {
+            IdentityType::None => None,
+            IdentityType::SystemAssigned => Some("SystemAssigned"),
+            IdentityType::UserAssigned => Some("UserAssigned"),
+            IdentityType::SystemAssignedUserAssigned => Some("SystemAssigned, UserAssigned"),
+        };
+        if let Some(identity_type) = identity_type {
+            let mut identity_block =
+                HclBlock::new("identity").attribute("type", &get_hcl_string(identity_type));
+            if !identity.user_assigned_identities.is_empty() {
+                identity_block = identity_block.attribute(
+                    "identity_ids",
+                    &get_hcl_list(identity.user_assigned_identities.keys()),
+                );
+            }
+            block = block.block(identity_block);
+        }
+    }
+
+    for secret in configuration.secrets.iter() {
+        let mut secret_block =
+            HclBlock::new("secret").attribute("name", &get_hcl_string(&secret.name));
+        if secret.value.is_some() {
+            secret_block = secret_block.attribute(
+                "value",
+                &format!(
+                    "var.{}",
+                    get_secret_variable_name(&config.name, &secret.name)
+                ),
+            );
+        }
+        if let Some(url) = &secret.key_vault_url {
+            secret_block = secret_block.attribute("key_vault_secret_id", &get_hcl_string(url));
+        }
+        if let Some(identity) = &secret.identity {
+            secret_block =
+                secret_block.attribute("identity", &get_hcl_string(&get_identity_value(identity)));
+        }
+        block = block.block(secret_block);
+    }
+
+    for registry in configuration.registries.iter() {
+        let mut registry_block =
+            HclBlock::new("registry").attribute("server", &get_hcl_string(&registry.server));
+        if let Some(username) = &registry.username {
+            registry_block = registry_block.attribute("username", &get_hcl_string(username));
+        }
+        if let Some(secret) = &registry.password_secret_ref {
+            registry_block =
+                registry_block.attribute("password_secret_name", &get_hcl_string(secret));
+        }
+        if let Some(identity) = &registry.identity {
+            registry_block = registry_block
+                .attribute("identity", &get_hcl_string(&get_identity_value(identity)));
+        }
+        block = block.block(registry_block);
+    }
+
+    if let Some(ingress) = configuration.ingress.as_ref() {
+        block = block.block(get_ingress_block(config, ingress));
+    }
+
+    let mut template_block = HclBlock::new("template")
+        .attribute("min_replicas", &template.scale.min_replicas.to_string());
+    if let Some(max_replicas) = template.scale.max_replicas {
+        template_block = template_block.attribute("max_replicas", &max_replicas.to_string());
+    }
+    if let Some(suffix) = &template.revision_suffix {
+        template_block = template_block.attribute("revision_suffix", &get_hcl_string(suffix));
+    }
+    for container in template.containers.iter() {
+        let resources = container.resources.clone().unwrap_or_default();
+        let cpu = resources
+            .cpu
+            .filter(|c| c.parse::<f64>().is_ok())
+            .unwrap_or_else(|| "0.5".to_string());
+        let memory = resources.memory.unwrap_or_else(|| "1Gi".to_string());
+        let mut container_block = HclBlock::new("container")
+            .attribute("name", &get_hcl_string(&container.name))
+            .attribute("image", &get_hcl_string(&container.image))
+            .attribute("cpu", &cpu)
+            .attribute("memory", &get_hcl_string(&memory));
+        if !container.command.is_empty() {
+            container_block =
+                container_block.attribute("command", &get_hcl_list(&container.command));
+        }
+        if !container.args.is_empty() {
+            container_block = container_block.attribute("args", &get_hcl_list(&container.args));
+        }
+        for env in container.env.iter() {
+            let mut env_block = HclBlock::new("env").attribute("name", &get_hcl_string(&env.name));
+            if let Some(value) = &env.value {
+                env_block = env_block.attribute("value", &get_hcl_string(value));
+            }
+            if let Some(secret) = &env.secret_ref {
+                env_block = env_block.attribute("secret_name", &get_hcl_string(secret));
+            }
+            container_block = container_block.block(env_block);
+        }
+        template_block = template_block.block(container_block);
+    }
+    block.block(template_block)
+}
+
+fn get_ingress_block(config: &ContainerAppConfig, ingress: &IngressConfiguration) -> HclBlock {
+    let transport = serde_json::to_value(&ingress.transport)
+        .ok()
+        .and_then(|t| t.as_str().map(|t| t.to_owned()))
+        .unwrap_or_else(|| "auto".to_string());
+
+    let mut ingress_block = HclBlock::new("ingress")
+        .attribute("external_enabled", &ingress.external.to_string())
+        .attribute(
+            "allow_insecure_connections",
+            &ingress.allow_insecure.to_string(),
+        )
+        .attribute(
+            "target_port",
+            &ingress.target_port.unwrap_or(80).to_string(),
+        )
+        .attribute("transport", &get_hcl_string(&transport));
+    if let Some(mode) = &ingress.client_certificate_mode {
+        if let Some(mode) = serde_json::to_value(mode)
+            .ok()
+            .and_then(|m| m.as_str().map(|m| m.to_owned()))
+        {
+            ingress_block =
+                ingress_block.attribute("client_certificate_mode", &get_hcl_string(&mode));
+        }
+    }
+
+    let traffic = if ingress.traffic.is_empty() {
+        vec![TrafficConfiguration {
+            revision_name: None,
+            latest_revision: Some(true),
+            weight: Some(100),
+        }]
+    } else {
+        ingress.traffic.clone()
+    };
+    for entry in traffic {
+        let mut traffic_block = HclBlock::new("traffic_weight");
+        if entry.latest_revision.unwrap_or(false) {
+            traffic_block = traffic_block.attribute("latest_revision", "true");
+        }
+        if let Some(revision_name) = &entry.revision_name {
+            let prefix = format!("{}--", &config.name);
+            let suffix = revision_name.strip_prefix(&prefix).unwrap_or(revision_name);
+            traffic_block = traffic_block.attribute("revision_suffix", &get_hcl_string(suffix));
+        }
+        traffic_block =
+            traffic_block.attribute("percentage", &entry.weight.unwrap_or(0).to_string());
+        ingress_block = ingress_block.block(traffic_block);
+    }
+
+    for restriction in ingress.ip_security_restrictions.iter() {
+        let mut restriction_block = HclBlock::new("ip_security_restriction")
+            .attribute("name", &get_hcl_string(&restriction.name))
+            .attribute(
+                "action",
+                &get_hcl_string(&format!("{:?}", restriction.action)),
+            )
+            .attribute(
+                "ip_address_range",
+                &get_hcl_string(&restriction.ip_address_range),
+            );
+        if let Some(description) = &restriction.description {
+            restriction_block =
+                restriction_block.attribute("description", &get_hcl_string(description));
+        }
+        ingress_block = ingress_block.block(restriction_block);
+    }
+
+    if ingress.cors_policy.is_some() || ingress.sticky_sessions.is_some() {
+        eprintln!(
+            "The azurerm provider does not support CORS or sticky sessions on {}, they are not in the Terraform configuration.",
+            &config.name
+        );
+    }
+    if !ingress.custom_domains.is_empty() {
+        eprintln!(
+            "Custom domains for {} need azurerm_container_app_custom_domain resources, they are not in the Terraform configuration.",
+            &config.name
+        );
+    }
+    ingress_block
+}
+
+fn get_identity_value(identity: &str) -> String {
+    if identity.eq_ignore_ascii_case("system") {
+        "System".to_string()
+    } else {
+        identity.to_string()
+    }
+}
+
+fn get_secret_variable_name(app_name: &str, secret_name: &str) -> String {
+    format!(
+        "{}_{}",
+        get_terraform_label(app_name),
+        get_terraform_label(secret_name)
+    )
+}
+
+fn get_terraform_label(name: &str) -> String {
+    let mut label: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if label.starts_with(|c: char| c.is_ascii_digit()) {
+        label.insert(0, '_');
+    }
+    label
+}
+
+fn get_hcl_string(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+        .replace("${", "$${")
+        .replace("%{", "%%{");
+    format!("\"{}\"", escaped)
+}
+
+fn get_hcl_list<I, S>(values: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let items: Vec<String> = values
+        .into_iter()
+        .map(|v| get_hcl_string(v.as_ref()))
+        .collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn write_terraform_file(file_path: &Path, blocks: &[HclBlock]) -> Result<()> {
+    let output_content: Vec<String> = blocks.iter().map(|b| b.render(0)).collect();
+    let mut file = File::create(file_path)?;
+    file.write_all(output_content.join("\n").into_bytes().as_ref())?;
+    Ok(())
+}
+
+/// A block of HCL with its attributes and nested blocks.  Attributes are
+/// rendered first with their `=` aligned, the way `terraform fmt` does.
+struct HclBlock {
+    header: String,
+    attributes: Vec<(String, String)>,
+    blocks: Vec<HclBlock>,
+}
+impl HclBlock {
+    fn new(header: &str) -> Self {
+        HclBlock {
+            header: header.to_string(),
+            attributes: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn attribute(mut self, name: &str, value: &str) -> Self {
+        self.attributes.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    fn block(mut self, block: HclBlock) -> Self {
+        self.blocks.push(block);
+        self
+    }
+
+    fn render(&self, indent: usize) -> String {
+        let padding = "  ".repeat(indent);
+        if self.attributes.is_empty() && self.blocks.is_empty() {
+            return format!("{}{} {{}}\n", &padding, &self.header);
+        }
+        let width = self
+            .attributes
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or_default();
+
+        let mut output = format!("{}{} {{\n", &padding, &self.header);
+        for (name, value) in self.attributes.iter() {
+            output.push_str(&format!(
+                "{}  {:width$} = {}\n",
+                &padding,
+                name,
+                value,
+                width = width
+            ));
+        }
+        for (index, block) in self.blocks.iter().enumerate() {
+            if index > 0 || !self.attributes.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&block.render(indent + 1));
+        }
+        output.push_str(&format!("{}}}\n", &padding));
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn hcl_blocks_align_attributes() {
+        let block = HclBlock::new("resource \"azurerm_container_app\" \"web\"")
+            .attribute("name", "\"web\"")
+            .attribute("revision_mode", "\"Single\"")
+            .block(HclBlock::new("template").attribute("min_replicas", "1"));
+
+        assert_eq!(
+            block.render(0),
+            "resource \"azurerm_container_app\" \"web\" {\n  name          = \"web\"\n  revision_mode = \"Single\"\n\n  template {\n    min_replicas = 1\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn environment_parts_come_from_the_resource_id() {
+        let (resource_group, name) = get_environment_parts(
+            "/subscriptions/mysubscription/resourceGroups/myresourcegroup/providers/Microsoft.Web/kubeEnvironments/myenvironment",
+        )
+        .unwrap();
+
+        assert_eq!(resource_group, "myresourcegroup");
+        assert_eq!(name, "myenvironment");
+    }
+
+    #[test]
+    fn apps_are_written_next_to_the_shared_files() {
+        let mut app = ContainerAppConfig {
+            name: "main".to_string(),
+            ..ContainerAppConfig::default()
+        };
+        app.properties.kube_environment_id = "/subscriptions/mysubscription/resourceGroups/myresourcegroup/providers/Microsoft.App/managedEnvironments/myenvironment".to_string();
+        app.properties.configuration.ingress = Some(IngressConfiguration::default());
+        let directory = std::env::temp_dir().join(format!("terraform-test-{}", Uuid::new_v4()));
+
+        write_containerapps_terraform(&directory, &[&app]).unwrap();
+        let read = |file: &str| std::fs::read_to_string(directory.join(file)).unwrap();
+        let main = read("main.tf");
+        let app_file = read("app_main.tf");
+        let outputs = read("outputs.tf");
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert!(main.contains("name                = \"myenvironment\""));
+        assert!(app_file.contains("resource \"azurerm_container_app\" \"main\""));
+        assert!(outputs.contains("output \"main_fqdn\""));
+        assert!(write_containerapps_terraform(&directory, &[]).is_err());
+    }
+}
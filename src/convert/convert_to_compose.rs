@@ -0,0 +1,355 @@
+use crate::containerapps::{get_environment_variable_name, ContainerAppConfig, RevisionMode};
+use anyhow::{anyhow, Result};
+use serde_yaml::{Mapping, Value};
+
+/// A Compose document built from ContainerApps configurations, along with
+/// the settings that have no local equivalent.
+#[derive(Debug)]
+pub struct ComposeConversion {
+    pub document: Value,
+    pub untranslated: Vec<String>,
+}
+
+/// Turns ContainerApps documents back into a Compose document.  Each
+/// container becomes a service; apps with several containers share the
+/// first container's network, as they share localhost in ContainerApps.
+/// Works from the raw documents so settings the model doesn't know about,
+/// like Dapr or scale rules, can still be reported.
+pub fn convert_to_compose(documents: &[Value]) -> Result<ComposeConversion> {
+    let mut services = Mapping::new();
+    let mut secrets = Mapping::new();
+    let mut untranslated = Vec::new();
+
+    for document in documents {
+        let config: ContainerAppConfig = serde_yaml::from_value(document.clone())
+            .map_err(|e| anyhow!("The configuration is not a ContainerApp resource - {}", e))?;
+        let app = &config.name;
+        let properties = &config.properties;
+
+        let mut secret_names = Vec::new();
+        for secret in properties.configuration.secrets.iter() {
+            let compose_name = format!("{}-{}", app, secret.name);
+            let mut source = Mapping::new();
+            source.insert(
+                Value::from("environment"),
+                Value::from(get_environment_variable_name(app, &secret.name)),
+            );
+            secrets.insert(Value::from(compose_name.as_str()), Value::Mapping(source));
+            secret_names.push(Value::from(compose_name));
+            if let Some(url) = secret.key_vault_url.as_ref() {
+                untranslated.push(format!(
+                    "{}: secret '{}' is read from Key Vault ({}), set {} locally",
+                    app,
+                    secret.name,
+                    url,
+                    get_environment_variable_name(app, &secret.name)
+                ));
+            }
+        }
+
+        let containers = &properties.template.containers;
+        let first_service = match containers.first() {
+            Some(container) if containers.len() > 1 => format!("{}-{}", app, container.name),
+            Some(_) => app.to_owned(),
+            None => {
+                untranslated.push(format!("{}: has no containers and was skipped", app));
+                continue;
+            }
+        };
+        if containers.len() > 1 {
+            untranslated.push(format!(
+                "{}: its {} containers became services sharing the network of {}",
+                app,
+                containers.len(),
+                first_service
+            ));
+        }
+
+        for (index, container) in containers.iter().enumerate() {
+            let mut service = Mapping::new();
+            service.insert(Value::from("image"), Value::from(container.image.as_str()));
+            if !container.command.is_empty() {
+                service.insert(
+                    Value::from("entrypoint"),
+                    Value::from(container.command.clone()),
+                );
+            }
+            if !container.args.is_empty() {
+                service.insert(Value::from("command"), Value::from(container.args.clone()));
+            }
+
+            if !container.env.is_empty() {
+                let mut environment = Mapping::new();
+                for variable in container.env.iter() {
+                    let value = match (variable.value.as_ref(), variable.secret_ref.as_ref()) {
+                        (_, Some(secret)) => {
+                            format!("${{{}}}", get_environment_variable_name(app, secret))
+                        }
+                        (Some(value), None) => value.to_owned(),
+                        (None, None) => String::new(),
+                    };
+                    environment.insert(Value::from(variable.name.as_str()), Value::from(value));
+                }
+                service.insert(Value::from("environment"), Value::Mapping(environment));
+            }
+
+            if index == 0 {
+                if let Some((port, external)) = properties
+                    .configuration
+                    .ingress
+                    .as_ref()
+                    .and_then(|i| i.target_port.map(|port| (port, i.external)))
+                {
+                    if external {
+                        service.insert(
+                            Value::from("ports"),
+                            Value::from(vec![format!("{}:{}", port, port)]),
+                        );
+                    } else {
+                        service.insert(Value::from("expose"), Value::from(vec![port.to_string()]));
+                    }
+                }
+            } else {
+                service.insert(
+                    Value::from("network_mode"),
+                    Value::from(format!("service:{}", first_service)),
+                );
+            }
+
+            if !secret_names.is_empty() {
+                service.insert(
+                    Value::from("secrets"),
+                    Value::Sequence(secret_names.clone()),
+                );
+            }
+
+            let deploy = get_deploy(&config, index);
+            if !deploy.is_empty() {
+                service.insert(Value::from("deploy"), Value::Mapping(deploy));
+            }
+
+            let service_name = if index == 0 {
+                first_service.clone()
+            } else {
+                format!("{}-{}", app, container.name)
+            };
+            services.insert(Value::from(service_name), Value::Mapping(service));
+        }
+
+        untranslated.extend(
+            get_untranslated(&config, document)
+                .into_iter()
+                .map(|note| format!("{}: {}", app, note)),
+        );
+    }
+
+    let mut compose = Mapping::new();
+    compose.insert(Value::from("services"), Value::Mapping(services));
+    if !secrets.is_empty() {
+        compose.insert(Value::from("secrets"), Value::Mapping(secrets));
+    }
+    Ok(ComposeConversion {
+        document: Value::Mapping(compose),
+        untranslated,
+    })
+}
+
+/// Replicas come from the minimum scale, since Compose doesn't scale on
+/// demand, and resources become limits.
+fn get_deploy(config: &ContainerAppConfig, index: usize) -> Mapping {
+    let mut deploy = Mapping::new();
+    let scale = &config.properties.template.scale;
+    if scale.min_replicas > 1 {
+        deploy.insert(Value::from("replicas"), Value::from(scale.min_replicas));
+    }
+
+    let resources = config.properties.template.containers[index]
+        .resources
+        .as_ref();
+    let mut limits = Mapping::new();
+    if let Some(cpu) = resources.and_then(|r| r.cpu.as_ref()) {
+        limits.insert(Value::from("cpus"), Value::from(cpu.as_str()));
+    }
+    if let Some(memory) = resources.and_then(|r| r.memory.as_ref()) {
+        limits.insert(
+            Value::from("memory"),
+            Value::from(get_compose_memory(memory)),
+        );
+    }
+    if !limits.is_empty() {
+        let mut resources = Mapping::new();
+        resources.insert(Value::from("limits"), Value::Mapping(limits));
+        deploy.insert(Value::from("resources"), Value::Mapping(resources));
+    }
+    deploy
+}
+
+/// ContainerApps sizes memory in `Gi`, Compose in `m` or `g`.
+fn get_compose_memory(memory: &str) -> String {
+    let (amount, factor) = if let Some(amount) = memory.strip_suffix("Gi") {
+        (amount, 1024.0)
+    } else if let Some(amount) = memory.strip_suffix("Mi") {
+        (amount, 1.0)
+    } else {
+        return memory.to_owned();
+    };
+    match amount.trim().parse::<f64>() {
+        Ok(amount) => format!("{}m", (amount * factor).round()),
+        Err(_) => memory.to_owned(),
+    }
+}
+
+fn get_untranslated(config: &ContainerAppConfig, document: &Value) -> Vec<String> {
+    let mut untranslated = Vec::new();
+    let configuration = &config.properties.configuration;
+    let ingress = configuration.ingress.clone().unwrap_or_default();
+    let scale = &config.properties.template.scale;
+
+    if scale.min_replicas == 0 {
+        untranslated.push("scales to zero, one replica will run locally".to_owned());
+    }
+    if let Some(max_replicas) = scale.max_replicas {
+        if max_replicas > scale.min_replicas.max(1) {
+            untranslated.push(format!("scales out to {} replicas", max_replicas));
+        }
+    }
+    if document["properties"]["template"]["scale"]
+        .get("rules")
+        .is_some()
+    {
+        untranslated.push("has scale rules".to_owned());
+    }
+    if configuration.active_revisions_mode == RevisionMode::Multiple || !ingress.traffic.is_empty()
+    {
+        untranslated.push("splits traffic between revisions".to_owned());
+    }
+    if !ingress.custom_domains.is_empty() {
+        untranslated.push("has custom domains".to_owned());
+    }
+    if !ingress.ip_security_restrictions.is_empty() {
+        untranslated.push("has IP security restrictions".to_owned());
+    }
+    if ingress.cors_policy.is_some() {
+        untranslated.push("has a CORS policy".to_owned());
+    }
+    if ingress.sticky_sessions.is_some() {
+        untranslated.push("uses sticky sessions".to_owned());
+    }
+    if ingress.client_certificate_mode.is_some() {
+        untranslated.push("has a client certificate mode".to_owned());
+    }
+    if document["properties"]["configuration"]
+        .get("dapr")
+        .is_some()
+        || document["properties"]["template"].get("dapr").is_some()
+    {
+        untranslated.push("uses Dapr, run a sidecar with the dapr CLI".to_owned());
+    }
+    if config.identity.is_some() {
+        untranslated.push("uses a managed identity".to_owned());
+    }
+    for registry in configuration.registries.iter() {
+        untranslated.push(format!(
+            "pulls from {}, run docker login first",
+            registry.server
+        ));
+    }
+    untranslated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTAINER_APP: &str = r#"
+location: eastus
+name: web
+type: Microsoft.App/containerApps
+properties:
+  environmentId: /subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env
+  configuration:
+    activeRevisionsMode: single
+    secrets:
+      - name: db-password
+        value: hunter2
+    ingress:
+      external: true
+      targetPort: 8080
+    dapr:
+      enabled: true
+  template:
+    containers:
+      - name: web
+        image: contoso/web:1.0
+        args: ["--verbose"]
+        env:
+          - name: MODE
+            value: production
+          - name: DB_PASSWORD
+            secretRef: db-password
+        resources:
+          cpu: "0.5"
+          memory: 1.0Gi
+      - name: proxy
+        image: envoyproxy/envoy
+    scale:
+      minReplicas: 0
+      maxReplicas: 5
+"#;
+
+    #[test]
+    fn container_apps_become_compose_services() {
+        let document: Value = serde_yaml::from_str(CONTAINER_APP).unwrap();
+        let conversion = convert_to_compose(&[document]).unwrap();
+        let compose = &conversion.document;
+        let web = &compose["services"]["web-web"];
+
+        assert_eq!(web["image"], "contoso/web:1.0");
+        assert_eq!(web["command"][0], "--verbose");
+        assert_eq!(web["ports"][0], "8080:8080");
+        assert_eq!(web["environment"]["MODE"], "production");
+        assert_eq!(web["environment"]["DB_PASSWORD"], "${WEB_DB_PASSWORD}");
+        assert_eq!(web["secrets"][0], "web-db-password");
+        assert_eq!(web["deploy"]["resources"]["limits"]["memory"], "1024m");
+        assert!(web["deploy"].get("replicas").is_none());
+        assert_eq!(
+            compose["services"]["web-proxy"]["network_mode"],
+            "service:web-web"
+        );
+        assert_eq!(
+            compose["secrets"]["web-db-password"]["environment"],
+            "WEB_DB_PASSWORD"
+        );
+        assert!(!serde_yaml::to_string(compose).unwrap().contains("hunter2"));
+        assert!(conversion.untranslated.iter().any(|n| n.contains("zero")));
+        assert!(conversion.untranslated.iter().any(|n| n.contains("Dapr")));
+    }
+
+    #[test]
+    fn apps_without_ingress_become_services_without_ports() {
+        let document: Value = serde_yaml::from_str(
+            r#"
+location: eastus
+name: worker
+type: Microsoft.App/containerApps
+properties:
+  managedEnvironmentId: /subscriptions/sub/resourceGroups/rg/providers/Microsoft.App/managedEnvironments/env
+  configuration:
+    activeRevisionsMode: single
+  template:
+    containers:
+      - name: worker
+        image: contoso/worker:1.0
+    scale:
+      minReplicas: 1
+"#,
+        )
+        .unwrap();
+        let conversion = convert_to_compose(&[document]).unwrap();
+        let worker = &conversion.document["services"]["worker"];
+
+        assert_eq!(worker["image"], "contoso/worker:1.0");
+        assert!(worker.get("ports").is_none());
+        assert!(worker.get("expose").is_none());
+    }
+}
@@ -4,7 +4,7 @@ use crate::containerapps::ContainerAppConfig;
 use crate::VERBOSE;
 use anyhow::Result;
 
-use super::get_properties;
+use super::{get_identity_from_service, get_properties};
 
 pub fn convert_to_containerapps(
     service_name: &str,
@@ -12,12 +12,15 @@ pub fn convert_to_containerapps(
     containerapps_configuration_data: ContainerAppsConfigurationData,
 ) -> Result<ContainerAppConfig> {
     if *VERBOSE {
-        println!();
-        println!(
+        eprintln!();
+        eprintln!(
             "The ContainerApps configuration file is documented at https://aka.ms/containerapps/spec."
         );
-        println!();
+        eprintln!();
     };
+    let properties = get_properties(&containerapps_configuration_data, service_name, &service)?;
+    let identity =
+        get_identity_from_service(&containerapps_configuration_data, &properties.configuration)?;
     let config = ContainerAppConfig {
         kind: Some("containerapp".to_string()),
         api_version: None,
@@ -26,7 +29,8 @@ pub fn convert_to_containerapps(
         location: containerapps_configuration_data.location.to_owned(),
         resource_type: "Microsoft.Web/containerApps".to_string(),
         tags: None,
-        properties: get_properties(&containerapps_configuration_data, service_name, &service)?,
+        identity,
+        properties,
     };
 
     Ok(config)
@@ -1,9 +1,11 @@
 use crate::commands::ContainerAppsConfigurationData;
 use crate::compose::Service;
-use crate::containerapps::{Configuration, RevisionMode};
-use anyhow::Result;
+use crate::containerapps::{Configuration, RevisionMode, TrafficConfiguration};
+use anyhow::{anyhow, Result};
+use log::debug;
 
 use super::get_ingress_from_service;
+use super::get_registries_from_service;
 use super::get_secrets_from_service;
 use crate::VERBOSE;
 
@@ -12,31 +14,70 @@ pub fn get_configuration_from_service(
     service: &Service,
 ) -> Result<Configuration> {
     if *VERBOSE {
-        println!();
-        println!("The ContainerApps container configuration is defined https://aka.ms/containerapps/spec#propertiesconfiguration.");
-        println!("This configuration includes any secrets for the environment, any container registries, and the ingress.");
-        println!("Details on configuring a container registries is located at https://aka.ms/containerapps/containers#container-registries.");
-        println!("An environment can include more than one container, like a pod in Kubernetes.");
-        println!("activeRevisionsMode is also defined here.  Revisions can be used to control traffic flow.");
-        println!("Here we are defaulting to the latest revision, as that's the experience you would have in Docker Compose.");
-        println!(
+        eprintln!();
+        eprintln!("The ContainerApps container configuration is defined https://aka.ms/containerapps/spec#propertiesconfiguration.");
+        eprintln!("This configuration includes any secrets for the environment, any container registries, and the ingress.");
+        eprintln!("Details on configuring a container registries is located at https://aka.ms/containerapps/containers#container-registries.");
+        eprintln!("An environment can include more than one container, like a pod in Kubernetes.");
+        eprintln!("activeRevisionsMode is also defined here.  Revisions can be used to control traffic flow.");
+        eprintln!("Here we are defaulting to the latest revision, as that's the experience you would have in Docker Compose.");
+        eprintln!(
             "You can learn more about revisions at https://aka.ms/containerapps/revisiondetail."
         );
-        println!();
+        eprintln!();
     }
+    let active_revisions_mode = match &containerapps_configuration_data.extension.revision_mode {
+        Some(revision_mode) => revision_mode.clone(),
+        None => containerapps_configuration_data.revision_mode.clone(),
+    };
+    debug!("Setting the revision mode to {}.", &active_revisions_mode);
     let config = Configuration {
-        secrets: get_secrets_from_service(service)?,
-        ingress: get_ingress_from_service(containerapps_configuration_data, service)?,
-        active_revisions_mode: RevisionMode::default(),
-        registries: Vec::new(),
+        secrets: get_secrets_from_service(containerapps_configuration_data, service)?,
+        ingress: Some(get_ingress_from_service(
+            containerapps_configuration_data,
+            service,
+        )?),
+        active_revisions_mode,
+        registries: get_registries_from_service(containerapps_configuration_data, service)?,
     };
+    if let Some(ingress) = config.ingress.as_ref() {
+        validate_traffic(&config.active_revisions_mode, &ingress.traffic)?;
+    }
     Ok(config)
 }
 
+fn validate_traffic(revision_mode: &RevisionMode, traffic: &[TrafficConfiguration]) -> Result<()> {
+    if traffic.is_empty() {
+        return Ok(());
+    }
+    for entry in traffic {
+        if entry.latest_revision.unwrap_or(false) == entry.revision_name.is_some() {
+            return Err(anyhow!(
+                "Each traffic entry needs either the latest revision or a revision name."
+            ));
+        }
+    }
+    if *revision_mode == RevisionMode::Single && traffic.iter().any(|t| t.revision_name.is_some()) {
+        return Err(anyhow!(
+            "Splitting traffic across named revisions requires the multiple revision mode."
+        ));
+    }
+    let total: u32 = traffic.iter().map(|t| t.weight.unwrap_or(0)).sum();
+    if total != 100 {
+        return Err(anyhow!(
+            "Traffic weights must add up to 100, but they add up to {}.",
+            total
+        ));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::containerapps::RevisionMode;
+    use super::validate_traffic;
+    use crate::containerapps::{RevisionMode, TrafficConfiguration};
     use crate::convert::tests::get_converted_containerapps_config;
+    use std::str::FromStr;
 
     #[test]
     fn conversion_defaults_properties_configuration_active_revision_mode_to_single() {
@@ -50,4 +91,25 @@ mod tests {
             RevisionMode::Single
         );
     }
+
+    #[test]
+    fn traffic_weights_must_add_up_to_100() {
+        let traffic = vec![
+            TrafficConfiguration::from_str("latest=20").unwrap(),
+            TrafficConfiguration::from_str("mycontainerapp--blue=70").unwrap(),
+        ];
+
+        assert!(validate_traffic(&RevisionMode::Multiple, &traffic).is_err());
+    }
+
+    #[test]
+    fn traffic_split_across_revisions_requires_multiple_revision_mode() {
+        let traffic = vec![
+            TrafficConfiguration::from_str("latest=20").unwrap(),
+            TrafficConfiguration::from_str("mycontainerapp--blue=80").unwrap(),
+        ];
+
+        assert!(validate_traffic(&RevisionMode::Single, &traffic).is_err());
+        assert!(validate_traffic(&RevisionMode::Multiple, &traffic).is_ok());
+    }
 }
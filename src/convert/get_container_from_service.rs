@@ -1,16 +1,23 @@
+use crate::commands::ContainerAppsConfigurationData;
 use crate::compose::Service;
 use crate::containerapps::{Container, EnvironmentConfiguration};
 use crate::VERBOSE;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use dialoguer::Input;
 use log::debug;
 
-pub fn get_container_from_service(service_name: &str, service: &Service) -> Result<Container> {
+use super::get_secret_references_from_service;
+
+pub fn get_container_from_service(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+    service_name: &str,
+    service: &Service,
+) -> Result<Container> {
     if *VERBOSE {
-        println!();
-        println!("The container template includes the container image, an optional name,");
-        println!("as well as environment variables. All the options are defined https://aka.ms/containerapps/containers#configuration.");
-        println!();
+        eprintln!();
+        eprintln!("The container template includes the container image, an optional name,");
+        eprintln!("as well as environment variables. All the options are defined https://aka.ms/containerapps/containers#configuration.");
+        eprintln!();
     };
     let mut container = Container::default();
     if let Some(image) = &service.image {
@@ -25,15 +32,36 @@ pub fn get_container_from_service(service_name: &str, service: &Service) -> Resu
 
     if !service.environment.is_empty() {
         debug!("Resolving environment variables for the container configuration.");
+        let secret_references =
+            get_secret_references_from_service(containerapps_configuration_data, service)?;
         for (key, mut wrapped_value) in service.environment.clone().into_iter() {
+            if let Some(secret_name) = secret_references.get(&key) {
+                debug!(
+                    "Environment variable {} is read from the secret {}",
+                    &key, secret_name
+                );
+                container.env.push(EnvironmentConfiguration {
+                    name: key,
+                    value: None,
+                    secret_ref: Some(secret_name.to_owned()),
+                });
+                continue;
+            }
             let new_value = match wrapped_value.interpolate() {
                 Ok(v) => {
                     debug!("Resolved environment variable for {} to {}", &key, v);
                     Some(v.to_string())
                 }
+                _ if containerapps_configuration_data.no_prompts => {
+                    return Err(anyhow!(
+                        "Unable to resolve the variable reference {} for {}; set it in the environment, the Compose file is read from stdin so it can't be prompted for.",
+                        &wrapped_value,
+                        &key
+                    ));
+                }
                 _ => {
                     debug!("Failed to interpolate the environment variable {}", &key);
-                    println!(
+                    eprintln!(
                         "Unable to resolve the variable reference for {}",
                         &wrapped_value
                     );
@@ -0,0 +1,224 @@
+use crate::commands::ContainerAppsConfigurationData;
+use crate::compose::interpolate_extension_value;
+use crate::containerapps::{BindingType, Certificate, CertificateProperties, CustomDomain};
+use crate::VERBOSE;
+use anyhow::{anyhow, Result};
+use log::{debug, trace};
+use serde::Deserialize;
+use std::env;
+use std::fs::read;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct CustomDomainExtension {
+    pub name: String,
+    pub certificate: Option<PathBuf>,
+    #[serde(rename = "certificatePassword")]
+    pub certificate_password: Option<String>,
+    #[serde(rename = "bindingType")]
+    pub binding_type: Option<BindingType>,
+}
+
+pub fn get_custom_domains_from_service(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+) -> Result<Vec<CustomDomain>> {
+    let domains = get_domain_bindings(containerapps_configuration_data);
+    if *VERBOSE && !domains.is_empty() {
+        eprintln!();
+        eprintln!("Custom domains are bound to the ingress with a certificate uploaded to the ContainerApps environment.");
+        eprintln!("Create the CNAME and TXT records for the domain before deploying.  More at https://aka.ms/containerapps/custom-domains.");
+        eprintln!();
+    }
+    let mut custom_domains = Vec::new();
+    for domain in domains {
+        let certificate_id = match &domain.certificate {
+            Some(path) => Some(format!(
+                "{}/certificates/{}",
+                containerapps_configuration_data
+                    .containerapps_environment_id
+                    .trim_end_matches('/'),
+                get_certificate_name(&containerapps_configuration_data.project_directory, path)?
+            )),
+            None => None,
+        };
+        let binding_type = match (&domain.binding_type, &certificate_id) {
+            (Some(binding_type), _) => binding_type.clone(),
+            (None, Some(_)) => BindingType::SniEnabled,
+            (None, None) => BindingType::Disabled,
+        };
+        if binding_type == BindingType::SniEnabled && certificate_id.is_none() {
+            return Err(anyhow!(
+                "The custom domain {} needs a certificate to enable SNI binding.",
+                &domain.name
+            ));
+        }
+        debug!("Binding the custom domain {}", &domain.name);
+        custom_domains.push(CustomDomain {
+            name: domain.name,
+            binding_type,
+            certificate_id,
+        });
+    }
+    Ok(custom_domains)
+}
+
+pub fn get_certificates_from_service(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+) -> Result<Vec<Certificate>> {
+    let environment_name = containerapps_configuration_data
+        .containerapps_environment_id
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    let mut certificates: Vec<Certificate> = Vec::new();
+    for domain in get_domain_bindings(containerapps_configuration_data) {
+        let path = match &domain.certificate {
+            Some(p) => p,
+            None => continue,
+        };
+        let name = format!(
+            "{}/{}",
+            environment_name,
+            get_certificate_name(&containerapps_configuration_data.project_directory, path)?
+        );
+        if certificates.iter().any(|c| c.name == name) {
+            continue;
+        }
+        trace!("Reading the certificate {}", path.display());
+        let pfx = read(path)
+            .map_err(|e| anyhow!("Unable to read the certificate {}: {}", path.display(), e))?;
+        certificates.push(Certificate {
+            location: containerapps_configuration_data.location.to_owned(),
+            name,
+            properties: CertificateProperties {
+                value: base64::encode(pfx),
+                password: domain.certificate_password.unwrap_or_default(),
+            },
+            ..Certificate::default()
+        });
+    }
+    Ok(certificates)
+}
+
+fn get_domain_bindings(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+) -> Vec<CustomDomainExtension> {
+    let mut domains: Vec<CustomDomainExtension> = containerapps_configuration_data
+        .extension
+        .ingress
+        .custom_domains
+        .iter()
+        .cloned()
+        .map(|mut domain| {
+            domain.certificate_password = domain
+                .certificate_password
+                .map(|p| interpolate_extension_value(&p))
+                .or_else(|| {
+                    containerapps_configuration_data
+                        .certificate_password
+                        .map(|p| p.to_owned())
+                });
+            domain
+        })
+        .collect();
+    for host in containerapps_configuration_data.domains.iter() {
+        if domains.iter().any(|d| &d.name == host) {
+            continue;
+        }
+        domains.push(CustomDomainExtension {
+            name: host.to_owned(),
+            certificate: containerapps_configuration_data
+                .certificate
+                .map(PathBuf::from),
+            certificate_password: containerapps_configuration_data
+                .certificate_password
+                .map(|p| p.to_owned()),
+            binding_type: None,
+        });
+    }
+    domains
+}
+
+/// Certificate names come from the PFX file path, relative to the Compose
+/// project directory when possible, lower cased with anything but letters,
+/// numbers and '-' removed.  Certificates with the same file name in different
+/// directories get different names, and a certificate gets the same name
+/// wherever the project is converted from.
+pub fn get_certificate_name(project_directory: &Path, path: &Path) -> Result<String> {
+    let full_path = get_full_path(path);
+    let path = full_path
+        .strip_prefix(get_full_path(project_directory))
+        .unwrap_or(path)
+        .with_extension("");
+    let segments: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(segment) => Some(segment.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect();
+    let mut name = String::new();
+    for c in segments.join("-").chars() {
+        let c = if c.is_ascii_alphanumeric() { c } else { '-' };
+        if c != '-' || !(name.is_empty() || name.ends_with('-')) {
+            name.push(c);
+        }
+    }
+    let name = name.trim_end_matches('-').to_string();
+    if name.is_empty() {
+        return Err(anyhow!(
+            "Unable to name a certificate after {}",
+            path.display()
+        ));
+    }
+    Ok(name)
+}
+
+fn get_full_path(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .unwrap_or_else(|_| env::current_dir().unwrap_or_default().join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_domain_references_the_environment_certificate() {
+        let cacd = ContainerAppsConfigurationData {
+            containerapps_environment_id: "/subscriptions/mysubscription/resourceGroups/myresourcegroup/providers/Microsoft.Web/kubeEnvironments/myenvironment",
+            domains: vec!["www.contoso.com".to_string()],
+            certificate: Some("./certs/Contoso_2022.pfx"),
+            ..Default::default()
+        };
+        let custom_domains = get_custom_domains_from_service(&cacd).unwrap();
+
+        assert_eq!(custom_domains[0].name, "www.contoso.com");
+        assert_eq!(custom_domains[0].binding_type, BindingType::SniEnabled);
+        assert_eq!(
+            custom_domains[0].certificate_id,
+            Some("/subscriptions/mysubscription/resourceGroups/myresourcegroup/providers/Microsoft.Web/kubeEnvironments/myenvironment/certificates/certs-contoso-2022".to_string())
+        );
+        assert_ne!(
+            get_certificate_name(Path::new("."), Path::new("prod/site.pfx")).unwrap(),
+            get_certificate_name(Path::new("."), Path::new("dev/site.pfx")).unwrap()
+        );
+        assert_eq!(
+            get_certificate_name(Path::new("app"), Path::new("app/certs/site.pfx")).unwrap(),
+            "certs-site"
+        );
+    }
+
+    #[test]
+    fn custom_domain_without_a_certificate_is_not_bound() {
+        let cacd = ContainerAppsConfigurationData {
+            domains: vec!["www.contoso.com".to_string()],
+            ..Default::default()
+        };
+        let custom_domains = get_custom_domains_from_service(&cacd).unwrap();
+
+        assert_eq!(custom_domains[0].binding_type, BindingType::Disabled);
+        assert!(custom_domains[0].certificate_id.is_none());
+    }
+}
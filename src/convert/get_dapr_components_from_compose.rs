@@ -0,0 +1,354 @@
+use crate::compose::{interpolate_extension_value, ComposeDocument};
+use crate::containerapps::{
+    DaprComponent, DaprComponentProperties, DaprMetadata, SecretsConfiguration,
+};
+use crate::VERBOSE;
+use anyhow::{anyhow, Result};
+use log::{debug, trace, warn};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::env;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+const DAPR_COMPONENTS_EXTENSION: &str = "x-dapr-components";
+
+#[derive(Debug, Deserialize)]
+struct DaprComponentDefinition {
+    name: String,
+    #[serde(rename = "type")]
+    component_type: String,
+    #[serde(default = "default_component_version")]
+    version: String,
+    #[serde(rename = "ignoreErrors", default)]
+    ignore_errors: bool,
+    #[serde(rename = "initTimeout")]
+    init_timeout: Option<String>,
+    #[serde(default)]
+    metadata: Vec<DaprMetadataDefinition>,
+    #[serde(default)]
+    secrets: Vec<SecretsConfiguration>,
+    #[serde(default)]
+    scopes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DaprMetadataDefinition {
+    name: String,
+    value: Option<Value>,
+    #[serde(rename = "secretRef")]
+    secret_ref: Option<String>,
+    #[serde(rename = "secretKeyRef")]
+    secret_key_ref: Option<DaprSecretKeyRef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DaprSecretKeyRef {
+    name: String,
+    key: Option<String>,
+}
+impl DaprSecretKeyRef {
+    /// ContainerApps secrets hold one value, so a key within a multi-valued
+    /// secret becomes its own secret, `name-key`.
+    fn secret_name(&self) -> String {
+        match self.key.as_deref() {
+            Some(key) if key != self.name => {
+                let name: String = format!("{}-{}", &self.name, key)
+                    .to_lowercase()
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                    .collect();
+                name
+            }
+            _ => self.name.to_owned(),
+        }
+    }
+}
+
+fn default_component_version() -> String {
+    "v1".to_string()
+}
+
+pub fn get_dapr_components_from_compose(
+    containerapps_environment_id: &str,
+    compose_document: &ComposeDocument,
+    service_names: &[String],
+) -> Result<Vec<DaprComponent>> {
+    trace!("Looking for Dapr components in the Compose file.");
+    let mut definitions = Vec::new();
+
+    if let Some(extension) = compose_document.extension(DAPR_COMPONENTS_EXTENSION) {
+        debug!("Found the {} extension.", DAPR_COMPONENTS_EXTENSION);
+        let components: Vec<Value> = serde_yaml::from_value(extension.clone())?;
+        for component in components {
+            definitions.push(get_component_definition(component)?);
+        }
+    }
+
+    for service_name in service_names {
+        if !is_daprd_service(compose_document, service_name) {
+            continue;
+        }
+        for source in compose_document.service_volume_sources(service_name) {
+            for component in read_mounted_components(&source)? {
+                if definitions.iter().any(|d| d.name == component.name) {
+                    debug!(
+                        "Dapr component {} is already defined, skipping the copy mounted into {}.",
+                        &component.name, service_name
+                    );
+                    continue;
+                }
+                definitions.push(component);
+            }
+        }
+    }
+
+    if *VERBOSE && !definitions.is_empty() {
+        eprintln!();
+        eprintln!("Dapr components are defined once for the ContainerApps environment and shared by the apps listed in their scopes.");
+        eprintln!("You can read more about Dapr components at https://aka.ms/containerapps/dapr.");
+        eprintln!();
+    }
+
+    let environment_name = get_environment_name(containerapps_environment_id)?;
+    Ok(definitions
+        .into_iter()
+        .map(|definition| get_dapr_component(environment_name, definition))
+        .collect())
+}
+
+fn get_environment_name(containerapps_environment_id: &str) -> Result<&str> {
+    match containerapps_environment_id
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+    {
+        Some(name) if !name.is_empty() => Ok(name),
+        _ => Err(anyhow!(
+            "Unable to determine the ContainerApps environment name from {}",
+            containerapps_environment_id
+        )),
+    }
+}
+
+fn get_component_definition(component: Value) -> Result<DaprComponentDefinition> {
+    if component.get("kind").and_then(Value::as_str) != Some("Component") {
+        return Ok(serde_yaml::from_value(component)?);
+    }
+
+    let name = component
+        .get("metadata")
+        .and_then(|m| m.get("name"))
+        .cloned()
+        .ok_or_else(|| anyhow!("Dapr component is missing metadata.name"))?;
+    let mut definition = match component.get("spec") {
+        Some(Value::Mapping(spec)) => spec.clone(),
+        _ => return Err(anyhow!("Dapr component {:?} is missing a spec", name)),
+    };
+    definition.insert(Value::from("name"), name);
+    if let Some(scopes) = component.get("scopes") {
+        definition.insert(Value::from("scopes"), scopes.clone());
+    }
+    Ok(serde_yaml::from_value(Value::Mapping(definition))?)
+}
+
+/// Whether the service runs the Dapr sidecar, which ContainerApps provides.
+pub fn is_daprd_service(compose_document: &ComposeDocument, service_name: &str) -> bool {
+    let image = compose_document
+        .service(service_name)
+        .and_then(|s| s.get("image"))
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    image.contains("daprd")
+        || compose_document
+            .service_command(service_name)
+            .iter()
+            .any(|arg| arg.ends_with("daprd"))
+}
+
+fn read_mounted_components(source: &Path) -> Result<Vec<DaprComponentDefinition>> {
+    let files: Vec<PathBuf> = if source.is_dir() {
+        read_dir(source)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .collect()
+    } else if source.is_file() {
+        vec![source.to_path_buf()]
+    } else {
+        debug!("Mounted path {} does not exist locally.", source.display());
+        Vec::new()
+    };
+
+    let mut components = Vec::new();
+    for file in files {
+        match file.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => {}
+            _ => continue,
+        }
+        let content = read_to_string(&file)?;
+        for document in serde_yaml::Deserializer::from_str(&content) {
+            let value = match Value::deserialize(document) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Skipping {}, it is not valid YAML: {}", file.display(), e);
+                    continue;
+                }
+            };
+            if value.get("kind").and_then(Value::as_str) == Some("Component") {
+                debug!("Found a Dapr component in {}", file.display());
+                components.push(get_component_definition(value)?);
+            }
+        }
+    }
+    Ok(components)
+}
+
+fn get_dapr_component(
+    environment_name: &str,
+    definition: DaprComponentDefinition,
+) -> DaprComponent {
+    let mut secrets: Vec<SecretsConfiguration> = definition
+        .secrets
+        .into_iter()
+        .map(|secret| SecretsConfiguration {
+            value: secret.value.map(|v| interpolate_extension_value(&v)),
+            ..secret
+        })
+        .collect();
+
+    let metadata: Vec<DaprMetadata> = definition
+        .metadata
+        .into_iter()
+        .map(|m| DaprMetadata {
+            name: m.name,
+            value: m.value.as_ref().and_then(metadata_value),
+            secret_ref: m
+                .secret_ref
+                .or_else(|| m.secret_key_ref.as_ref().map(|r| r.secret_name())),
+        })
+        .collect();
+
+    for secret_ref in metadata.iter().filter_map(|m| m.secret_ref.as_ref()) {
+        if secrets.iter().any(|s| &s.name == secret_ref) {
+            continue;
+        }
+        let env_var_name = secret_ref.to_uppercase().replace('-', "_");
+        match env::var(&env_var_name) {
+            Ok(value) => {
+                debug!(
+                    "Using {} as the value for the {} secret.",
+                    &env_var_name, secret_ref
+                );
+                secrets.push(SecretsConfiguration {
+                    name: secret_ref.to_owned(),
+                    value: Some(value),
+                    ..SecretsConfiguration::default()
+                });
+            }
+            Err(_) => warn!(
+                "Dapr component {} references the secret {}, but no value was found. Set {} to supply it.",
+                &definition.name, secret_ref, &env_var_name
+            ),
+        }
+    }
+
+    DaprComponent {
+        name: format!("{}/{}", environment_name, &definition.name),
+        properties: DaprComponentProperties {
+            component_type: definition.component_type,
+            version: definition.version,
+            ignore_errors: definition.ignore_errors,
+            init_timeout: definition.init_timeout,
+            secrets,
+            metadata,
+            scopes: definition.scopes,
+        },
+        ..DaprComponent::default()
+    }
+}
+
+fn metadata_value(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(interpolate_extension_value(s)),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compose::read_compose_document;
+    use crate::containerapps::{write_dapr_components_arm_template, ApiVersion};
+
+    fn get_converted_dapr_components() -> Vec<DaprComponent> {
+        let compose_document =
+            read_compose_document(Path::new("test/dapr_components_convert/docker-compose.yml"))
+                .unwrap();
+        let service_names = vec![
+            "app".to_string(),
+            "app-dapr".to_string(),
+            "redis".to_string(),
+        ];
+        get_dapr_components_from_compose(
+            "/subscriptions/mysubscription/resourceGroups/myresourcegroup/providers/Microsoft.Web/kubeEnvironments/myenvironment",
+            &compose_document,
+            &service_names,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn conversion_reads_dapr_components_from_extension() {
+        let components = get_converted_dapr_components();
+        let statestore = components
+            .iter()
+            .find(|c| c.name == "myenvironment/statestore")
+            .unwrap();
+
+        assert_eq!(statestore.properties.component_type, "state.redis");
+        assert_eq!(statestore.properties.scopes, vec!["app".to_string()]);
+        assert_eq!(
+            statestore.properties.metadata[1].secret_ref,
+            Some("redis-password".to_string())
+        );
+    }
+
+    #[test]
+    fn conversion_reads_dapr_components_mounted_into_daprd() {
+        let components = get_converted_dapr_components();
+        let pubsub = components
+            .iter()
+            .find(|c| c.name == "myenvironment/pubsub")
+            .unwrap();
+
+        assert_eq!(pubsub.properties.component_type, "pubsub.redis");
+        assert_eq!(
+            pubsub.properties.metadata[1].secret_ref,
+            Some("redis-password".to_string())
+        );
+    }
+
+    #[test]
+    fn secret_keys_and_values_stay_out_of_the_template() {
+        let key_ref = DaprSecretKeyRef {
+            name: "redis".to_string(),
+            key: Some("password".to_string()),
+        };
+        assert_eq!(key_ref.secret_name(), "redis-password");
+
+        let components = get_converted_dapr_components();
+        let path = env::temp_dir().join(format!(
+            "dapr-components-secrets-test-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let secrets =
+            write_dapr_components_arm_template(&path, &components, ApiVersion::default()).unwrap();
+        let template = read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(secrets.contains_key("statestoreRedisPassword"));
+        assert!(template.contains("[parameters('statestoreRedisPassword')]"));
+        assert!(!template.contains("REDIS_PASSWORD"));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::compose::ComposeDocument;
+use crate::containerapps::{
+    ClientCertificateMode, ContainerRegistry, CorsPolicy, IpSecurityRestriction, RevisionMode,
+    StickySessions, TrafficConfiguration,
+};
+use crate::convert::{CustomDomainExtension, SecretExtension};
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use serde::Deserialize;
+use std::fs::read_to_string;
+
+const CONTAINERAPPS_EXTENSION: &str = "x-containerapps";
+
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct ContainerAppsExtension {
+    #[serde(rename = "revisionMode")]
+    pub revision_mode: Option<RevisionMode>,
+    #[serde(rename = "revisionSuffix")]
+    pub revision_suffix: Option<String>,
+    #[serde(default)]
+    pub traffic: Vec<TrafficConfiguration>,
+    #[serde(default)]
+    pub identity: Vec<String>,
+    #[serde(default)]
+    pub registries: Vec<ContainerRegistry>,
+    #[serde(default)]
+    pub secrets: Vec<SecretExtension>,
+    #[serde(default)]
+    pub ingress: IngressExtension,
+}
+
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct IngressExtension {
+    #[serde(rename = "customDomains", default)]
+    pub custom_domains: Vec<CustomDomainExtension>,
+    #[serde(rename = "ipSecurityRestrictions", default)]
+    pub ip_security_restrictions: Vec<IpSecurityRestriction>,
+    #[serde(rename = "corsPolicy")]
+    pub cors_policy: Option<CorsPolicy>,
+    #[serde(rename = "clientCertificateMode")]
+    pub client_certificate_mode: Option<ClientCertificateMode>,
+    #[serde(rename = "stickySessions")]
+    pub sticky_sessions: Option<StickySessions>,
+}
+
+pub fn get_extension_from_service(
+    compose_document: &ComposeDocument,
+    service_name: &str,
+) -> Result<ContainerAppsExtension> {
+    let mut extension =
+        match compose_document.service_extension(service_name, CONTAINERAPPS_EXTENSION) {
+            Some(extension) => {
+                debug!(
+                    "Found the {} extension for the {} service.",
+                    CONTAINERAPPS_EXTENSION, service_name
+                );
+                let mut extension: ContainerAppsExtension =
+                    serde_yaml::from_value(extension.clone()).map_err(|e| {
+                        anyhow!(
+                            "Invalid {} extension for the {} service: {}",
+                            CONTAINERAPPS_EXTENSION,
+                            service_name,
+                            e
+                        )
+                    })?;
+                for domain in extension.ingress.custom_domains.iter_mut() {
+                    if let Some(certificate) = &domain.certificate {
+                        domain.certificate =
+                            Some(compose_document.resolve_path(&certificate.to_string_lossy()));
+                    }
+                }
+                extension
+            }
+            None => ContainerAppsExtension::default(),
+        };
+    for (name, path) in compose_document.service_secret_files(service_name) {
+        let name = get_secret_name(&name);
+        if extension.secrets.iter().any(|s| s.name == name) {
+            continue;
+        }
+        let value = read_to_string(&path).map_err(|e| {
+            anyhow!(
+                "Unable to read the secret {} of the {} service from {} - {}",
+                name,
+                service_name,
+                path.display(),
+                e
+            )
+        })?;
+        warn!(
+            "The Compose secret {} of the {} service becomes a Container Apps secret; it isn't mounted as a file.",
+            name, service_name
+        );
+        extension.secrets.push(SecretExtension {
+            name,
+            value: Some(value.trim_end_matches(&['\r', '\n'][..]).to_owned()),
+            ..Default::default()
+        });
+    }
+    Ok(extension)
+}
+
+/// Container Apps secret names are lower case alphanumerics and '-'.
+fn get_secret_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
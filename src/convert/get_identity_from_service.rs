@@ -0,0 +1,117 @@
+use crate::commands::ContainerAppsConfigurationData;
+use crate::containerapps::{Configuration, Identity, IdentityType, UserAssignedIdentity};
+use crate::VERBOSE;
+use anyhow::{anyhow, Result};
+use log::debug;
+use std::collections::BTreeMap;
+
+pub const SYSTEM_ASSIGNED_IDENTITY: &str = "system";
+
+pub fn get_identity_from_service(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+    configuration: &Configuration,
+) -> Result<Option<Identity>> {
+    let mut identities = containerapps_configuration_data.identities.clone();
+    identities.extend(
+        containerapps_configuration_data
+            .extension
+            .identity
+            .iter()
+            .cloned(),
+    );
+    identities.extend(
+        configuration
+            .registries
+            .iter()
+            .filter_map(|r| r.identity.clone()),
+    );
+    identities.extend(
+        configuration
+            .secrets
+            .iter()
+            .filter_map(|s| s.identity.clone()),
+    );
+    if identities.is_empty() {
+        return Ok(None);
+    }
+    if *VERBOSE {
+        eprintln!();
+        eprintln!("Managed identities let the ContainerApp pull images and read secrets without storing credentials.");
+        eprintln!(
+            "You can read more about managed identities at https://aka.ms/containerapps/identity."
+        );
+        eprintln!();
+    }
+
+    let mut system_assigned = false;
+    let mut user_assigned_identities = BTreeMap::new();
+    for identity in identities {
+        if identity.eq_ignore_ascii_case(SYSTEM_ASSIGNED_IDENTITY) {
+            system_assigned = true;
+        } else if identity
+            .to_lowercase()
+            .contains("/providers/microsoft.managedidentity/userassignedidentities/")
+        {
+            debug!("Assigning the user assigned identity {}", &identity);
+            user_assigned_identities.insert(identity, UserAssignedIdentity::default());
+        } else {
+            return Err(anyhow!(
+                "{} is not a user assigned identity resource id or '{}'.",
+                identity,
+                SYSTEM_ASSIGNED_IDENTITY
+            ));
+        }
+    }
+
+    let identity_type = match (system_assigned, user_assigned_identities.is_empty()) {
+        (true, true) => IdentityType::SystemAssigned,
+        (true, false) => IdentityType::SystemAssignedUserAssigned,
+        (false, _) => IdentityType::UserAssigned,
+    };
+    Ok(Some(Identity {
+        identity_type,
+        user_assigned_identities,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::containerapps::ContainerRegistry;
+
+    const IDENTITY_ID: &str = "/subscriptions/mysubscription/resourceGroups/myresourcegroup/providers/Microsoft.ManagedIdentity/userAssignedIdentities/myidentity";
+
+    #[test]
+    fn registry_identity_is_assigned_to_the_containerapp() {
+        let cacd = ContainerAppsConfigurationData {
+            identities: vec!["system".to_string()],
+            ..Default::default()
+        };
+        let configuration = Configuration {
+            registries: vec![ContainerRegistry {
+                server: "myregistry.azurecr.io".to_string(),
+                identity: Some(IDENTITY_ID.to_string()),
+                ..ContainerRegistry::default()
+            }],
+            ..Configuration::default()
+        };
+        let identity = get_identity_from_service(&cacd, &configuration)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            identity.identity_type,
+            IdentityType::SystemAssignedUserAssigned
+        );
+        assert!(identity.user_assigned_identities.contains_key(IDENTITY_ID));
+    }
+
+    #[test]
+    fn no_identity_is_set_by_default() {
+        let cacd = ContainerAppsConfigurationData::default();
+
+        assert!(get_identity_from_service(&cacd, &Configuration::default())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,10 +1,16 @@
 use crate::commands::ContainerAppsConfigurationData;
 use crate::compose::{PortMapping, Ports, Protocol, Service};
-use crate::containerapps::IngressConfiguration;
+use crate::containerapps::{
+    IngressConfiguration, IpSecurityRestriction, IpSecurityRestrictionAction, RevisionMode,
+};
 use crate::VERBOSE;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use dialoguer::FuzzySelect;
 use log::{debug, trace, warn};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use super::get_custom_domains_from_service;
 
 pub fn get_ingress_from_service(
     containerapps_configuration_data: &ContainerAppsConfigurationData,
@@ -12,20 +18,31 @@ pub fn get_ingress_from_service(
 ) -> Result<IngressConfiguration> {
     trace!("Creating the ingress configuration.");
     if *VERBOSE {
-        println!();
-        println!("Ingress in ContainerApps exposes port 80 and 443 to the world via an external ingress.");
-        println!("By default external port 80 HTTP traffic is redirected to HTTPS on 443.");
-        println!("You can also expose internal ingresses.  This is how one ContainerApp can talk to another.");
-        println!("Internal ingresses are HTTP/HTTPS only as well - no general TCP or UDP traffic.");
-        println!("You can read more about ingresses at https://aka.ms/containerapps/ingress.");
-        println!(
+        eprintln!();
+        eprintln!("Ingress in ContainerApps exposes port 80 and 443 to the world via an external ingress.");
+        eprintln!("By default external port 80 HTTP traffic is redirected to HTTPS on 443.");
+        eprintln!("You can also expose internal ingresses.  This is how one ContainerApp can talk to another.");
+        eprintln!(
+            "Internal ingresses are HTTP/HTTPS only as well - no general TCP or UDP traffic."
+        );
+        eprintln!("You can read more about ingresses at https://aka.ms/containerapps/ingress.");
+        eprintln!(
             "If you are running multiple public-facing ContainerApps (multiple external ingresses)"
         );
-        println!("make sure you read https://aka.ms/containerapps/ingress#ip-addresses-and-domain-names.");
-        println!();
+        eprintln!("make sure you read https://aka.ms/containerapps/ingress#ip-addresses-and-domain-names.");
+        eprintln!();
     };
     let mut ingress = IngressConfiguration::new();
     ingress.transport = containerapps_configuration_data.transport.clone();
+    ingress.traffic = if containerapps_configuration_data
+        .extension
+        .traffic
+        .is_empty()
+    {
+        containerapps_configuration_data.traffic.clone()
+    } else {
+        containerapps_configuration_data.extension.traffic.clone()
+    };
 
     if !service.ports.is_empty() {
         debug!("Service had ports defined.");
@@ -63,9 +80,71 @@ pub fn get_ingress_from_service(
         }
     };
 
+    ingress.custom_domains = get_custom_domains_from_service(containerapps_configuration_data)?;
+    if !ingress.custom_domains.is_empty() && !ingress.external {
+        warn!("Custom domains are only reachable through an external ingress.");
+    }
+
+    let ingress_extension = &containerapps_configuration_data.extension.ingress;
+    validate_ip_security_restrictions(&ingress_extension.ip_security_restrictions)?;
+    ingress.ip_security_restrictions = ingress_extension.ip_security_restrictions.clone();
+    ingress.cors_policy = ingress_extension.cors_policy.clone();
+    ingress.client_certificate_mode = ingress_extension.client_certificate_mode.clone();
+    ingress.sticky_sessions = ingress_extension.sticky_sessions.clone();
+    if ingress.sticky_sessions.is_some()
+        && containerapps_configuration_data
+            .extension
+            .revision_mode
+            .as_ref()
+            .unwrap_or(&containerapps_configuration_data.revision_mode)
+            != &RevisionMode::Single
+    {
+        warn!("Sticky sessions are only supported with the single revision mode.");
+    }
+
     Ok(ingress)
 }
 
+fn validate_ip_security_restrictions(restrictions: &[IpSecurityRestriction]) -> Result<()> {
+    for restriction in restrictions {
+        let (address, prefix) = match restriction.ip_address_range.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (restriction.ip_address_range.as_str(), None),
+        };
+        let address = IpAddr::from_str(address).map_err(|_| {
+            anyhow!(
+                "IP security restriction {} has an invalid address range {}.",
+                &restriction.name,
+                &restriction.ip_address_range
+            )
+        })?;
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        if let Some(prefix) = prefix {
+            match u8::from_str(prefix) {
+                Ok(p) if p <= max_prefix => {}
+                _ => {
+                    return Err(anyhow!(
+                        "IP security restriction {} has an invalid prefix length in {}.",
+                        &restriction.name,
+                        &restriction.ip_address_range
+                    ))
+                }
+            }
+        }
+    }
+    if let Some(first) = restrictions.first() {
+        if restrictions.iter().any(|r| r.action != first.action) {
+            return Err(anyhow!(
+                "IP security restrictions must all allow or all deny traffic, they cannot be mixed."
+            ));
+        }
+        if first.action == IpSecurityRestrictionAction::Allow {
+            debug!("Only the listed IP address ranges will be able to reach the ingress.");
+        }
+    }
+    Ok(())
+}
+
 fn expand_service_expose(service: &Service) -> Vec<u16> {
     let expose_ports_local = service.expose.clone();
     expose_ports_local
@@ -120,7 +199,8 @@ fn expand_service_ports(service: &Service) -> Vec<u16> {
 
 #[cfg(test)]
 mod tests {
-    use crate::containerapps::Transport;
+    use super::validate_ip_security_restrictions;
+    use crate::containerapps::{IpSecurityRestriction, IpSecurityRestrictionAction, Transport};
     use crate::convert::tests::{get_converted_containerapps_config, read_containerapps_file};
 
     #[test]
@@ -132,6 +212,7 @@ mod tests {
                 .properties
                 .configuration
                 .ingress
+                .unwrap()
                 .transport,
             Transport::Auto
         );
@@ -147,12 +228,50 @@ mod tests {
                 .properties
                 .configuration
                 .ingress
+                .unwrap()
                 .target_port,
             reference_containerapps_config
                 .properties
                 .configuration
                 .ingress
+                .unwrap()
                 .target_port
         );
     }
+
+    fn get_restriction(
+        ip_address_range: &str,
+        action: IpSecurityRestrictionAction,
+    ) -> IpSecurityRestriction {
+        IpSecurityRestriction {
+            name: "office".to_string(),
+            description: None,
+            ip_address_range: ip_address_range.to_string(),
+            action,
+        }
+    }
+
+    #[test]
+    fn ip_security_restrictions_require_valid_ranges() {
+        let valid = vec![
+            get_restriction("192.168.1.0/24", IpSecurityRestrictionAction::Allow),
+            get_restriction("10.0.0.1", IpSecurityRestrictionAction::Allow),
+        ];
+        assert!(validate_ip_security_restrictions(&valid).is_ok());
+
+        let invalid = vec![get_restriction(
+            "192.168.1.0/33",
+            IpSecurityRestrictionAction::Allow,
+        )];
+        assert!(validate_ip_security_restrictions(&invalid).is_err());
+    }
+
+    #[test]
+    fn ip_security_restrictions_cannot_mix_allow_and_deny() {
+        let restrictions = vec![
+            get_restriction("192.168.1.0/24", IpSecurityRestrictionAction::Allow),
+            get_restriction("10.0.0.0/8", IpSecurityRestrictionAction::Deny),
+        ];
+        assert!(validate_ip_security_restrictions(&restrictions).is_err());
+    }
 }
@@ -13,17 +13,21 @@ pub fn get_properties(
     service: &Service,
 ) -> Result<Properties> {
     if *VERBOSE {
-        println!();
-        println!("The properties that for the ContainerApps configuration are defined at https://aka.ms/containerapps/spec#properties.");
-        println!("kubeEnvironmentId is the Resource ID for the ContainerApps environment.  More at https://aka.ms/containerapps/environment.");
-        println!();
+        eprintln!();
+        eprintln!("The properties that for the ContainerApps configuration are defined at https://aka.ms/containerapps/spec#properties.");
+        eprintln!("kubeEnvironmentId is the Resource ID for the ContainerApps environment.  More at https://aka.ms/containerapps/environment.");
+        eprintln!();
     };
     let props = Properties {
         kube_environment_id: containerapps_configuration_data
             .containerapps_environment_id
             .to_owned(),
         configuration: get_configuration_from_service(containerapps_configuration_data, service)?,
-        template: get_template_from_service(service_name, service)?,
+        template: get_template_from_service(
+            containerapps_configuration_data,
+            service_name,
+            service,
+        )?,
     };
     Ok(props)
 }
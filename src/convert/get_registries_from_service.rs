@@ -0,0 +1,47 @@
+use crate::commands::ContainerAppsConfigurationData;
+use crate::compose::Service;
+use crate::containerapps::ContainerRegistry;
+use anyhow::{anyhow, Result};
+use log::debug;
+
+pub fn get_registries_from_service(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+    service: &Service,
+) -> Result<Vec<ContainerRegistry>> {
+    let mut registries = containerapps_configuration_data
+        .extension
+        .registries
+        .clone();
+
+    if let Some(server) = containerapps_configuration_data.registry_server {
+        let image = match &service.image {
+            Some(image) => image.value()?.to_string(),
+            None => String::new(),
+        };
+        if image.starts_with(&format!("{}/", server))
+            && !registries.iter().any(|r| r.server == server)
+        {
+            debug!(
+                "Pulling {} from {} with a managed identity.",
+                &image, server
+            );
+            registries.push(ContainerRegistry {
+                server: server.to_owned(),
+                identity: containerapps_configuration_data
+                    .registry_identity
+                    .map(|i| i.to_owned()),
+                ..ContainerRegistry::default()
+            });
+        }
+    }
+
+    for registry in registries.iter() {
+        if registry.identity.is_none() && registry.username.is_none() {
+            return Err(anyhow!(
+                "The registry {} needs either an identity or a username and password secret.",
+                &registry.server
+            ));
+        }
+    }
+    Ok(registries)
+}
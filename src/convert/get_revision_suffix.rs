@@ -0,0 +1,130 @@
+use crate::containerapps::Template;
+use anyhow::{anyhow, Result};
+use duct::cmd;
+use log::{debug, trace};
+
+const MAX_REVISION_NAME_LENGTH: usize = 64;
+const SHORT_HASH_LENGTH: usize = 7;
+
+/// Expands a revision suffix template.  `{service}` is replaced with the service
+/// name, `{hash}` with a short hash of the rendered template, and `{git}` with the
+/// current git commit.  The result is sanitized to the revision suffix rules.
+pub fn get_revision_suffix(
+    suffix_template: &str,
+    service_name: &str,
+    template: &Template,
+) -> Result<String> {
+    trace!("Expanding the revision suffix {}", suffix_template);
+    let mut suffix = suffix_template.replace("{service}", service_name);
+    if suffix.contains("{hash}") {
+        suffix = suffix.replace("{hash}", &get_template_hash(template)?);
+    }
+    if suffix.contains("{git}") {
+        suffix = suffix.replace("{git}", &get_git_commit()?);
+    }
+
+    let max_length = MAX_REVISION_NAME_LENGTH.saturating_sub(service_name.len() + 2);
+    let sanitized = sanitize_revision_suffix(&suffix, max_length);
+    if sanitized.is_empty() {
+        return Err(anyhow!(
+            "The revision suffix {} has no usable characters.",
+            suffix_template
+        ));
+    }
+    debug!("Revision suffix for {} is {}", service_name, &sanitized);
+    Ok(sanitized)
+}
+
+fn get_template_hash(template: &Template) -> Result<String> {
+    let mut template = template.clone();
+    template.revision_suffix = None;
+    let rendered = serde_json::to_string(&template)?;
+
+    // FNV-1a keeps the hash stable across platforms and toolchains.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in rendered.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    Ok(format!("{:016x}", hash)[..SHORT_HASH_LENGTH].to_string())
+}
+
+fn get_git_commit() -> Result<String> {
+    let output = cmd!("git", "rev-parse", "--short=7", "HEAD")
+        .stdout_capture()
+        .stderr_null()
+        .unchecked()
+        .run()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Unable to read the current git commit for the revision suffix."
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// Revision suffixes are lower case alphanumeric characters or '-', start with a
+/// letter, end with an alphanumeric character and cannot contain '--'.
+fn sanitize_revision_suffix(suffix: &str, max_length: usize) -> String {
+    let mut sanitized = String::new();
+    for c in suffix.to_lowercase().chars() {
+        let c = if c.is_ascii_alphanumeric() { c } else { '-' };
+        if c == '-' && (sanitized.is_empty() || sanitized.ends_with('-')) {
+            continue;
+        }
+        if sanitized.is_empty() && !c.is_ascii_alphabetic() {
+            sanitized.push('r');
+        }
+        sanitized.push(c);
+    }
+    sanitized.truncate(max_length);
+    sanitized.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::containerapps::Container;
+
+    fn get_template() -> Template {
+        Template {
+            containers: vec![Container {
+                image: "ghost:latest".to_string(),
+                name: "ghost".to_string(),
+                ..Container::default()
+            }],
+            ..Template::default()
+        }
+    }
+
+    #[test]
+    fn revision_suffix_is_sanitized() {
+        assert_eq!(
+            sanitize_revision_suffix("Release_1.2--Beta-", 64),
+            "release-1-2-beta"
+        );
+        assert_eq!(sanitize_revision_suffix("2021", 64), "r2021");
+    }
+
+    #[test]
+    fn revision_suffix_from_hash_is_deterministic() {
+        let template = get_template();
+        let first = get_revision_suffix("{service}-{hash}", "ghost", &template).unwrap();
+        let second = get_revision_suffix("{service}-{hash}", "ghost", &template).unwrap();
+
+        assert_eq!(first, second);
+        assert!(first.starts_with("ghost-"));
+    }
+
+    #[test]
+    fn revision_suffix_from_hash_changes_with_the_template() {
+        let template = get_template();
+        let mut changed_template = get_template();
+        changed_template.containers[0].image = "ghost:4".to_string();
+
+        assert_ne!(
+            get_revision_suffix("{hash}", "ghost", &template).unwrap(),
+            get_revision_suffix("{hash}", "ghost", &changed_template).unwrap()
+        );
+    }
+}
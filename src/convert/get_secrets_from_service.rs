@@ -1,8 +1,152 @@
-use crate::compose::Service;
+use crate::commands::ContainerAppsConfigurationData;
+use crate::compose::{interpolate_extension_value, Service};
 use crate::containerapps::SecretsConfiguration;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 
-pub fn get_secrets_from_service(_compose_file: &Service) -> Result<Vec<SecretsConfiguration>> {
-    let secrets = Vec::new();
+use super::SYSTEM_ASSIGNED_IDENTITY;
+
+#[derive(Clone, Default, Debug, Deserialize)]
+pub struct SecretExtension {
+    pub name: String,
+    pub value: Option<String>,
+    #[serde(rename = "keyVaultUrl")]
+    pub key_vault_url: Option<String>,
+    pub identity: Option<String>,
+    pub env: Option<String>,
+}
+
+pub fn get_secrets_from_service(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+    service: &Service,
+) -> Result<Vec<SecretsConfiguration>> {
+    let secrets = get_secret_extensions(containerapps_configuration_data, service)?
+        .into_iter()
+        .map(|secret| SecretsConfiguration {
+            name: secret.name,
+            value: secret.value.map(|v| interpolate_extension_value(&v)),
+            key_vault_url: secret.key_vault_url,
+            identity: secret.identity,
+        })
+        .collect();
+    Ok(secrets)
+}
+
+/// Maps environment variable names to the secret that supplies their value.
+pub fn get_secret_references_from_service(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+    service: &Service,
+) -> Result<BTreeMap<String, String>> {
+    let references = get_secret_extensions(containerapps_configuration_data, service)?
+        .into_iter()
+        .filter_map(|secret| secret.env.map(|env| (env, secret.name)))
+        .collect();
+    Ok(references)
+}
+
+fn get_secret_extensions(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+    service: &Service,
+) -> Result<Vec<SecretExtension>> {
+    let default_identity = get_default_identity(containerapps_configuration_data);
+    let mut secrets = Vec::new();
+
+    for secret in containerapps_configuration_data.extension.secrets.iter() {
+        let mut secret = secret.clone();
+        if secret.key_vault_url.is_some() {
+            if secret.value.is_some() {
+                return Err(anyhow!(
+                    "The secret {} can have either a value or a keyVaultUrl, not both.",
+                    &secret.name
+                ));
+            }
+            if secret.identity.is_none() {
+                secret.identity = Some(default_identity.clone());
+            }
+        } else if secret.value.is_none() {
+            return Err(anyhow!(
+                "The secret {} needs either a value or a keyVaultUrl.",
+                &secret.name
+            ));
+        }
+        secrets.push(secret);
+    }
+
+    for (env_name, key_vault_url) in containerapps_configuration_data.key_vault_secrets.iter() {
+        if !service.environment.contains_key(env_name)
+            || secrets.iter().any(|s| s.env.as_ref() == Some(env_name))
+        {
+            continue;
+        }
+        let name = env_name.to_lowercase().replace('_', "-");
+        debug!(
+            "Reading {} from the Key Vault secret {} as {}",
+            env_name, key_vault_url, &name
+        );
+        secrets.push(SecretExtension {
+            name,
+            value: None,
+            key_vault_url: Some(key_vault_url.to_owned()),
+            identity: Some(default_identity.clone()),
+            env: Some(env_name.to_owned()),
+        });
+    }
     Ok(secrets)
 }
+
+/// Key Vault references use the first user assigned identity given for the
+/// service, or the system assigned identity when there is none.
+fn get_default_identity(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+) -> String {
+    containerapps_configuration_data
+        .identities
+        .iter()
+        .chain(containerapps_configuration_data.extension.identity.iter())
+        .find(|i| !i.eq_ignore_ascii_case(SYSTEM_ASSIGNED_IDENTITY))
+        .cloned()
+        .unwrap_or_else(|| SYSTEM_ASSIGNED_IDENTITY.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::tests::get_service_from_docker_compose_file;
+
+    #[test]
+    fn key_vault_mapping_creates_a_secret_reference() {
+        let cacd = ContainerAppsConfigurationData {
+            key_vault_secrets: vec![(
+                "NODE_ENV".to_string(),
+                "https://myvault.vault.azure.net/secrets/node-env".to_string(),
+            )],
+            ..Default::default()
+        };
+        let service = get_service_from_docker_compose_file();
+        let secrets = get_secrets_from_service(&cacd, &service).unwrap();
+        let references = get_secret_references_from_service(&cacd, &service).unwrap();
+
+        assert_eq!(secrets[0].name, "node-env");
+        assert!(secrets[0].value.is_none());
+        assert_eq!(secrets[0].identity, Some("system".to_string()));
+        assert_eq!(references.get("NODE_ENV"), Some(&"node-env".to_string()));
+    }
+
+    #[test]
+    fn key_vault_mapping_skips_services_without_the_variable() {
+        let cacd = ContainerAppsConfigurationData {
+            key_vault_secrets: vec![(
+                "DATABASE_PASSWORD".to_string(),
+                "https://myvault.vault.azure.net/secrets/database-password".to_string(),
+            )],
+            ..Default::default()
+        };
+        let service = get_service_from_docker_compose_file();
+
+        assert!(get_secrets_from_service(&cacd, &service)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -1,25 +1,45 @@
+use crate::commands::ContainerAppsConfigurationData;
 use crate::compose::Service;
 use crate::containerapps::{ScaleConfiguration, Template};
 use crate::VERBOSE;
 use anyhow::Result;
 
-use super::get_container_from_service;
+use super::{get_container_from_service, get_revision_suffix};
 
-pub fn get_template_from_service(service_name: &str, service: &Service) -> Result<Template> {
+pub fn get_template_from_service(
+    containerapps_configuration_data: &ContainerAppsConfigurationData,
+    service_name: &str,
+    service: &Service,
+) -> Result<Template> {
     if *VERBOSE {
-        println!();
-        println!("The template defines the container images and scaling configuration.");
-        println!("While the internal ingress is HTTP only, you can run multiple container images in a single");
-        println!("ContainerApp. They can communicate via localhost and share disk and network resources.");
-        println!("The container image details can be found at https://aka.ms/containerapps/containers#configuration.");
-        println!("Scaling details can be found at https://aka.ms/containerapps/scaling.");
-        println!();
+        eprintln!();
+        eprintln!("The template defines the container images and scaling configuration.");
+        eprintln!("While the internal ingress is HTTP only, you can run multiple container images in a single");
+        eprintln!("ContainerApp. They can communicate via localhost and share disk and network resources.");
+        eprintln!("The container image details can be found at https://aka.ms/containerapps/containers#configuration.");
+        eprintln!("Scaling details can be found at https://aka.ms/containerapps/scaling.");
+        eprintln!();
     };
-    let template = Template {
-        containers: vec![get_container_from_service(service_name, service)?],
+    let mut template = Template {
+        containers: vec![get_container_from_service(
+            containerapps_configuration_data,
+            service_name,
+            service,
+        )?],
         revision_suffix: None,
         scale: ScaleConfiguration::default(),
     };
+    let suffix_template = match &containerapps_configuration_data.extension.revision_suffix {
+        Some(suffix) => Some(suffix.as_str()),
+        None => containerapps_configuration_data.revision_suffix,
+    };
+    if let Some(suffix_template) = suffix_template {
+        template.revision_suffix = Some(get_revision_suffix(
+            suffix_template,
+            service_name,
+            &template,
+        )?);
+    }
     Ok(template)
 }
 
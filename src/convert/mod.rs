@@ -1,16 +1,30 @@
+mod convert_to_compose;
 mod convert_to_containerapps;
 mod get_configuration_from_service;
 mod get_container_from_service;
+mod get_custom_domains_from_service;
+mod get_dapr_components_from_compose;
+mod get_extension_from_service;
+mod get_identity_from_service;
 mod get_ingress_from_service;
 mod get_properties_from_service;
+mod get_registries_from_service;
+mod get_revision_suffix;
 mod get_secrets_from_service;
 mod get_template_from_service;
 
+pub use convert_to_compose::*;
 pub use convert_to_containerapps::*;
 pub use get_configuration_from_service::*;
 pub use get_container_from_service::*;
+pub use get_custom_domains_from_service::*;
+pub use get_dapr_components_from_compose::*;
+pub use get_extension_from_service::*;
+pub use get_identity_from_service::*;
 pub use get_ingress_from_service::*;
 pub use get_properties_from_service::*;
+pub use get_registries_from_service::*;
+pub use get_revision_suffix::*;
 pub use get_secrets_from_service::*;
 pub use get_template_from_service::*;
 
@@ -46,6 +60,7 @@ pub mod tests {
             location: "northeurope",
             containerapps_environment_id: "/subscriptions/mysubscription/resourceGroups/myresourcegroup/providers/Microsoft.Web/kubeEnvironments/myenvironment",
             transport: Transport::default(),
+            ..Default::default()
         };
         let compose_config = get_service_from_docker_compose_file();
         convert_to_containerapps("mycontainerapp", compose_config, cacd).unwrap()
@@ -12,6 +12,7 @@ mod convert;
 use anyhow::Result;
 use cli::get_app_cli;
 use commands::*;
+use std::process::ExitCode;
 
 // Set the RUST_LOG environment variable to control the log output
 // Possible log options are (in order). Log levels are cumulative.
@@ -23,18 +24,39 @@ lazy_static! {
     pub static ref VERBOSE: bool = get_app_cli(&VERSION).get_matches().is_present("verbose");
 }
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     env_logger::init();
     let main_matches = get_app_cli(&VERSION).get_matches();
+    let mut exit_code = ExitCode::SUCCESS;
 
     if let Some(matches) = main_matches.subcommand_matches("convert") {
         ConvertComposeCommand::default()
             .with_compose_path(matches.value_of("INPUT").unwrap())
+            .with_project_directory(matches.value_of("ProjectDirectory"))
             .with_containerapps_path(matches.value_of("OUTPUT").unwrap())
             .with_resource_group(matches.value_of("ResourceGroup"))
             .with_location(matches.value_of("Location"))
             .with_containerapps_environment_id(matches.value_of("ContainerAppsEnvironmentId"))
-            .with_transport(matches.value_of("Transport"))
+            .with_containerapps_environment_name(matches.value_of("ContainerAppsEnvironmentName"))
+            .with_skip_azure(matches.is_present("SkipAzure"))
+            .with_transport(matches.value_of("Transport"))?
+            .with_revision_mode(matches.value_of("RevisionMode"))?
+            .with_revision_suffix(matches.value_of("RevisionSuffix"))
+            .with_traffic(matches.value_of("Traffic"))
+            .with_identities(matches.values_of("Identity").map(|v| v.collect()))
+            .with_registry_server(matches.value_of("RegistryServer"))
+            .with_registry_identity(matches.value_of("RegistryIdentity"))
+            .with_key_vault_secrets(matches.values_of("KeyVault").map(|v| v.collect()))
+            .with_domains(matches.values_of("Domain").map(|v| v.collect()))
+            .with_certificate(matches.value_of("Certificate"))
+            .with_certificate_password(matches.value_of("CertificatePassword"))
+            .with_out_dir(matches.value_of("OutDir"))
+            .with_name_template(matches.value_of("NameTemplate"))
+            .with_format(matches.value_of("Format"))?
+            .with_single_file(matches.is_present("SingleFile"))
+            .with_combined(matches.is_present("Combined"))
+            .with_api_version(matches.value_of("ApiVersion"))?
+            .with_template_parameters(matches.values_of("TemplateParameters").map(|v| v.collect()))
             .convert()?
             .write()?;
     };
@@ -48,24 +70,41 @@ fn main() -> Result<()> {
                 matches.value_of("ContainerAppsEnvironmentId"),
             )
             .with_location(matches.value_of("Location"))
+            .with_dry_run(matches.is_present("DryRun"))
             .validate_azure_login()?
             .retrieve_containerapps_environment()?
             .containerapps_environment_id()?;
 
         ConvertComposeCommand::default()
             .with_compose_path(matches.value_of("INPUT").unwrap())
+            .with_project_directory(matches.value_of("ProjectDirectory"))
             .with_containerapps_path(matches.value_of("OUTPUT").unwrap())
             .with_resource_group(matches.value_of("ResourceGroup"))
             .with_location(matches.value_of("Location"))
             .with_containerapps_environment_id(Some(&containerapps_environment_id))
-            .with_transport(matches.value_of("Transport"))
+            .with_transport(matches.value_of("Transport"))?
+            .with_revision_mode(matches.value_of("RevisionMode"))?
+            .with_revision_suffix(matches.value_of("RevisionSuffix"))
+            .with_traffic(matches.value_of("Traffic"))
+            .with_identities(matches.values_of("Identity").map(|v| v.collect()))
+            .with_registry_server(matches.value_of("RegistryServer"))
+            .with_registry_identity(matches.value_of("RegistryIdentity"))
+            .with_key_vault_secrets(matches.values_of("KeyVault").map(|v| v.collect()))
+            .with_domains(matches.values_of("Domain").map(|v| v.collect()))
+            .with_certificate(matches.value_of("Certificate"))
+            .with_certificate_password(matches.value_of("CertificatePassword"))
+            .with_out_dir(matches.value_of("OutDir"))
+            .with_name_template(matches.value_of("NameTemplate"))
+            .with_combined(matches.is_present("Combined"))
+            .with_api_version(matches.value_of("ApiVersion"))?
+            .with_template_parameters(matches.values_of("TemplateParameters").map(|v| v.collect()))
             .with_deploy_azure(true)
+            .with_dry_run(matches.is_present("DryRun"))
             .convert()?
             .get_configurations()
             .iter()
-            .map(|configuration| {
-                println!("Deployed: https://{}", &configuration.url.as_ref().unwrap())
-            })
+            .filter_map(|configuration| configuration.url.as_ref())
+            .map(|url| println!("Deployed: https://{}", url))
             .for_each(drop);
     }
 
@@ -82,5 +121,83 @@ fn main() -> Result<()> {
             .run()?;
     }
 
-    Ok(())
+    if let Some(matches) = main_matches.subcommand_matches("migrate") {
+        MigrateContainerAppsCommand::default()
+            .with_input_path(matches.value_of("INPUT").unwrap())
+            .with_output_path(matches.value_of("OUTPUT"))
+            .with_api_version(matches.value_of("ApiVersion"))?
+            .migrate()?
+            .write()?;
+    }
+
+    if let Some(matches) = main_matches.subcommand_matches("to-compose") {
+        ConvertContainerAppsCommand::default()
+            .with_containerapps_paths(matches.values_of("INPUT").unwrap().collect())
+            .with_compose_path(matches.value_of("OUTPUT").unwrap())
+            .convert()?
+            .write()?;
+    }
+
+    if let Some(matches) = main_matches.subcommand_matches("export") {
+        ExportContainerAppCommand::default()
+            .with_name(matches.value_of("ContainerAppName").unwrap())
+            .with_resource_group(matches.value_of("ResourceGroup"))
+            .with_output_path(matches.value_of("OUTPUT"))
+            .with_api_version(matches.value_of("ApiVersion"))?
+            .export()?
+            .write()?;
+    }
+
+    if let Some(matches) = main_matches.subcommand_matches("diff") {
+        let configurations = ConvertComposeCommand::default()
+            .with_compose_path(matches.value_of("INPUT").unwrap())
+            .with_project_directory(matches.value_of("ProjectDirectory"))
+            .with_containerapps_path("containerapps.yml")
+            .with_resource_group(matches.value_of("ResourceGroup"))
+            .with_location(matches.value_of("Location"))
+            .with_containerapps_environment_id(matches.value_of("ContainerAppsEnvironmentId"))
+            .with_transport(matches.value_of("Transport"))?
+            .with_revision_mode(matches.value_of("RevisionMode"))?
+            .with_identities(matches.values_of("Identity").map(|v| v.collect()))
+            .with_registry_server(matches.value_of("RegistryServer"))
+            .with_registry_identity(matches.value_of("RegistryIdentity"))
+            .with_key_vault_secrets(matches.values_of("KeyVault").map(|v| v.collect()))
+            .with_api_version(matches.value_of("ApiVersion"))?
+            .convert()?
+            .get_configurations();
+
+        exit_code = DiffContainerAppsCommand::default()
+            .with_configurations(configurations)
+            .diff()?
+            .result()
+            .into();
+    }
+
+    if let Some(matches) = main_matches.subcommand_matches("generate-workflow") {
+        GenerateWorkflowCommand::default()
+            .with_compose_path(matches.value_of("INPUT").unwrap())
+            .with_workflow_path(matches.value_of("OUTPUT"))
+            .with_pipeline(matches.value_of("Pipeline"))?
+            .with_branch(matches.value_of("Branch"))
+            .with_resource_group(matches.value_of("ResourceGroup"))
+            .with_location(matches.value_of("Location"))
+            .with_containerapps_environment_id(matches.value_of("ContainerAppsEnvironmentId"))
+            .with_transport(matches.value_of("Transport"))
+            .with_revision_mode(matches.value_of("RevisionMode"))
+            .with_revision_suffix(matches.value_of("RevisionSuffix"))
+            .with_traffic(matches.value_of("Traffic"))
+            .with_identities(matches.values_of("Identity").map(|v| v.collect()))
+            .with_registry_server(matches.value_of("RegistryServer"))
+            .with_registry_identity(matches.value_of("RegistryIdentity"))
+            .with_key_vault_secrets(matches.values_of("KeyVault").map(|v| v.collect()))
+            .with_domains(matches.values_of("Domain").map(|v| v.collect()))
+            .with_certificate(matches.value_of("Certificate"))
+            .with_combined(matches.is_present("Combined"))
+            .with_template_parameters(matches.values_of("TemplateParameters").map(|v| v.collect()))
+            .with_api_version(matches.value_of("ApiVersion"))
+            .generate()?
+            .write()?;
+    }
+
+    Ok(exit_code)
 }